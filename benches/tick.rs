@@ -0,0 +1,142 @@
+//! Benchmarks for the work done on every tick: collecting readings and
+//! turning them into markup. The bar runs all day, often on battery, so these
+//! are what a slowdown would cost most in.
+//!
+//! Run with `cargo bench`, or `cargo bench -- markup` for one group.
+
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use byte_unit::Byte;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use status_bar::battery::{BatteryInfo, BatterySample, ChargeStatus, Charger};
+use status_bar::collectors::battery::BatteryCollector;
+use status_bar::collectors::memory::{MemoryCollector, MemoryInfo};
+use status_bar::collectors::Collector;
+use status_bar::markup;
+use status_bar::presets::{self, Preset};
+use status_bar::render::{Render, RenderContext};
+use status_bar::template::Template;
+
+/// A battery directory laid out like `/sys/class/power_supply/BAT0`, with
+/// the state directory moved next to it so the collector's history doesn't
+/// touch the real one
+fn fake_battery() -> PathBuf {
+    let root = env::temp_dir().join(format!("status_bar-bench-{}", std::process::id()));
+    env::set_var("XDG_STATE_HOME", root.join("state"));
+
+    let battery = root.join("BAT0");
+    fs::create_dir_all(&battery).unwrap();
+    for (name, contents) in [
+        ("current_now", "1534000"),
+        ("charge_now", "3912000"),
+        ("charge_full_design", "5800000"),
+        ("capacity", "74"),
+        ("status", "Discharging"),
+    ] {
+        fs::write(battery.join(name), format!("{contents}\n")).unwrap();
+    }
+    battery
+}
+
+fn battery_info() -> BatteryInfo {
+    BatteryInfo {
+        status: ChargeStatus::Charging,
+        capacity: 74,
+        time_till_empty: Duration::from_secs(2 * 60 * 60 + 17 * 60),
+        history: (0..240)
+            .map(|i| BatterySample {
+                time: 1_700_000_000 + i * 30,
+                capacity: 80 - i as u64 / 40,
+            })
+            .collect(),
+        charge_limit: Some(80),
+        charger: Some(Charger {
+            watts: 65.0,
+            usb_type: Some("PD".to_string()),
+        }),
+    }
+}
+
+fn memory_info() -> MemoryInfo {
+    MemoryInfo {
+        used_memory: Byte::from_u64(9_876_543_210),
+        total_memory: Byte::from_u64(16_706_453_504),
+        used_swap: Byte::from_u64(123_456_789),
+        total_swap: Byte::from_u64(8_589_934_592),
+        pressure: None,
+    }
+}
+
+fn collectors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect");
+
+    let mut battery = BatteryCollector::new(&fake_battery());
+    group.bench_function("battery", |b| b.iter(|| black_box(battery.collect())));
+
+    let mut memory = MemoryCollector::new();
+    group.bench_function("memory", |b| b.iter(|| black_box(memory.collect())));
+
+    group.finish();
+}
+
+fn renders(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    let context = RenderContext::current();
+
+    for preset in [Preset::Minimal, Preset::Verbose, Preset::NerdFont] {
+        let render = presets::battery(preset);
+        group.bench_function(format!("battery/{preset:?}"), |b| {
+            b.iter_batched(
+                battery_info,
+                |info| black_box(render.render(info, &context)),
+                BatchSize::SmallInput,
+            )
+        });
+
+        let render = presets::memory(preset);
+        group.bench_function(format!("memory/{preset:?}"), |b| {
+            b.iter_batched(
+                memory_info,
+                |info| black_box(render.render(info, &context)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    let template =
+        Template::parse("[{icon} {capacity|color(<20)}%{, time_left}{ @ charger}]").unwrap();
+    group.bench_function("battery/template", |b| {
+        b.iter_batched(
+            battery_info,
+            |info| black_box(template.render(info, &context)),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn markup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("markup");
+    let title = "Simon & Garfunkel - The Sound of Silence <Live> (Remastered)";
+    let styled = format!(
+        "<span foreground=\"#ff0000\"><b>{}</b></span> &amp; more",
+        markup::escape(title)
+    );
+
+    group.bench_function("escape", |b| b.iter(|| markup::escape(black_box(title))));
+    group.bench_function("strip", |b| b.iter(|| markup::strip(black_box(&styled))));
+    group.bench_function("template/parse", |b| {
+        b.iter(|| Template::parse(black_box("[{icon} {capacity|color(<20)}%{, time_left}]")))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, collectors, renders, markup);
+criterion_main!(benches);
@@ -0,0 +1,155 @@
+//! Actions run automatically when a reading crosses a threshold, such as
+//! suspending before the battery runs out. In the config they are written as
+//! `"suspend"`, `"hibernate"`, `{ dim = 30 }`, `{ command = ["prog", "arg"] }`
+//! or `{ notify = { summary = "...", body = "..." } }`.
+//!
+//! Built without the `dbus` feature, notifications go through `notify-send`,
+//! suspending through `systemctl`, and dimming writes to sysfs directly,
+//! which needs write access to the backlight.
+
+#[cfg(feature = "dbus")]
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+use std::thread;
+
+#[cfg(not(feature = "dbus"))]
+use anyhow::bail;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+#[cfg(feature = "dbus")]
+use zbus::blocking::Connection;
+#[cfg(feature = "dbus")]
+use zbus::zvariant;
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Runs a program, the first element, with the remaining elements as its
+    /// arguments
+    Command(Vec<String>),
+    /// Shows a desktop notification
+    Notify { summary: String, body: String },
+    /// Suspends to RAM through logind
+    Suspend,
+    /// Hibernates through logind
+    Hibernate,
+    /// Sets the backlight to the given percentage of its maximum brightness
+    Dim(u8),
+}
+
+impl Action {
+    /// Runs the action on a background thread, reporting failures on stderr
+    pub fn spawn(&self) {
+        let action = self.clone();
+        thread::spawn(move || {
+            if let Err(e) = action.run() {
+                eprintln!("Could not run {action:?}: {e}");
+            }
+        });
+    }
+
+    pub fn run(&self) -> Result<()> {
+        match self {
+            Action::Command(command) => {
+                let (program, args) = command
+                    .split_first()
+                    .ok_or_else(|| anyhow!("empty command"))?;
+                let mut child = Command::new(program).args(args).spawn()?;
+                // Waited on so it doesn't linger as a zombie once it exits
+                thread::spawn(move || child.wait());
+            }
+            Action::Notify { summary, body } => notify(summary, body)?,
+            Action::Suspend => login_manager("Suspend")?,
+            Action::Hibernate => login_manager("Hibernate")?,
+            Action::Dim(percentage) => dim(*percentage)?,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "dbus")]
+fn notify(summary: &str, body: &str) -> Result<()> {
+    Connection::session()?.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "status_bar",
+            0u32,
+            "",
+            summary,
+            body,
+            Vec::<&str>::new(),
+            HashMap::<&str, zvariant::Value>::new(),
+            -1i32,
+        ),
+    )?;
+    Ok(())
+}
+
+#[cfg(not(feature = "dbus"))]
+fn notify(summary: &str, body: &str) -> Result<()> {
+    run(Command::new("notify-send").args(["--app-name=status_bar", summary, body]))
+}
+
+/// Runs `command` to completion, failing if it does
+#[cfg(not(feature = "dbus"))]
+fn run(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        bail!("{command:?} exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "dbus")]
+fn login_manager(method: &str) -> Result<()> {
+    // `false` as the bar has no way to ask for authorisation
+    Connection::system()?.call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1",
+        Some("org.freedesktop.login1.Manager"),
+        method,
+        &(false,),
+    )?;
+    Ok(())
+}
+
+#[cfg(not(feature = "dbus"))]
+fn login_manager(method: &str) -> Result<()> {
+    run(Command::new("systemctl").arg(method.to_lowercase()))
+}
+
+/// Sets the first backlight to `percentage` of its maximum, through logind so
+/// that no write access to sysfs is needed
+fn dim(percentage: u8) -> Result<()> {
+    let backlight = fs::read_dir(BACKLIGHT_DIR)?
+        .flatten()
+        .next()
+        .ok_or_else(|| anyhow!("no backlight in {BACKLIGHT_DIR}"))?;
+    let max: u32 = fs::read_to_string(backlight.path().join("max_brightness"))?
+        .trim()
+        .parse()?;
+    let brightness = max * u32::from(percentage.min(100)) / 100;
+
+    #[cfg(feature = "dbus")]
+    Connection::system()?.call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1/session/auto",
+        Some("org.freedesktop.login1.Session"),
+        "SetBrightness",
+        &(
+            "backlight",
+            backlight.file_name().to_string_lossy().as_ref(),
+            brightness,
+        ),
+    )?;
+    #[cfg(not(feature = "dbus"))]
+    fs::write(backlight.path().join("brightness"), brightness.to_string())?;
+    Ok(())
+}
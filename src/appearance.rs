@@ -0,0 +1,406 @@
+//! Switching between a light and a dark [`Theme`] automatically, following
+//! the desktop's colour scheme preference, an ambient light sensor or the
+//! time of day.
+//!
+//! The preference is the `color-scheme` setting of the XDG desktop portal,
+//! which desktop environments and tools such as darkman set. The sensor is
+//! the first IIO device reporting illuminance. The time of day is either
+//! fixed times or sunrise and sunset at a location.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(not(feature = "dbus"))]
+use anyhow::bail;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+#[cfg(feature = "dbus")]
+use zbus::blocking::{Connection, Proxy};
+#[cfg(feature = "dbus")]
+use zbus::zvariant::OwnedValue;
+
+use crate::render::{self, Theme};
+use crate::state;
+
+const IIO_DIR: &str = "/sys/bus/iio/devices";
+
+/// How often the light sensor is read
+const SENSOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Ambient light, in lux, below which the dark theme is used unless
+/// configured otherwise
+pub const DEFAULT_THRESHOLD: f64 = 50.0;
+
+/// The light theme only comes back once the light is this many times
+/// brighter than the threshold, so a shadow passing over the sensor doesn't
+/// flip the theme back and forth
+const HYSTERESIS: f64 = 1.5;
+
+/// How often the schedule is checked
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Altitude of the sun's centre at sunrise and sunset, in degrees, allowing
+/// for refraction and the size of its disc
+const SUNRISE_ALTITUDE: f64 = -0.833;
+
+/// Axial tilt of the Earth, in degrees
+const OBLIQUITY: f64 = 23.4397;
+
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2_451_545.0;
+
+/// Julian date of the Unix epoch
+const UNIX_EPOCH: f64 = 2_440_587.5;
+
+/// `color-scheme` value for a dark preference. Others are light or no
+/// preference
+#[cfg(feature = "dbus")]
+const PREFER_DARK: u32 = 1;
+
+/// What decides between the light and dark themes, as named in the config
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AppearanceSource {
+    /// The colour scheme preference of the XDG desktop portal
+    Portal,
+    /// An ambient light sensor
+    LightSensor,
+    /// The time of day
+    Schedule,
+}
+
+/// What decides between the light and dark themes, with its settings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    Portal,
+    /// Dark below `threshold` lux
+    LightSensor {
+        threshold: f64,
+    },
+    Schedule(Schedule),
+}
+
+/// When the light theme is used
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    /// From `day` until `night`, local time
+    Fixed { day: NaiveTime, night: NaiveTime },
+    /// From sunrise until sunset at a location, in degrees north and east
+    Sun { latitude: f64, longitude: f64 },
+}
+
+impl Schedule {
+    fn appearance_at(self, now: DateTime<Local>) -> Appearance {
+        let light = match self {
+            Schedule::Fixed { day, night } => {
+                let time = now.time();
+                if day <= night {
+                    day <= time && time < night
+                } else {
+                    // A day that runs past midnight
+                    time >= day || time < night
+                }
+            }
+            Schedule::Sun {
+                latitude,
+                longitude,
+            } => match sun_times(now.date_naive(), latitude, longitude) {
+                SunTimes::Rises(sunrise, sunset) => {
+                    let now = now.with_timezone(&Utc);
+                    sunrise <= now && now < sunset
+                }
+                SunTimes::AlwaysUp => true,
+                SunTimes::AlwaysDown => false,
+            },
+        };
+
+        if light {
+            Appearance::Light
+        } else {
+            Appearance::Dark
+        }
+    }
+}
+
+enum SunTimes {
+    /// Sunrise and sunset
+    Rises(DateTime<Utc>, DateTime<Utc>),
+    /// Midnight sun
+    AlwaysUp,
+    /// Polar night
+    AlwaysDown,
+}
+
+/// Sunrise and sunset on `date` at a location, with the sunrise equation,
+/// which is accurate to a minute or two away from the poles
+fn sun_times(date: NaiveDate, latitude: f64, longitude: f64) -> SunTimes {
+    let epoch = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap_or_default();
+    let days = (date - epoch).num_days() as f64;
+
+    // Mean solar time at the location, in days since J2000
+    let mean_time = days - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_time).rem_euclid(360.0);
+    let anomaly_rad = anomaly.to_radians();
+    let centre = 1.9148 * anomaly_rad.sin()
+        + 0.0200 * (2.0 * anomaly_rad).sin()
+        + 0.0003 * (3.0 * anomaly_rad).sin();
+    let ecliptic_longitude = (anomaly + centre + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_time + 0.0053 * anomaly_rad.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = (SUNRISE_ALTITUDE.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+
+    if cos_hour_angle < -1.0 {
+        return SunTimes::AlwaysUp;
+    }
+    if cos_hour_angle > 1.0 {
+        return SunTimes::AlwaysDown;
+    }
+
+    let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+    let time = |julian: f64| {
+        let seconds = ((julian - UNIX_EPOCH) * 86_400.0) as i64;
+        Utc.timestamp_opt(seconds, 0).single().unwrap_or_default()
+    };
+    SunTimes::Rises(time(transit - half_day), time(transit + half_day))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Appearance {
+    Light,
+    Dark,
+}
+
+/// The theme for each [`Appearance`]
+struct Themes {
+    light: Theme,
+    dark: Theme,
+}
+
+impl Themes {
+    /// Switches to the theme for `appearance` and redraws every widget with it
+    fn apply(&self, appearance: Appearance) {
+        let theme = match appearance {
+            Appearance::Light => &self.light,
+            Appearance::Dark => &self.dark,
+        };
+        render::set_theme(theme.clone());
+        state::bar().request_refresh();
+    }
+}
+
+/// Switches between `light` and `dark` from now on, following `trigger`
+pub fn follow(trigger: Trigger, light: Theme, dark: Theme) -> Result<()> {
+    let themes = Themes { light, dark };
+    match trigger {
+        Trigger::Portal => follow_portal(themes),
+        Trigger::LightSensor { threshold } => follow_sensor(themes, threshold),
+        Trigger::Schedule(schedule) => {
+            follow_schedule(themes, schedule);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "dbus")]
+fn color_scheme(value: &OwnedValue) -> Option<Appearance> {
+    let scheme = u32::try_from(value).ok()?;
+    Some(if scheme == PREFER_DARK {
+        Appearance::Dark
+    } else {
+        Appearance::Light
+    })
+}
+
+#[cfg(not(feature = "dbus"))]
+fn follow_portal(_themes: Themes) -> Result<()> {
+    bail!("following the desktop's preference needs the dbus feature")
+}
+
+#[cfg(feature = "dbus")]
+fn follow_portal(themes: Themes) -> Result<()> {
+    let connection = Connection::session()?;
+    let settings = Proxy::new(
+        &connection,
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Settings",
+    )?;
+
+    let scheme: OwnedValue =
+        settings.call("ReadOne", &("org.freedesktop.appearance", "color-scheme"))?;
+    if let Some(appearance) = color_scheme(&scheme) {
+        themes.apply(appearance);
+    }
+
+    let signals = settings.receive_signal("SettingChanged")?;
+    thread::spawn(move || {
+        // Keep the connection alive for as long as signals are received
+        let _connection = connection;
+
+        for signal in signals {
+            let Ok((namespace, key, value)) =
+                signal.body().deserialize::<(String, String, OwnedValue)>()
+            else {
+                continue;
+            };
+            if namespace != "org.freedesktop.appearance" || key != "color-scheme" {
+                continue;
+            }
+            if let Some(appearance) = color_scheme(&value) {
+                themes.apply(appearance);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Directory of the first IIO device with an illuminance channel
+fn light_sensor() -> Result<PathBuf> {
+    fs::read_dir(IIO_DIR)?
+        .flatten()
+        .map(|device| device.path())
+        .find(|device| {
+            device.join("in_illuminance_input").exists()
+                || device.join("in_illuminance_raw").exists()
+        })
+        .ok_or_else(|| anyhow!("no ambient light sensor in {IIO_DIR}"))
+}
+
+/// Ambient light in lux. Sensors either report it directly or as a raw
+/// reading and a scale
+fn read_lux(sensor: &Path) -> Result<f64> {
+    let read =
+        |name: &str| -> Result<f64> { Ok(fs::read_to_string(sensor.join(name))?.trim().parse()?) };
+
+    if let Ok(lux) = read("in_illuminance_input") {
+        return Ok(lux);
+    }
+    let scale = read("in_illuminance_scale").unwrap_or(1.0);
+    Ok(read("in_illuminance_raw")? * scale)
+}
+
+fn follow_sensor(themes: Themes, threshold: f64) -> Result<()> {
+    let sensor = light_sensor()?;
+    read_lux(&sensor)?;
+
+    thread::spawn(move || {
+        let mut current = None;
+        loop {
+            if let Ok(lux) = read_lux(&sensor) {
+                let appearance = match current {
+                    Some(Appearance::Dark) if lux < threshold * HYSTERESIS => Appearance::Dark,
+                    _ if lux < threshold => Appearance::Dark,
+                    _ => Appearance::Light,
+                };
+                if current != Some(appearance) {
+                    themes.apply(appearance);
+                    current = Some(appearance);
+                }
+            }
+            thread::sleep(SENSOR_INTERVAL);
+        }
+    });
+
+    Ok(())
+}
+
+fn follow_schedule(themes: Themes, schedule: Schedule) {
+    thread::spawn(move || {
+        let mut current = None;
+        loop {
+            let appearance = schedule.appearance_at(Local::now());
+            if current != Some(appearance) {
+                themes.apply(appearance);
+                current = Some(appearance);
+            }
+            thread::sleep(SCHEDULE_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts `actual` is within two minutes of `expected`, given as UTC
+    /// `YYYY-MM-DD HH:MM`
+    fn assert_near(actual: DateTime<Utc>, expected: &str) {
+        let expected = chrono::NaiveDateTime::parse_from_str(expected, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc();
+        let difference = (actual - expected).num_seconds().abs();
+        assert!(difference <= 120, "{actual} is not {expected}");
+    }
+
+    fn rises(
+        date: (i32, u32, u32),
+        latitude: f64,
+        longitude: f64,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let date = NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap();
+        match sun_times(date, latitude, longitude) {
+            SunTimes::Rises(sunrise, sunset) => (sunrise, sunset),
+            SunTimes::AlwaysUp => panic!("sun never sets"),
+            SunTimes::AlwaysDown => panic!("sun never rises"),
+        }
+    }
+
+    #[test]
+    fn london_summer_solstice() {
+        let (sunrise, sunset) = rises((2024, 6, 21), 51.5074, -0.1278);
+        assert_near(sunrise, "2024-06-21 03:43");
+        assert_near(sunset, "2024-06-21 20:21");
+    }
+
+    #[test]
+    fn new_york_winter_solstice() {
+        let (sunrise, sunset) = rises((2024, 12, 21), 40.7128, -74.0060);
+        assert_near(sunrise, "2024-12-21 12:16");
+        assert_near(sunset, "2024-12-21 21:32");
+    }
+
+    #[test]
+    fn sydney_sunrise_is_the_previous_utc_day() {
+        let (sunrise, sunset) = rises((2024, 1, 1), -33.8688, 151.2093);
+        assert_near(sunrise, "2023-12-31 18:47");
+        assert_near(sunset, "2024-01-01 09:09");
+    }
+
+    #[test]
+    fn equator_equinox_is_twelve_hours() {
+        let (sunrise, sunset) = rises((2024, 3, 20), 0.0, 0.0);
+        assert_near(sunrise, "2024-03-20 06:04");
+        assert_near(sunset, "2024-03-20 18:11");
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        let tromso = (69.6492, 18.9553);
+        let midsummer = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let midwinter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+
+        assert!(matches!(
+            sun_times(midsummer, tromso.0, tromso.1),
+            SunTimes::AlwaysUp
+        ));
+        assert!(matches!(
+            sun_times(midwinter, tromso.0, tromso.1),
+            SunTimes::AlwaysDown
+        ));
+        // The seasons are the other way round in the south
+        assert!(matches!(
+            sun_times(midsummer, -77.85, 166.67),
+            SunTimes::AlwaysDown
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+//! Workspaces holding windows that demand attention, such as a chat window
+//! with a new message, so they can be spotted from any workspace.
+//!
+//! `attention focus` focuses the oldest such window, e.g. from a key binding.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+use x11rb::protocol::xproto::Window;
+
+use crate::ewmh::Ewmh;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::urgent::Urgency;
+use crate::{ipc, markup, render, state};
+
+// Abstracted type to represent the render closure
+type AttentionRender = Box<dyn Render<AttentionInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttentionInfo {
+    /// Names of the workspaces holding windows that demand attention, in
+    /// workspace order. Workspaces without a name are numbered from 1
+    pub workspaces: Vec<String>,
+}
+
+/// Windows demanding attention from oldest to newest, with the index of the
+/// desktop they are on
+fn attention_windows(ewmh: &Ewmh) -> Result<Vec<(Window, Option<u32>)>> {
+    let mut windows = vec![];
+    for window in ewmh.windows()? {
+        // Errors here are windows closed since the list was read
+        if let Ok(true) = ewmh.demands_attention(window) {
+            windows.push((window, ewmh.desktop(window).unwrap_or_default()));
+        }
+    }
+    Ok(windows)
+}
+
+fn read(ewmh: &Ewmh) -> Result<AttentionInfo> {
+    let names = ewmh.desktop_names()?;
+
+    let mut desktops: Vec<u32> = attention_windows(ewmh)?
+        .into_iter()
+        .filter_map(|(_, desktop)| desktop)
+        .collect();
+    desktops.sort_unstable();
+    desktops.dedup();
+
+    let workspaces = desktops
+        .into_iter()
+        .map(|desktop| {
+            names
+                .get(desktop as usize)
+                .filter(|name| !name.is_empty())
+                .cloned()
+                .unwrap_or_else(|| (desktop + 1).to_string())
+        })
+        .collect();
+
+    Ok(AttentionInfo { workspaces })
+}
+
+/// cnx widget that names the workspaces holding windows that demand
+/// attention, hidden while there are none, and registers the `attention` IPC
+/// command
+pub struct Attention {
+    attrs: Attributes,
+    render: Option<AttentionRender>,
+    update_interval: Duration,
+    urgency: Option<Urgency>,
+}
+
+impl Attention {
+    /// Creates a new [`Attention`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<AttentionRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the windows are read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<AttentionRender>,
+        update_interval: Duration,
+    ) -> Attention {
+        Attention {
+            attrs,
+            render,
+            update_interval,
+            urgency: None,
+        }
+    }
+
+    /// Sets `urgency` while any window demands attention, e.g. to make the
+    /// widget blink with [`crate::urgent::Blink`]
+    #[must_use]
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = Some(urgency);
+        self
+    }
+}
+
+fn default_render(attention_info: &AttentionInfo) -> String {
+    if attention_info.workspaces.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        "<span foreground=\"{}\">! {}</span>",
+        render::theme().critical.to_hex(),
+        markup::escape(&attention_info.workspaces.join(" "))
+    )
+}
+
+impl Widget for Attention {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Attention {
+            attrs,
+            render,
+            update_interval,
+            urgency,
+        } = *self;
+
+        ipc::register("attention", |args| match args {
+            ["focus"] => {
+                let ewmh = Ewmh::connect()?;
+                let (window, _) = attention_windows(&ewmh)?
+                    .first()
+                    .copied()
+                    .ok_or_else(|| anyhow!("no window demands attention"))?;
+                ewmh.activate(window)?;
+                state::bar().request_refresh();
+                Ok(String::new())
+            }
+            _ => bail!("usage: attention focus"),
+        });
+
+        // Connects on first use, and again after an error
+        let mut ewmh = None;
+        let collect = move || -> Result<AttentionInfo> {
+            let connection = match ewmh.take() {
+                Some(connection) => connection,
+                None => Ewmh::connect()?,
+            };
+            let attention_info = read(&connection)?;
+            ewmh = Some(connection);
+
+            if let Some(urgency) = &urgency {
+                urgency.set(!attention_info.workspaces.is_empty());
+            }
+            Ok(attention_info)
+        };
+
+        let show = move |attention_info: AttentionInfo| match &render {
+            Some(render) => render.render(attention_info, &RenderContext::current()),
+            None => default_render(&attention_info),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, collect, show).with_export("attention");
+        Box::new(widget).into_stream()
+    }
+}
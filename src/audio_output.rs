@@ -0,0 +1,204 @@
+//! Audio output picker through `pactl`, which works with both PulseAudio and
+//! PipeWire.
+//!
+//! `audio list` prints each sink and port, marking the active ones with `*`,
+//! and `audio set <sink> [port]` makes a sink the default output, optionally
+//! switching its port, e.g. from speakers to headphones.
+
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use crate::render::{Render, RenderContext};
+use crate::{ipc, markup, state};
+
+// Abstracted type to represent the render closure
+type AudioOutputRender = Box<dyn Render<AudioOutputInfo>>;
+
+#[derive(Clone, Debug)]
+pub struct Port {
+    pub name: String,
+    pub description: String,
+    /// Whether something is plugged into the port, where that is known
+    pub available: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sink {
+    pub name: String,
+    pub description: String,
+    pub ports: Vec<Port>,
+    pub active_port: Option<String>,
+    pub default: bool,
+}
+
+pub struct AudioOutputInfo {
+    pub sinks: Vec<Sink>,
+}
+
+impl AudioOutputInfo {
+    /// The sink audio is currently played through
+    #[must_use]
+    pub fn default_sink(&self) -> Option<&Sink> {
+        self.sinks.iter().find(|sink| sink.default)
+    }
+}
+
+/// cnx widget that shows the default audio output and registers the `audio`
+/// IPC command for switching it
+pub struct AudioOutput {
+    attrs: Attributes,
+    render: Option<AudioOutputRender>,
+    update_interval: Duration,
+}
+
+impl AudioOutput {
+    /// Creates a new [`AudioOutput`] widget and registers the `audio` IPC
+    /// command
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<AudioOutputRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the outputs are read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<AudioOutputRender>,
+        update_interval: Duration,
+    ) -> AudioOutput {
+        ipc::register("audio", |args| match args {
+            ["list"] => {
+                let mut lines = vec![];
+                for sink in sinks()? {
+                    let marker = if sink.default { "* " } else { "  " };
+                    lines.push(format!("{marker}{} ({})", sink.description, sink.name));
+                    for port in &sink.ports {
+                        let marker = if sink.active_port.as_ref() == Some(&port.name) {
+                            "* "
+                        } else {
+                            "  "
+                        };
+                        lines.push(format!("    {marker}{} ({})", port.description, port.name));
+                    }
+                }
+                Ok(lines.join("\n"))
+            }
+            ["set", sink] => {
+                pactl(&["set-default-sink", sink])?;
+                state::bar().request_refresh();
+                Ok(String::new())
+            }
+            ["set", sink, port] => {
+                pactl(&["set-sink-port", sink, port])?;
+                pactl(&["set-default-sink", sink])?;
+                state::bar().request_refresh();
+                Ok(String::new())
+            }
+            _ => bail!("usage: audio list|set <sink> [port]"),
+        });
+
+        AudioOutput {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let audio_info = AudioOutputInfo {
+            sinks: sinks().unwrap_or_default(),
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(audio_info, &RenderContext::current())
+        } else {
+            match audio_info.default_sink() {
+                Some(sink) => {
+                    let port = sink
+                        .active_port
+                        .as_ref()
+                        .and_then(|active| sink.ports.iter().find(|port| &port.name == active));
+                    match port {
+                        // Sinks with several ports are told apart by port
+                        Some(port) if sink.ports.len() > 1 => markup::escape(&port.description),
+                        _ => markup::escape(&sink.description),
+                    }
+                }
+                None => "no output".to_string(),
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for AudioOutput {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Runs `pactl` with `args` and returns its output
+pub(crate) fn pactl(args: &[&str]) -> Result<String> {
+    let output = Command::new("pactl").args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "pactl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Every sink known to the sound server. Needs `pactl` 16 or later for
+/// JSON output
+pub fn sinks() -> Result<Vec<Sink>> {
+    let default = pactl(&["get-default-sink"])?.trim().to_string();
+    let json: Value = serde_json::from_str(&pactl(&["--format=json", "list", "sinks"])?)?;
+
+    let string = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+    Ok(json
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|sink| Sink {
+            name: string(&sink["name"]),
+            description: string(&sink["description"]),
+            ports: sink["ports"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|port| Port {
+                    name: string(&port["name"]),
+                    description: string(&port["description"]),
+                    available: port["availability"].as_str() != Some("not available"),
+                })
+                .collect(),
+            active_port: sink["active_port"].as_str().map(str::to_string),
+            default: sink["name"].as_str() == Some(default.as_str()),
+        })
+        .collect())
+}
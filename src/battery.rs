@@ -1,40 +1,133 @@
-use std::{fs::File, io::Read, path::Path, time::Duration};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::bail;
+use cnx::{text::Attributes, widgets::Widget};
 
-use cnx::{
-    text::{Attributes, Text},
-    widgets::Widget,
+use crate::actions::Action;
+use crate::collectors::battery::{
+    charge_limit, on_ac_power, set_charge_limit, BatteryCollector, FULL,
+};
+pub use crate::collectors::battery::{
+    BatteryInfo, BatterySample, CapacityBasis, ChargeStatus, Charger,
 };
-use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
+use crate::collectors::Collector;
+use crate::polling::PollingWidget;
+use crate::progress::ProgressBar;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{color, icons, ipc, state};
+
+/// Percentage the battery has to charge back above a threshold before its
+/// action can run again, so readings wavering around it don't repeat it
+const REARM_MARGIN: u64 = 5;
+
+/// Drawn by the `bar` template field
+const BAR: ProgressBar = ProgressBar::blocks(8);
 
 pub struct Battery {
     attrs: Attributes,
-    render: Option<Box<dyn Fn(BatteryInfo) -> String>>,
+    render: Option<Box<dyn Render<BatteryInfo>>>,
     update_interval: Duration,
-    battery_path: String,
+    collector: BatteryCollector,
+    full_display: FullDisplay,
+    smoothing: Smoothing,
+    alarms: Vec<Alarm>,
+    battery_path: PathBuf,
+    conservation_limit: Option<u64>,
+}
+
+/// An [`Action`] run when the battery discharges to `threshold`
+struct Alarm {
+    threshold: u64,
+    action: Action,
+    armed: bool,
+}
+
+impl Alarm {
+    fn check(&mut self, battery: &BatteryInfo) {
+        if self.armed
+            && matches!(battery.status, ChargeStatus::Discharging)
+            && battery.capacity <= self.threshold
+        {
+            self.armed = false;
+            self.action.spawn();
+        } else if battery.capacity >= self.threshold + REARM_MARGIN {
+            self.armed = true;
+        }
+    }
+}
+
+/// Applies a [`Smoothing`] to successive capacity readings
+struct CapacitySmoother {
+    smoothing: Smoothing,
+    recent_capacity: VecDeque<u64>,
+    displayed_capacity: Option<u64>,
+}
+
+/// How the battery widget is shown while the battery is full and on AC power
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FullDisplay {
+    /// Rendered as usual
+    Normal,
+    /// Collapsed to the charging icon
+    IconOnly,
+    /// Not shown at all
+    Hidden,
 }
 
-/// Battery statuses as written in `power_supply.h`
-#[derive(Debug)]
-pub enum ChargeStatus {
-    Unknown,
-    Charging,
-    Discharging,
-    NotCharging,
-    Full,
+/// How capacity readings are smoothed before being displayed, for firmware
+/// which reports values flickering between adjacent percentages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Smoothing {
+    /// Readings are shown as reported
+    None,
+    /// Shows the mean of the last `n` readings
+    MovingAverage(usize),
+    /// Only follows readings in the direction the battery is charging or
+    /// discharging, unless they move at least `n` percent the other way
+    Hysteresis(u64),
 }
 
-pub struct BatteryInfo {
-    pub status: ChargeStatus,
-    pub capacity: u64,
-    pub time_till_empty: Duration,
+impl Fields for BatteryInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(match self.status {
+                ChargeStatus::Charging => context.icons.charging,
+                _ => context.icons.battery,
+            }))),
+            "capacity" => Some(Value::Number(self.capacity as f64)),
+            "bar" => Some(Value::Markup(format!(
+                "<span foreground=\"{}\">{}</span>",
+                color::gradient(100.0 - self.capacity as f64).to_hex(),
+                BAR.render(self.capacity as f64)
+            ))),
+            "conservation" if self.conserving() => {
+                Some(Value::Markup(context.icon(context.icons.conservation)))
+            }
+            "status" => Some(Value::Text(format!("{:?}", self.status))),
+            "charger" => Some(Value::Text(format!("{:.0}W", self.charger.as_ref()?.watts))),
+            "charger_type" => Some(Value::Text(self.charger.as_ref()?.usb_type.clone()?)),
+            "time_left" if !self.time_till_empty.is_zero() => {
+                let minutes = self.time_till_empty.as_secs() / 60;
+                Some(Value::Text(format!(
+                    "{}h{:02}m",
+                    minutes / 60,
+                    minutes % 60
+                )))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Battery {
     pub fn new(
         attrs: Attributes,
-        render: Option<Box<dyn Fn(BatteryInfo) -> String>>,
+        render: Option<Box<dyn Render<BatteryInfo>>>,
         update_interval: Duration,
         battery_path: String,
     ) -> Self {
@@ -42,85 +135,197 @@ impl Battery {
             attrs,
             render,
             update_interval,
-            battery_path,
+            collector: BatteryCollector::new(Path::new(&battery_path)),
+            full_display: FullDisplay::Normal,
+            smoothing: Smoothing::None,
+            alarms: Vec::new(),
+            battery_path: PathBuf::from(battery_path),
+            conservation_limit: None,
         }
     }
 
-    pub fn tick(&self) -> Vec<Text> {
-        let battery_location = Path::new(&self.battery_path);
-        let mut current_now =
-            File::open(battery_location.join("current_now")).expect("Could not open current_now");
-        let mut charge_now =
-            File::open(battery_location.join("charge_now")).expect("Could not open charge_full");
-        let mut capacity =
-            File::open(battery_location.join("capacity")).expect("Could not open capacity");
-        let mut status =
-            File::open(battery_location.join("status")).expect("Could not open status");
-
-        let mut temp_str = String::new();
-
-        let _ = current_now.read_to_string(&mut temp_str);
-        let current_micro_amps: u64 = temp_str
-            .trim()
-            .parse()
-            .expect("File did not contain integer data");
-
-        temp_str.clear();
-        let _ = charge_now.read_to_string(&mut temp_str);
-        let charge_micro_amp_hrs: u64 = temp_str
-            .trim()
-            .parse()
-            .expect("File did not contain integer data");
-
-        temp_str.clear();
-        let _ = capacity.read_to_string(&mut temp_str);
-        let current_percent: u64 = temp_str
-            .trim()
-            .parse()
-            .expect("File did not contain integer data");
-
-        temp_str.clear();
-        let _ = status.read_to_string(&mut temp_str);
-        let batt_status = match temp_str.trim() {
-            "Charging" => ChargeStatus::Charging,
-            "Discharging" => ChargeStatus::Discharging,
-            "Full" => ChargeStatus::Full,
-            "Not Charging" => ChargeStatus::NotCharging,
-            _ => ChargeStatus::Unknown,
-        };
+    /// Sets how the widget is shown while the battery is full and on AC
+    /// power, to reduce clutter on docked laptops
+    #[must_use]
+    pub fn with_full_display(mut self, full_display: FullDisplay) -> Self {
+        self.full_display = full_display;
+        self
+    }
+
+    /// Runs `action` when the battery discharges to `threshold` percent, e.g.
+    /// to suspend before it runs out. It runs once per crossing: the battery
+    /// has to charge a few percent above `threshold` for it to run again
+    #[must_use]
+    pub fn with_action(mut self, threshold: u64, action: Action) -> Self {
+        self.alarms.push(Alarm {
+            threshold,
+            action,
+            armed: true,
+        });
+        self
+    }
 
-        let estimated_hrs_left = charge_micro_amp_hrs as f64 / current_micro_amps as f64;
-        let estimated_duration = Duration::from_secs_f64(3600.0 * estimated_hrs_left);
+    /// Registers the `battery conservation on|off|toggle` IPC command, which
+    /// turns conservation mode on by making charging stop at `limit`
+    /// percent, and off by letting the battery charge fully. Needs a laptop
+    /// with a charge threshold, and polkit to authorise the change
+    #[must_use]
+    pub fn with_conservation(mut self, limit: u64) -> Self {
+        self.conservation_limit = Some(limit);
+        self
+    }
 
-        let batt_info = BatteryInfo {
-            status: batt_status,
-            capacity: current_percent,
-            time_till_empty: estimated_duration,
-        };
+    /// Sets what the percentage is relative to. [`CapacityBasis::Design`]
+    /// shows how much charge is left of what the battery held when new, for
+    /// worn batteries whose firmware reports against their degraded capacity
+    #[must_use]
+    pub fn with_capacity_basis(mut self, basis: CapacityBasis) -> Self {
+        self.collector = self.collector.with_capacity_basis(basis);
+        self
+    }
+
+    /// Sets how capacity readings are smoothed, so the percentage and colour
+    /// don't flap between ticks
+    #[must_use]
+    pub fn with_smoothing(mut self, smoothing: Smoothing) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+}
+
+impl CapacitySmoother {
+    fn new(smoothing: Smoothing) -> CapacitySmoother {
+        CapacitySmoother {
+            smoothing,
+            recent_capacity: VecDeque::new(),
+            displayed_capacity: None,
+        }
+    }
 
-        let text = if let Some(render) = &self.render {
-            render(batt_info)
-        } else {
-            format!(
-                "{:?} : {}%, : {:.0?}",
-                batt_info.status, batt_info.capacity, batt_info.time_till_empty
-            )
+    fn smooth(&mut self, capacity: u64, status: &ChargeStatus) -> u64 {
+        let smoothed = match self.smoothing {
+            Smoothing::None => capacity,
+            Smoothing::MovingAverage(window) => {
+                self.recent_capacity.push_back(capacity);
+                while self.recent_capacity.len() > window.max(1) {
+                    self.recent_capacity.pop_front();
+                }
+                let sum: u64 = self.recent_capacity.iter().sum();
+                (sum as f64 / self.recent_capacity.len() as f64).round() as u64
+            }
+            Smoothing::Hysteresis(threshold) => match self.displayed_capacity {
+                Some(displayed) => {
+                    let expected_direction = match status {
+                        ChargeStatus::Charging => capacity > displayed,
+                        ChargeStatus::Discharging => capacity < displayed,
+                        _ => false,
+                    };
+                    if expected_direction || capacity.abs_diff(displayed) >= threshold {
+                        capacity
+                    } else {
+                        displayed
+                    }
+                }
+                None => capacity,
+            },
         };
 
-        vec![Text {
-            attr: self.attrs.clone(),
-            text,
-            stretch: false,
-            markup: self.render.is_some(),
-        }]
+        self.displayed_capacity = Some(smoothed);
+        smoothed
     }
 }
 
+/// The default render
+fn render_default(batt_info: &BatteryInfo) -> String {
+    let icon = icons::icon_markup(match batt_info.status {
+        ChargeStatus::Charging => icons::icons().charging,
+        _ => icons::icons().battery,
+    });
+
+    let conservation = if batt_info.conserving() {
+        format!(" {}", icons::icon_markup(icons::icons().conservation))
+    } else {
+        String::new()
+    };
+    let charger = match &batt_info.charger {
+        Some(charger) if matches!(batt_info.status, ChargeStatus::Charging) => {
+            format!(" @ {:.0}W", charger.watts)
+        }
+        _ => String::new(),
+    };
+
+    format!(
+        "{icon} {:?}{charger} : <span foreground=\"{}\">{}%</span>, : {:.0?}{conservation}",
+        batt_info.status,
+        color::gradient(100.0 - batt_info.capacity as f64).to_hex(),
+        batt_info.capacity,
+        batt_info.time_till_empty
+    )
+}
+
 impl Widget for Battery {
     fn into_stream(self: Box<Self>) -> anyhow::Result<cnx::widgets::WidgetStream> {
-        let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
+        let Battery {
+            attrs,
+            render,
+            update_interval,
+            mut collector,
+            full_display,
+            smoothing,
+            mut alarms,
+            battery_path,
+            conservation_limit,
+        } = *self;
+
+        if let Some(limit) = conservation_limit {
+            ipc::register("battery", move |args| {
+                let enable = match args {
+                    ["conservation", "on"] => true,
+                    ["conservation", "off"] => false,
+                    ["conservation", "toggle"] => {
+                        !charge_limit(&battery_path).is_some_and(|limit| limit < FULL)
+                    }
+                    _ => bail!("usage: battery conservation on|off|toggle"),
+                };
+
+                set_charge_limit(&battery_path, if enable { limit } else { FULL })?;
+                state::bar().request_refresh();
+                Ok(String::new())
+            });
+        }
+
+        // Alarms have to fire even while nobody watches the bar
+        let alarms_empty = alarms.is_empty();
+        let mut smoother = CapacitySmoother::new(smoothing);
+        let collect = move || -> anyhow::Result<BatteryInfo> {
+            let mut batt_info = collector.collect()?;
+            batt_info.capacity = smoother.smooth(batt_info.capacity, &batt_info.status);
+            for alarm in &mut alarms {
+                alarm.check(&batt_info);
+            }
+            Ok(batt_info)
+        };
+
+        let show = move |batt_info: BatteryInfo| {
+            if matches!(batt_info.status, ChargeStatus::Full)
+                && full_display != FullDisplay::Normal
+                && on_ac_power()
+            {
+                return match full_display {
+                    FullDisplay::Hidden => String::new(),
+                    _ => icons::icon_markup(icons::icons().charging),
+                };
+            }
+
+            match &render {
+                Some(render) => render.render(batt_info, &RenderContext::current()),
+                None => render_default(&batt_info),
+            }
+        };
 
-        Ok(Box::pin(stream))
+        let widget = PollingWidget::new(attrs, update_interval, collect, show)
+            .with_export("battery")
+            .with_pausing(alarms_empty);
+        Box::new(widget).into_stream()
     }
 }
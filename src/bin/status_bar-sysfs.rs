@@ -0,0 +1,15 @@
+//! Helper writing one of the sysfs knobs the bar changes as root, run through
+//! `pkexec` by [`status_bar::privileged::set`]. It takes the knob and value
+//! as arguments and refuses anything [`Knob::parse`] doesn't accept.
+
+use std::env;
+
+use anyhow::Result;
+use status_bar::privileged::Knob;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    Knob::parse(&args)?.apply()
+}
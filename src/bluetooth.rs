@@ -0,0 +1,185 @@
+//! Battery levels of connected Bluetooth devices, read from BlueZ's
+//! `org.bluez.Battery1` interface on the system bus.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+use zbus::blocking::fdo::ObjectManagerProxy;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedValue;
+
+use crate::power_supply::SupplyKind;
+use crate::render::{Render, RenderContext};
+use crate::{color, icons, state};
+
+const BLUEZ: &str = "org.bluez";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+// Abstracted type to represent the render closure
+type BluetoothRender = Box<dyn Render<BluetoothInfo>>;
+
+pub struct BluetoothDevice {
+    pub name: String,
+    pub kind: SupplyKind,
+    pub percentage: u8,
+}
+
+pub struct BluetoothInfo {
+    /// Connected devices which report their battery level, sorted by name
+    pub devices: Vec<BluetoothDevice>,
+}
+
+/// cnx widget that shows the battery level of every connected Bluetooth
+/// device which reports one, such as headphones or a mouse. Hidden while
+/// there are none
+pub struct BluetoothBatteries {
+    attrs: Attributes,
+    render: Option<BluetoothRender>,
+    update_interval: Duration,
+    connection: Option<Connection>,
+}
+
+impl BluetoothBatteries {
+    /// Creates a new [`BluetoothBatteries`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<BluetoothRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often BlueZ is queried
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<BluetoothRender>,
+        update_interval: Duration,
+    ) -> BluetoothBatteries {
+        BluetoothBatteries {
+            attrs,
+            render,
+            update_interval,
+            connection: None,
+        }
+    }
+
+    fn devices(&mut self) -> Result<Vec<BluetoothDevice>> {
+        // Connect lazily so the widget recovers if the bus wasn't up yet
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Connection::system()?),
+        };
+
+        let objects = ObjectManagerProxy::builder(connection)
+            .destination(BLUEZ)?
+            .path("/")?
+            .build()?
+            .get_managed_objects()?;
+
+        let mut devices: Vec<BluetoothDevice> = objects
+            .values()
+            .filter_map(|interfaces| {
+                let interface = |name: &str| {
+                    interfaces
+                        .iter()
+                        .find(|(interface, _)| interface.as_str() == name)
+                        .map(|(_, properties)| properties)
+                };
+                let device = interface(DEVICE_INTERFACE)?;
+                let battery = interface(BATTERY_INTERFACE)?;
+                if !property::<bool>(device, "Connected").unwrap_or(false) {
+                    return None;
+                }
+
+                let name = property::<&str>(device, "Alias")
+                    .or_else(|| property::<&str>(device, "Name"))
+                    .unwrap_or_default()
+                    .to_string();
+                let kind = match property::<&str>(device, "Icon") {
+                    Some("audio-headset" | "audio-headphones" | "audio-card") => {
+                        SupplyKind::Headphones
+                    }
+                    Some("input-mouse") => SupplyKind::Mouse,
+                    Some("input-keyboard") => SupplyKind::Keyboard,
+                    _ => SupplyKind::Peripheral,
+                };
+
+                Some(BluetoothDevice {
+                    name,
+                    kind,
+                    percentage: property::<u8>(battery, "Percentage")?,
+                })
+            })
+            .collect();
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(devices)
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        let devices = match self.devices() {
+            Ok(devices) => devices,
+            Err(_) => {
+                // Reconnect next time in case the bus went away
+                self.connection = None;
+                vec![]
+            }
+        };
+        if devices.is_empty() {
+            return vec![];
+        }
+
+        let bluetooth_info = BluetoothInfo { devices };
+
+        let text = if let Some(render) = &self.render {
+            render.render(bluetooth_info, &RenderContext::current())
+        } else {
+            bluetooth_info
+                .devices
+                .iter()
+                .map(|device| {
+                    format!(
+                        "{} <span foreground=\"{}\">{}%</span>",
+                        icons::icon_markup(device.kind.icon()),
+                        color::gradient(100.0 - f64::from(device.percentage)).to_hex(),
+                        device.percentage
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for BluetoothBatteries {
+    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+fn property<'a, T>(properties: &'a HashMap<String, OwnedValue>, name: &str) -> Option<T>
+where
+    T: TryFrom<&'a OwnedValue>,
+{
+    properties
+        .get(name)
+        .and_then(|value| T::try_from(value).ok())
+}
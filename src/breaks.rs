@@ -0,0 +1,247 @@
+//! Reminders to take a break after working for a while without one.
+//!
+//! Time counts as work for as long as there is keyboard or mouse input, and
+//! pauses shorter than a break count towards it. Being idle for a whole break
+//! starts the count again. The widget counts down to the next break, and once
+//! it is due sends a notification and shows that it is overdue.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::Action;
+use crate::idle::Idle;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::urgent::Urgency;
+use crate::{icons, render};
+
+// Abstracted type to represent the render closure
+type BreakRender = Box<dyn Render<BreakInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BreakInfo {
+    /// Seconds worked since the last break
+    pub active: u64,
+    /// Seconds left until the next break is due, 0 once it is
+    pub remaining: u64,
+    pub due: bool,
+}
+
+impl Fields for BreakInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.rest))),
+            "active" => Some(Value::Number(self.active as f64 / 60.0)),
+            "remaining" => Some(Value::Number(self.remaining as f64 / 60.0)),
+            "due" => Some(Value::Number(f64::from(u8::from(self.due)))),
+            _ => None,
+        }
+    }
+}
+
+/// Work time since the last break
+struct Tracker {
+    work: Duration,
+    rest: Duration,
+    /// Start of the current stretch of work, `None` during a break
+    active_since: Option<Instant>,
+    notified: bool,
+}
+
+impl Tracker {
+    /// Accounts for the input seen `idle` ago, as of `now`
+    fn update(&mut self, idle: Duration, now: Instant) -> BreakInfo {
+        if idle >= self.rest {
+            self.active_since = None;
+            self.notified = false;
+        } else if self.active_since.is_none() {
+            // Work started again with the last input
+            self.active_since = Some(now.checked_sub(idle).unwrap_or(now));
+        }
+
+        let active = self
+            .active_since
+            .map_or(Duration::ZERO, |since| now - since);
+
+        let due = active >= self.work;
+        if due && !self.notified {
+            Action::Notify {
+                summary: "Time for a break".to_string(),
+                body: format!(
+                    "You have been working for {} minutes",
+                    active.as_secs() / 60
+                ),
+            }
+            .spawn();
+            self.notified = true;
+        }
+
+        BreakInfo {
+            active: active.as_secs(),
+            remaining: self.work.saturating_sub(active).as_secs(),
+            due,
+        }
+    }
+}
+
+/// cnx widget that counts down to the next break
+pub struct BreakReminder {
+    attrs: Attributes,
+    render: Option<BreakRender>,
+    work: Duration,
+    rest: Duration,
+    urgency: Option<Urgency>,
+}
+
+impl BreakReminder {
+    /// Creates a new [`BreakReminder`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<BreakRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `work`: [`Duration`] - How long to work before a break is due
+    ///
+    /// `rest`: [`Duration`] - How long without input counts as a break
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<BreakRender>,
+        work: Duration,
+        rest: Duration,
+    ) -> BreakReminder {
+        BreakReminder {
+            attrs,
+            render,
+            work,
+            rest,
+            urgency: None,
+        }
+    }
+
+    /// Sets `urgency` while a break is due, e.g. to make the widget blink
+    /// with [`crate::urgent::Blink`]
+    #[must_use]
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = Some(urgency);
+        self
+    }
+}
+
+fn default_render(break_info: &BreakInfo) -> String {
+    let icon = icons::icon_markup(icons::icons().rest);
+    if break_info.due {
+        return format!(
+            "<span foreground=\"{}\">{icon} break</span>",
+            render::theme().critical.to_hex()
+        );
+    }
+
+    format!("{icon} {}m", break_info.remaining.div_ceil(60))
+}
+
+impl Widget for BreakReminder {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let BreakReminder {
+            attrs,
+            render,
+            work,
+            rest,
+            urgency,
+        } = *self;
+
+        let mut tracker = Tracker {
+            work,
+            rest,
+            active_since: None,
+            notified: false,
+        };
+
+        // Connects on first use, and again after an error
+        let mut idle = None;
+        let collect = move || -> Result<BreakInfo> {
+            let connection = match idle.take() {
+                Some(connection) => connection,
+                None => Idle::connect()?,
+            };
+            let idle_time = connection.idle_time()?;
+            idle = Some(connection);
+
+            let break_info = tracker.update(idle_time, Instant::now());
+            if let Some(urgency) = &urgency {
+                urgency.set(break_info.due);
+            }
+            Ok(break_info)
+        };
+
+        let show = move |break_info: BreakInfo| match &render {
+            Some(render) => render.render(break_info, &RenderContext::current()),
+            None => default_render(&break_info),
+        };
+
+        // Keeps polling while the screen is locked, so that a locked break is
+        // seen as one rather than counted as work when the screen unlocks
+        let widget = PollingWidget::new(attrs, Duration::from_secs(1), collect, show)
+            .with_export("breaks")
+            .with_pausing(false);
+        Box::new(widget).into_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn tracker() -> Tracker {
+        Tracker {
+            work: 50 * MINUTE,
+            rest: 5 * MINUTE,
+            active_since: None,
+            notified: false,
+        }
+    }
+
+    #[test]
+    fn short_pauses_count_as_work() {
+        let start = Instant::now();
+        let mut tracker = tracker();
+        tracker.update(Duration::ZERO, start);
+
+        let info = tracker.update(2 * MINUTE, start + 10 * MINUTE);
+        assert_eq!(info.active, 600);
+        assert_eq!(info.remaining, 2400);
+        assert!(!info.due);
+    }
+
+    #[test]
+    fn locked_stretch_is_a_break() {
+        let start = Instant::now();
+        let mut tracker = tracker();
+        tracker.update(Duration::ZERO, start);
+        tracker.update(Duration::ZERO, start + 40 * MINUTE);
+
+        // Locked for lunch, with no input the whole time
+        let info = tracker.update(20 * MINUTE, start + 60 * MINUTE);
+        assert_eq!(info.active, 0);
+        assert!(!info.due);
+
+        // Back at the keyboard
+        let info = tracker.update(Duration::ZERO, start + 61 * MINUTE);
+        assert_eq!(info.active, 0);
+        let info = tracker.update(Duration::ZERO, start + 71 * MINUTE);
+        assert_eq!(info.active, 600);
+    }
+}
@@ -0,0 +1,167 @@
+//! Cache for widgets backed by network requests, which keeps showing the last
+//! good value while a refresh is in flight or failing rather than blanking
+//! the widget.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// A value served from a [`Cache`]
+pub struct Cached<T> {
+    pub value: T,
+    /// Time since the value was fetched
+    pub age: Duration,
+    /// Whether the value is older than the cache's TTL, because refreshing
+    /// it has been failing
+    pub stale: bool,
+}
+
+struct Entry<T> {
+    value: Option<(T, Instant)>,
+    refreshing: bool,
+    /// Set by [`Cache::invalidate`] to refresh regardless of age
+    invalidated: bool,
+}
+
+struct Shared<T> {
+    entry: Mutex<Entry<T>>,
+    updates: watch::Sender<()>,
+}
+
+/// Clears `refreshing` when the refresh thread ends, even by panicking, as
+/// otherwise no refresh would ever start again
+struct Refreshing<T>(Arc<Shared<T>>);
+
+impl<T> Drop for Refreshing<T> {
+    fn drop(&mut self) {
+        let mut entry = self.0.entry.lock().unwrap_or_else(PoisonError::into_inner);
+        entry.refreshing = false;
+    }
+}
+
+/// Holds the last successfully fetched value and refreshes it on a background
+/// thread, so slow or flaky requests never block the bar
+pub struct Cache<T> {
+    shared: Arc<Shared<T>>,
+    refresh_after: Duration,
+    ttl: Duration,
+}
+
+impl<T: Clone + Send + 'static> Cache<T> {
+    /// Creates an empty cache whose values are refreshed once they are
+    /// `refresh_after` old, and reported stale once they are `ttl` old
+    #[must_use]
+    pub fn new(refresh_after: Duration, ttl: Duration) -> Cache<T> {
+        Cache {
+            shared: Arc::new(Shared {
+                entry: Mutex::new(Entry {
+                    value: None,
+                    refreshing: false,
+                    invalidated: false,
+                }),
+                updates: watch::channel(()).0,
+            }),
+            refresh_after,
+            ttl,
+        }
+    }
+
+    /// Returns the cached value, `None` until the first fetch succeeds. If the
+    /// value is due a refresh and none is in flight, `fetch` is run on a
+    /// background thread; failures leave the previous value in place
+    pub fn get(&self, fetch: impl FnOnce() -> Result<T> + Send + 'static) -> Option<Cached<T>> {
+        let mut entry = self.shared.entry.lock().unwrap();
+
+        let due = entry.invalidated
+            || entry
+                .value
+                .as_ref()
+                .is_none_or(|(_, fetched)| fetched.elapsed() >= self.refresh_after);
+        if due && !entry.refreshing {
+            entry.refreshing = true;
+            entry.invalidated = false;
+
+            let refreshing = Refreshing(Arc::clone(&self.shared));
+            thread::spawn(move || {
+                let result = fetch();
+
+                let shared = &refreshing.0;
+                let mut entry = shared.entry.lock().unwrap();
+                entry.refreshing = false;
+                if let Ok(value) = result {
+                    entry.value = Some((value, Instant::now()));
+                    drop(entry);
+                    shared.updates.send_replace(());
+                }
+            });
+        }
+
+        entry.value.as_ref().map(|(value, fetched)| Cached {
+            value: value.clone(),
+            age: fetched.elapsed(),
+            stale: fetched.elapsed() >= self.ttl,
+        })
+    }
+
+    /// Refreshes the value on the next [`Cache::get`], however recent it is,
+    /// e.g. after a change the widget made itself. The current value is
+    /// still served until then
+    pub fn invalidate(&self) {
+        self.shared.entry.lock().unwrap().invalidated = true;
+    }
+
+    /// A stream that yields whenever a refresh completes, for widgets to
+    /// render new values without waiting for their next tick
+    pub fn updates(&self) -> impl Stream<Item = ()> {
+        WatchStream::from_changes(self.shared.updates.subscribe()).map(|_| ())
+    }
+}
+
+/// Pango markup drawing `markup` faded, to mark a stale value
+#[must_use]
+pub fn mark_stale(markup: &str) -> String {
+    format!("<span alpha=\"50%\">{markup}</span>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Calls `get` until it returns a value, which refreshes complete on
+    /// another thread
+    fn wait_for<T: Clone + Send + 'static>(
+        cache: &Cache<T>,
+        fetch: impl Fn() -> Result<T> + Send + Clone + 'static,
+    ) -> Cached<T> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(cached) = cache.get(fetch.clone()) {
+                return cached;
+            }
+            assert!(Instant::now() < deadline, "no value was fetched");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn serves_the_fetched_value() {
+        let cache = Cache::new(Duration::from_secs(60), Duration::from_secs(120));
+        let cached = wait_for(&cache, || Ok(1));
+        assert_eq!(cached.value, 1);
+        assert!(!cached.stale);
+    }
+
+    #[test]
+    fn retries_after_a_panicking_fetch() {
+        let cache = Cache::new(Duration::ZERO, Duration::from_secs(60));
+        assert!(cache.get(|| panic!("fetch panicked")).is_none());
+
+        let cached = wait_for(&cache, || Ok(2));
+        assert_eq!(cached.value, 2);
+    }
+}
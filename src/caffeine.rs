@@ -0,0 +1,175 @@
+//! Idle inhibitor which keeps the screen from blanking or locking, toggled
+//! through the `caffeine` IPC command.
+
+use std::os::fd::OwnedFd;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+use zbus::blocking::Connection;
+
+use crate::render::{Render, RenderContext};
+use crate::{icons, ipc, state};
+
+// Abstracted type to represent the render closure
+type CaffeineRender = Box<dyn Render<bool>>;
+
+/// How idleness is inhibited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InhibitMethod {
+    /// Take a systemd-logind idle inhibitor lock, which is held until it is
+    /// released or the bar exits
+    Logind,
+    /// Disable the X11 screensaver and DPMS with `xset`
+    Xset,
+}
+
+enum Inhibitor {
+    /// The file descriptor of the logind lock
+    Logind(OwnedFd),
+    Xset,
+}
+
+impl InhibitMethod {
+    fn inhibit(self) -> Result<Inhibitor> {
+        match self {
+            InhibitMethod::Logind => {
+                let reply = Connection::system()?.call_method(
+                    Some("org.freedesktop.login1"),
+                    "/org/freedesktop/login1",
+                    Some("org.freedesktop.login1.Manager"),
+                    "Inhibit",
+                    &("idle", "status_bar", "Caffeine mode", "block"),
+                )?;
+                let fd: zbus::zvariant::OwnedFd = reply.body().deserialize()?;
+                Ok(Inhibitor::Logind(fd.into()))
+            }
+            InhibitMethod::Xset => {
+                xset(&["s", "off", "-dpms"])?;
+                Ok(Inhibitor::Xset)
+            }
+        }
+    }
+}
+
+impl Inhibitor {
+    fn release(self) -> Result<()> {
+        match self {
+            // logind drops the lock once its file descriptor is closed
+            Inhibitor::Logind(fd) => drop(fd),
+            Inhibitor::Xset => xset(&["s", "on", "+dpms"])?,
+        }
+        Ok(())
+    }
+}
+
+fn xset(args: &[&str]) -> Result<()> {
+    if !Command::new("xset").args(args).status()?.success() {
+        bail!("xset {} failed", args.join(" "));
+    }
+    Ok(())
+}
+
+/// cnx widget that shows whether the idle inhibitor is active
+pub struct Caffeine {
+    attrs: Attributes,
+    render: Option<CaffeineRender>,
+    inhibitor: Arc<Mutex<Option<Inhibitor>>>,
+}
+
+impl Caffeine {
+    /// Creates a new [`Caffeine`] widget and registers the `caffeine` IPC
+    /// command, which takes `toggle`, `on` or `off`
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<CaffeineRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `method`: [`InhibitMethod`] - How idleness is inhibited
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<CaffeineRender>,
+        method: InhibitMethod,
+    ) -> Caffeine {
+        let inhibitor: Arc<Mutex<Option<Inhibitor>>> = Arc::new(Mutex::new(None));
+
+        let command_inhibitor = Arc::clone(&inhibitor);
+        ipc::register("caffeine", move |args| {
+            let mut inhibitor = command_inhibitor.lock().unwrap();
+            let enable = match args {
+                ["toggle"] | [] => inhibitor.is_none(),
+                ["on"] => true,
+                ["off"] => false,
+                _ => bail!("usage: caffeine [toggle|on|off]"),
+            };
+
+            if enable && inhibitor.is_none() {
+                *inhibitor = Some(method.inhibit()?);
+            } else if !enable {
+                if let Some(active) = inhibitor.take() {
+                    active.release()?;
+                }
+            }
+
+            state::bar().request_refresh();
+            Ok(String::new())
+        });
+
+        // xset changes outlive the bar, so undo them on the way out
+        let shutdown_inhibitor = Arc::clone(&inhibitor);
+        state::bar().on_shutdown(move || {
+            if let Some(active) = shutdown_inhibitor.lock().unwrap().take() {
+                let _ = active.release();
+            }
+        });
+
+        Caffeine {
+            attrs,
+            render,
+            inhibitor,
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let active = self.inhibitor.lock().unwrap().is_some();
+
+        let text = if let Some(render) = &self.render {
+            render.render(active, &RenderContext::current())
+        } else if active {
+            icons::icon_markup(icons::icons().caffeine)
+        } else {
+            format!(
+                "<span foreground=\"{}\">{}</span>",
+                Color::from_rgb(128, 128, 128).to_hex(),
+                icons::icon_markup(icons::icons().caffeine)
+            )
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Caffeine {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        // Only changes through the IPC command, which requests a refresh
+        let stream = state::ticks(Duration::from_secs(60)).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
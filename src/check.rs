@@ -0,0 +1,182 @@
+//! `status_bar check`, which validates the config file and reports every
+//! problem with its position, exiting unsuccessfully if there are any, e.g.
+//! to check dotfiles in CI.
+//!
+//! ```text
+//! ~/.config/status_bar/config.toml:12:1: unknown widget `cpus`
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::config::Config;
+use crate::profiles;
+
+/// Subcommand that checks the config
+pub const COMMAND: &str = "check";
+
+/// A problem found in the config
+pub struct Diagnostic {
+    /// 1-based line and column of the problem, `None` for problems which
+    /// aren't about any part of the file
+    position: Option<(usize, usize)>,
+    message: String,
+}
+
+impl Diagnostic {
+    fn at(contents: &str, offset: usize, message: String) -> Diagnostic {
+        let before = &contents[..offset.min(contents.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+        Diagnostic {
+            position: Some((line, column)),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{line}:{column}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The parts of the config which are checked beyond parsing, with their
+/// positions
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Spans {
+    widgets: BTreeMap<Spanned<String>, toml::Value>,
+    export: ExportSpans,
+    profile: Option<Spanned<String>>,
+    profiles: BTreeMap<String, ProfileSpans>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ProfileSpans {
+    hide: Vec<Spanned<String>>,
+    widgets: BTreeMap<Spanned<String>, toml::Value>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ExportSpans {
+    address: Option<Spanned<String>>,
+}
+
+/// Reports the files in `paths` which don't exist, skipping those of widgets
+/// which `config` doesn't show
+fn check_paths(mut config: Config, paths: &[(&str, &Path)]) -> Vec<Diagnostic> {
+    if let Some(profile) = profiles::active(&config) {
+        // A missing profile is reported along with the rest of the config
+        let _ = config.apply_profile(&profile);
+    }
+
+    paths
+        .iter()
+        .filter(|(widget, path)| config.shown(widget) && !path.exists())
+        .map(|(widget, path)| Diagnostic {
+            position: None,
+            message: format!("{widget}: {} does not exist", path.display()),
+        })
+        .collect()
+}
+
+/// Checks the config at `path`. `widgets` are the names widgets can be
+/// configured under, and `paths` are files the bar reads, named after the
+/// widget reading them
+pub fn check(path: &Path, widgets: &[&str], paths: &[(&str, &Path)]) -> Result<Vec<Diagnostic>> {
+    // No config file is a valid config
+    if !path.exists() {
+        return Ok(check_paths(Config::default(), paths));
+    }
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+
+    // Template, interval and unknown field errors all come from parsing
+    let config = match toml::from_str::<Config>(&contents) {
+        Ok(config) => config,
+        Err(e) => {
+            let offset = e.span().map_or(0, |span| span.start);
+            return Ok(vec![Diagnostic::at(
+                &contents,
+                offset,
+                e.message().to_string(),
+            )]);
+        }
+    };
+    let mut diagnostics = check_paths(config, paths);
+
+    let spans: Spans = toml::from_str(&contents)?;
+
+    let profile_widgets = spans
+        .profiles
+        .values()
+        .flat_map(|profile| profile.hide.iter().chain(profile.widgets.keys()));
+    for name in spans.widgets.keys().chain(profile_widgets) {
+        if !widgets.contains(&name.get_ref().as_str()) {
+            diagnostics.push(Diagnostic::at(
+                &contents,
+                name.span().start,
+                format!(
+                    "unknown widget `{}`, expected one of {}",
+                    name.get_ref(),
+                    widgets.join(", ")
+                ),
+            ));
+        }
+    }
+
+    if let Some(profile) = &spans.profile {
+        if !spans.profiles.contains_key(profile.get_ref()) {
+            diagnostics.push(Diagnostic::at(
+                &contents,
+                profile.span().start,
+                format!("no profile called `{}`", profile.get_ref()),
+            ));
+        }
+    }
+
+    if let Some(address) = &spans.export.address {
+        if let Err(e) = address.get_ref().to_socket_addrs() {
+            diagnostics.push(Diagnostic::at(
+                &contents,
+                address.span().start,
+                format!("invalid export address `{}`: {e}", address.get_ref()),
+            ));
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// Checks the config file and prints what is wrong with it. Returns whether
+/// it is valid
+pub fn run(widgets: &[&str], paths: &[(&str, &Path)]) -> Result<bool> {
+    let path = Config::path();
+    let diagnostics = check(&path, widgets, paths)?;
+
+    for diagnostic in &diagnostics {
+        match diagnostic.position {
+            Some(_) => println!("{}:{diagnostic}", path.display()),
+            None => println!("{diagnostic}"),
+        }
+    }
+    if diagnostics.is_empty() {
+        println!("{} is valid", path.display());
+    }
+
+    Ok(diagnostics.is_empty())
+}
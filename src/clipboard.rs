@@ -0,0 +1,269 @@
+//! Copying widget values to the X clipboard.
+//!
+//! X has no clipboard daemon: the copied text lives in the client which owns
+//! the `CLIPBOARD` selection, and is sent to whichever client asks for it.
+//! The bar keeps its own connection, whose window every copy makes the owner
+//! again, as another client may have taken the selection since. A background
+//! thread answers requests on it for as long as the bar runs.
+//!
+//! cnx doesn't deliver clicks to widgets, so copying is triggered with the
+//! `copy <name>` IPC command, e.g. from a key binding.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, SelectionNotifyEvent,
+    Window, WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+use crate::{ipc, markup};
+
+/// Text currently offered on the clipboard
+static CONTENTS: Mutex<String> = Mutex::new(String::new());
+
+/// Connection owning the selection, made by the first copy. Cleared if it
+/// is lost, so the next copy connects again
+static OWNER: Mutex<Option<Arc<Owner>>> = Mutex::new(None);
+
+/// Latest value of each [`Copyable`] widget, keyed by name
+static VALUES: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Puts `text` on the clipboard
+pub fn copy(text: String) -> Result<()> {
+    *CONTENTS.lock().unwrap() = text;
+
+    let owner = {
+        let mut owner = OWNER.lock().unwrap();
+        match &*owner {
+            Some(running) => Arc::clone(running),
+            None => {
+                let started = Arc::new(Owner::connect()?);
+                let serving = Arc::clone(&started);
+                thread::spawn(move || {
+                    if let Err(e) = serving.serve() {
+                        eprintln!("Clipboard error: {e}");
+                    }
+                    let mut owner = OWNER.lock().unwrap();
+                    if owner
+                        .as_ref()
+                        .is_some_and(|current| Arc::ptr_eq(current, &serving))
+                    {
+                        *owner = None;
+                    }
+                });
+                *owner = Some(Arc::clone(&started));
+                started
+            }
+        }
+    };
+
+    owner.claim()
+}
+
+/// A connection with a window to own the selection with
+struct Owner {
+    connection: RustConnection,
+    window: Window,
+    clipboard: Atom,
+    targets: Atom,
+    utf8_string: Atom,
+}
+
+impl Owner {
+    fn connect() -> Result<Owner> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen].root;
+
+        let window = connection.generate_id()?;
+        connection.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )?;
+
+        let atom = |name: &[u8]| -> Result<Atom> {
+            Ok(connection.intern_atom(false, name)?.reply()?.atom)
+        };
+        let clipboard = atom(b"CLIPBOARD")?;
+        let targets = atom(b"TARGETS")?;
+        let utf8_string = atom(b"UTF8_STRING")?;
+
+        Ok(Owner {
+            connection,
+            window,
+            clipboard,
+            targets,
+            utf8_string,
+        })
+    }
+
+    /// Makes the window the selection owner, whether or not it still is
+    fn claim(&self) -> Result<()> {
+        self.connection
+            .set_selection_owner(self.window, self.clipboard, x11rb::CURRENT_TIME)?;
+        let owner = self
+            .connection
+            .get_selection_owner(self.clipboard)?
+            .reply()?
+            .owner;
+        if owner != self.window {
+            bail!("could not take ownership of the clipboard");
+        }
+        Ok(())
+    }
+
+    /// Answers requests for the selection until the connection is lost.
+    /// Losing the selection to another client only means no requests come
+    /// until the next [`Owner::claim`]
+    fn serve(&self) -> Result<()> {
+        let connection = &self.connection;
+        let string = AtomEnum::STRING.into();
+
+        loop {
+            let Event::SelectionRequest(request) = connection.wait_for_event()? else {
+                continue;
+            };
+
+            // Obsolete clients may not name a property to write to
+            let property = if request.property == x11rb::NONE {
+                request.target
+            } else {
+                request.property
+            };
+
+            let answered = if request.target == self.targets {
+                connection.change_property32(
+                    PropMode::REPLACE,
+                    request.requestor,
+                    property,
+                    AtomEnum::ATOM,
+                    &[self.targets, self.utf8_string, string],
+                )?;
+                true
+            } else if request.target == self.utf8_string || request.target == string {
+                connection.change_property8(
+                    PropMode::REPLACE,
+                    request.requestor,
+                    property,
+                    request.target,
+                    CONTENTS.lock().unwrap().as_bytes(),
+                )?;
+                true
+            } else {
+                false
+            };
+
+            let notification = SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: if answered { property } else { x11rb::NONE },
+            };
+            connection.send_event(false, request.requestor, EventMask::NO_EVENT, notification)?;
+            connection.flush()?;
+        }
+    }
+}
+
+/// Wraps a widget so that its value can be copied to the clipboard with the
+/// `copy <name>` IPC command
+pub struct Copyable {
+    name: String,
+    inner: Box<dyn Widget>,
+    extract: Option<Box<dyn Fn(&str) -> String>>,
+}
+
+impl Copyable {
+    /// Creates a new [`Copyable`] widget. By default the widget's text is
+    /// copied with any markup removed
+    ///
+    /// Arguments
+    ///
+    /// `name`: [`&str`] - Name used to address the widget
+    ///
+    /// `widget`: [`Widget`] - The widget whose value is copied
+    pub fn new(name: &str, widget: impl Widget + 'static) -> Copyable {
+        ipc::register("copy", |args| {
+            let [name] = args else {
+                return Err(anyhow!("usage: copy <name>"));
+            };
+            let value = VALUES
+                .lock()
+                .unwrap()
+                .get(*name)
+                .cloned()
+                .ok_or_else(|| anyhow!("no copyable widget called {name}"))?;
+
+            copy(value)?;
+            Ok(String::new())
+        });
+
+        Copyable {
+            name: name.to_string(),
+            inner: Box::new(widget),
+            extract: None,
+        }
+    }
+
+    /// Sets how the copied value is derived from the widget's plain text,
+    /// e.g. to drop an icon and keep only an IP address
+    #[must_use]
+    pub fn with_extract(mut self, extract: impl Fn(&str) -> String + 'static) -> Self {
+        self.extract = Some(Box::new(extract));
+        self
+    }
+}
+
+impl Widget for Copyable {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Copyable {
+            name,
+            inner,
+            extract,
+        } = *self;
+
+        let stream = inner.into_stream()?.map(move |texts| {
+            if let Ok(texts) = &texts {
+                let plain: String = texts
+                    .iter()
+                    .map(|text| {
+                        if text.markup {
+                            markup::strip(&text.text)
+                        } else {
+                            text.text.clone()
+                        }
+                    })
+                    .collect();
+                let value = match &extract {
+                    Some(extract) => extract(&plain),
+                    None => plain.trim().to_string(),
+                };
+                VALUES.lock().unwrap().insert(name.clone(), value);
+            }
+            texts
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
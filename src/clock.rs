@@ -0,0 +1,254 @@
+//! Local date and time, formatted with chrono's `strftime` syntax plus a few
+//! extras of our own:
+//!
+//! - `{week}`: ISO 8601 week number, e.g. `7`
+//! - `{yday}`: day of the year, from 1
+//! - `{moon}`: glyph of the moon's current phase, from the icon set
+//!
+//! A format without them shows just the time, e.g. `%H:%M {moon}`.
+//!
+//! Alarms go off at a time of day with a desktop notification, and the
+//! widget shows the alarm's message until it is dismissed with the `clock
+//! dismiss` IPC command, e.g. from a key binding.
+
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::actions::Action;
+use crate::polling::PollingWidget;
+use crate::urgent::Urgency;
+use crate::{icons, ipc, markup, render, state};
+
+/// Mean time between two new moons, in days
+const SYNODIC_MONTH: f64 = 29.530_588_853;
+
+/// A new moon, 2000-01-06 18:14 UTC, as a Unix timestamp
+const NEW_MOON: i64 = 947_182_440;
+
+/// The clock only needs to update once a second, however it is formatted
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The eight phases of the moon, in order from the new moon
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+impl MoonPhase {
+    const ALL: [MoonPhase; 8] = [
+        MoonPhase::New,
+        MoonPhase::WaxingCrescent,
+        MoonPhase::FirstQuarter,
+        MoonPhase::WaxingGibbous,
+        MoonPhase::Full,
+        MoonPhase::WaningGibbous,
+        MoonPhase::LastQuarter,
+        MoonPhase::WaningCrescent,
+    ];
+
+    /// The phase at `time`, from the mean length of a lunar month, which
+    /// can be out by up to about half a day
+    #[must_use]
+    pub fn at<Tz: TimeZone>(time: &DateTime<Tz>) -> MoonPhase {
+        let days = (time.timestamp() - NEW_MOON) as f64 / 86_400.0;
+        let age = days.rem_euclid(SYNODIC_MONTH) / SYNODIC_MONTH;
+        let index = (age * 8.0).round() as usize % 8;
+        MoonPhase::ALL[index]
+    }
+
+    #[must_use]
+    pub fn icon(self) -> &'static str {
+        icons::icons().moon[self as usize]
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClockInfo {
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    /// ISO 8601 week number
+    pub week: u32,
+    /// Day of the year, from 1
+    pub day_of_year: u32,
+    pub moon: MoonPhase,
+    /// Message of the alarm going off, until it is dismissed
+    pub alarm: Option<String>,
+}
+
+impl ClockInfo {
+    fn at(now: DateTime<Local>, alarm: Option<String>) -> ClockInfo {
+        ClockInfo {
+            timestamp: now.timestamp(),
+            week: now.iso_week().week(),
+            day_of_year: now.ordinal(),
+            moon: MoonPhase::at(&now),
+            alarm,
+        }
+    }
+}
+
+struct Alarm {
+    time: NaiveTime,
+    message: String,
+}
+
+impl Alarm {
+    /// Whether the alarm went off after `last` and at or before `now`
+    fn due(&self, last: NaiveDateTime, now: NaiveDateTime) -> bool {
+        let today = now.date().and_time(self.time);
+        last < today && today <= now
+    }
+}
+
+/// cnx widget that shows the local date and time, sounds alarms and
+/// registers the `clock` IPC command for dismissing them
+pub struct Clock {
+    attrs: Attributes,
+    format: String,
+    alarms: Vec<Alarm>,
+    urgency: Option<Urgency>,
+}
+
+impl Clock {
+    /// Creates a new [`Clock`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `format`: [`&str`] - `strftime` format, which may also use the
+    /// tokens described in [`crate::clock`]
+    #[must_use]
+    pub fn new(attrs: Attributes, format: &str) -> Clock {
+        Clock {
+            attrs,
+            format: format.to_string(),
+            alarms: vec![],
+            urgency: None,
+        }
+    }
+
+    /// Adds an alarm which goes off every day at `time`, showing `message`
+    #[must_use]
+    pub fn with_alarm(mut self, time: NaiveTime, message: &str) -> Self {
+        self.alarms.push(Alarm {
+            time,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    /// Sets `urgency` while an alarm is going off, e.g. to make the widget
+    /// blink with [`crate::urgent::Blink`]
+    #[must_use]
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = Some(urgency);
+        self
+    }
+}
+
+/// `format` filled in with the time in `clock_info`, as Pango markup, or
+/// `None` if it isn't a valid format
+fn format_time(format: &str, clock_info: &ClockInfo) -> Option<String> {
+    let time = Local.timestamp_opt(clock_info.timestamp, 0).single()?;
+    let format = format
+        .replace("{week}", &clock_info.week.to_string())
+        .replace("{yday}", &clock_info.day_of_year.to_string());
+
+    // The moon glyph is markup, so each piece around it is formatted and
+    // escaped on its own
+    let moon = icons::icon_markup(clock_info.moon.icon());
+    let mut pieces = vec![];
+    for piece in format.split("{moon}") {
+        let mut text = String::new();
+        write!(text, "{}", time.format(piece)).ok()?;
+        pieces.push(markup::escape(&text));
+    }
+    let mut text = pieces.join(&moon);
+
+    if let Some(alarm) = &clock_info.alarm {
+        text.push_str(&format!(
+            " <span foreground=\"{}\">{} {}</span>",
+            render::theme().critical.to_hex(),
+            icons::icon_markup(icons::icons().alarm),
+            markup::escape(alarm)
+        ));
+    }
+    Some(text)
+}
+
+impl Widget for Clock {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Clock {
+            attrs,
+            format,
+            alarms,
+            urgency,
+        } = *self;
+
+        // Message of the alarm going off, shared with the IPC command
+        let ringing: Arc<Mutex<Option<String>>> = Arc::default();
+
+        let dismiss = Arc::clone(&ringing);
+        ipc::register("clock", move |args| match args {
+            ["dismiss"] => {
+                dismiss.lock().unwrap().take();
+                state::bar().request_refresh();
+                Ok(String::new())
+            }
+            _ => bail!("usage: clock dismiss"),
+        });
+
+        let alarms_empty = alarms.is_empty();
+        let mut last: Option<NaiveDateTime> = None;
+        let collect = move || -> Result<ClockInfo> {
+            let now = Local::now();
+            let naive_now = now.naive_local();
+
+            // Alarms only go off once the clock is running, not for times
+            // already past when the bar starts
+            if let Some(last) = last {
+                for alarm in alarms.iter().filter(|alarm| alarm.due(last, naive_now)) {
+                    Action::Notify {
+                        summary: "Alarm".to_string(),
+                        body: alarm.message.clone(),
+                    }
+                    .spawn();
+                    *ringing.lock().unwrap() = Some(alarm.message.clone());
+                }
+            }
+            last = Some(naive_now);
+
+            let alarm = ringing.lock().unwrap().clone();
+            if let Some(urgency) = &urgency {
+                urgency.set(alarm.is_some());
+            }
+            Ok(ClockInfo::at(now, alarm))
+        };
+
+        let show = move |clock_info: ClockInfo| {
+            format_time(&format, &clock_info).unwrap_or_else(|| "invalid clock format".to_string())
+        };
+
+        let widget = PollingWidget::new(attrs, UPDATE_INTERVAL, collect, show)
+            .with_export("clock")
+            .with_pausing(alarms_empty);
+        Box::new(widget).into_stream()
+    }
+}
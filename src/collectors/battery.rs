@@ -0,0 +1,379 @@
+//! Battery charge, status and runtime estimate from
+//! `/sys/class/power_supply`, with a history of readings kept in the state
+//! directory. On FreeBSD and OpenBSD the battery is read through ACPI and
+//! APM instead, see [`bsd`].
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+use anyhow::Context;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+use crate::collectors::bsd;
+use crate::collectors::Collector;
+use crate::privileged::{self, Knob};
+use crate::{paths, state};
+
+/// Weight given to each new current reading in the discharge rate average.
+/// At a 30 second interval, readings older than about five minutes have
+/// little influence
+const RATE_SMOOTHING: f64 = 0.1;
+
+/// How far back samples are kept
+const HISTORY_SPAN: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// A saved discharge rate older than this no longer reflects the current
+/// load, e.g. after the laptop was suspended, and is discarded
+const STALE_RATE: Duration = Duration::from_secs(10 * 60);
+
+/// Threshold charging stops at, on ThinkPads, ASUS and other laptops whose
+/// firmware supports one
+pub(crate) const CHARGE_LIMIT: &str = "charge_control_end_threshold";
+
+/// A charge limit of 100% lets the battery charge fully
+pub const FULL: u64 = 100;
+
+/// How often the history is written to disk
+const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A capacity reading, timestamped in seconds since the Unix epoch
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BatterySample {
+    pub time: i64,
+    pub capacity: u64,
+}
+
+/// Recent readings, persisted so the averaged discharge rate and history
+/// survive restarts of the bar
+#[derive(Default, Serialize, Deserialize)]
+struct BatteryHistory {
+    /// Exponential moving average of the discharge current in µA, `None`
+    /// while not discharging
+    discharge_rate: Option<f64>,
+    samples: VecDeque<BatterySample>,
+}
+
+impl BatteryHistory {
+    fn load(name: &str) -> BatteryHistory {
+        let mut history: BatteryHistory = paths::read_state(name)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        let now = chrono::Utc::now().timestamp();
+        history.prune(now);
+        let newest = history
+            .samples
+            .back()
+            .map_or(i64::MIN, |sample| sample.time);
+        if now.saturating_sub(newest) > STALE_RATE.as_secs() as i64 {
+            history.discharge_rate = None;
+        }
+
+        history
+    }
+
+    fn save(&self, name: &str) -> Result<()> {
+        paths::write_state(name, &serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn prune(&mut self, now: i64) {
+        let oldest = now - HISTORY_SPAN.as_secs() as i64;
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.time < oldest)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// What the capacity percentage is relative to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CapacityBasis {
+    /// The `capacity` the kernel reports, relative to `charge_full`, which
+    /// shrinks as the battery wears
+    #[default]
+    Reported,
+    /// Relative to `charge_full_design`, so a worn battery never shows 100%
+    Design,
+}
+
+/// Battery statuses as written in `power_supply.h`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ChargeStatus {
+    Unknown,
+    Charging,
+    Discharging,
+    NotCharging,
+    Full,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatteryInfo {
+    pub status: ChargeStatus,
+    pub capacity: u64,
+    /// Estimated from the average discharge rate, zero while not discharging
+    pub time_till_empty: Duration,
+    /// Capacity readings from the last two hours, oldest first
+    pub history: Vec<BatterySample>,
+    /// Percentage charging stops at, to preserve the battery, `None` if the
+    /// firmware has no charge threshold
+    pub charge_limit: Option<u64>,
+    /// Supply plugged in, `None` on battery or if no supply reports its
+    /// rating
+    pub charger: Option<Charger>,
+}
+
+/// A mains or USB supply the battery charges from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Charger {
+    /// Most the supply can deliver, from its `voltage_max` and `current_max`
+    pub watts: f64,
+    /// USB type negotiated, such as `PD` or `PD_PPS`, `None` for barrel
+    /// chargers
+    pub usb_type: Option<String>,
+}
+
+impl BatteryInfo {
+    /// Whether charging stops before the battery is full, as conservation
+    /// mode does
+    #[must_use]
+    pub fn conserving(&self) -> bool {
+        self.charge_limit.is_some_and(|limit| limit < FULL)
+    }
+}
+
+/// A reading of the battery, before the runtime is estimated
+pub(crate) struct Reading {
+    pub(crate) status: ChargeStatus,
+    pub(crate) capacity: u64,
+    /// Discharge current in µA and remaining charge in µAh, `None` where the
+    /// firmware only reports a runtime
+    pub(crate) current: Option<(u64, u64)>,
+    /// Runtime as estimated by the firmware
+    pub(crate) time_left: Option<Duration>,
+}
+
+/// Reads a battery, keeping its history and averaged discharge rate
+pub struct BatteryCollector {
+    battery_path: PathBuf,
+    history: Arc<Mutex<BatteryHistory>>,
+    history_file: String,
+    last_saved: Instant,
+    basis: CapacityBasis,
+}
+
+impl BatteryCollector {
+    /// Creates a collector for the battery at `battery_path`, e.g.
+    /// `/sys/class/power_supply/BAT0`, loading its saved history. On the BSDs
+    /// the path only names the history
+    #[must_use]
+    pub fn new(battery_path: &Path) -> BatteryCollector {
+        let name = battery_path
+            .file_name()
+            .map_or_else(|| "battery".into(), |name| name.to_string_lossy());
+        let history_file = format!("battery-{name}.json");
+        let history = Arc::new(Mutex::new(BatteryHistory::load(&history_file)));
+
+        let pending = Arc::clone(&history);
+        let pending_file = history_file.clone();
+        state::bar().on_shutdown(move || {
+            if let Err(e) = pending.lock().unwrap().save(&pending_file) {
+                eprintln!("Could not save battery history: {e}");
+            }
+        });
+
+        BatteryCollector {
+            battery_path: battery_path.to_path_buf(),
+            history,
+            history_file,
+            last_saved: Instant::now(),
+            basis: CapacityBasis::Reported,
+        }
+    }
+
+    /// Sets what the capacity percentage is relative to
+    #[must_use]
+    pub fn with_capacity_basis(mut self, basis: CapacityBasis) -> BatteryCollector {
+        self.basis = basis;
+        self
+    }
+
+    #[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+    fn read(&self, name: &str) -> Result<String> {
+        let path = self.battery_path.join(name);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        Ok(contents.trim().to_string())
+    }
+
+    #[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+    fn read_number(&self, name: &str) -> Result<u64> {
+        self.read(name)?
+            .parse()
+            .with_context(|| format!("{name} did not contain integer data"))
+    }
+
+    #[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+    fn read_sysfs(&self) -> Result<Reading> {
+        let current_micro_amps = self.read_number("current_now")?;
+        let charge_micro_amp_hrs = self.read_number("charge_now")?;
+        let capacity = match self.basis {
+            CapacityBasis::Reported => self.read_number("capacity")?,
+            CapacityBasis::Design => {
+                let design_micro_amp_hrs = self.read_number("charge_full_design")?;
+                (100 * charge_micro_amp_hrs / design_micro_amp_hrs.max(1)).min(FULL)
+            }
+        };
+        let status = match self.read("status")?.as_str() {
+            "Charging" => ChargeStatus::Charging,
+            "Discharging" => ChargeStatus::Discharging,
+            "Full" => ChargeStatus::Full,
+            "Not Charging" => ChargeStatus::NotCharging,
+            _ => ChargeStatus::Unknown,
+        };
+
+        Ok(Reading {
+            status,
+            capacity,
+            current: Some((current_micro_amps, charge_micro_amp_hrs)),
+            time_left: None,
+        })
+    }
+}
+
+impl Collector for BatteryCollector {
+    type Output = BatteryInfo;
+
+    fn collect(&mut self) -> Result<BatteryInfo> {
+        #[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+        let reading = self.read_sysfs()?;
+        #[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+        let reading = bsd::battery(self.basis)?;
+
+        // The instantaneous current swings with load, so estimate from its
+        // average instead
+        let mut history = self.history.lock().unwrap();
+        history.discharge_rate = match (&reading.status, reading.current, history.discharge_rate) {
+            (ChargeStatus::Discharging, Some((current_micro_amps, _)), Some(rate)) => {
+                Some(rate + RATE_SMOOTHING * (current_micro_amps as f64 - rate))
+            }
+            (ChargeStatus::Discharging, Some((current_micro_amps, _)), None) => {
+                Some(current_micro_amps as f64)
+            }
+            _ => None,
+        };
+        // Firmware which doesn't report the current estimates the runtime
+        // itself
+        let estimated_duration = match (reading.time_left, reading.current, history.discharge_rate)
+        {
+            (Some(time_left), _, _) => time_left,
+            (None, Some((_, charge_micro_amp_hrs)), Some(rate)) if rate > 0.0 => {
+                Duration::try_from_secs_f64(3600.0 * charge_micro_amp_hrs as f64 / rate)
+                    .unwrap_or(Duration::MAX)
+            }
+            _ => Duration::ZERO,
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        history.samples.push_back(BatterySample {
+            time: now,
+            capacity: reading.capacity,
+        });
+        history.prune(now);
+        let samples = history.samples.iter().copied().collect();
+
+        if self.last_saved.elapsed() >= SAVE_INTERVAL {
+            let _ = history.save(&self.history_file);
+            self.last_saved = Instant::now();
+        }
+
+        Ok(BatteryInfo {
+            status: reading.status,
+            capacity: reading.capacity,
+            time_till_empty: estimated_duration,
+            history: samples,
+            charge_limit: charge_limit(&self.battery_path),
+            charger: charger(),
+        })
+    }
+}
+
+/// Percentage the battery at `battery_path` stops charging at, `None` if its
+/// firmware has no charge threshold
+#[must_use]
+pub fn charge_limit(battery_path: &Path) -> Option<u64> {
+    fs::read_to_string(battery_path.join(CHARGE_LIMIT))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Sets the percentage the battery at `battery_path` stops charging at. The
+/// threshold is only writable by root, see [`crate::privileged`]
+pub fn set_charge_limit(battery_path: &Path, percent: u64) -> Result<()> {
+    let battery = battery_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Battery path has no name")?;
+    privileged::set(&Knob::ChargeLimit {
+        battery: battery.to_string(),
+        percent,
+    })
+}
+
+/// The most powerful mains or USB supply online, if any reports its rating
+#[must_use]
+pub fn charger() -> Option<Charger> {
+    let supplies = fs::read_dir("/sys/class/power_supply").ok()?;
+
+    supplies
+        .flatten()
+        .filter_map(|supply| {
+            let read = |name: &str| fs::read_to_string(supply.path().join(name)).ok();
+            let number = |name: &str| read(name)?.trim().parse::<f64>().ok();
+
+            let kind = read("type")?;
+            if !matches!(kind.trim(), "Mains" | "USB") || read("online")?.trim() != "1" {
+                return None;
+            }
+            // Microvolts and microamps
+            let watts = number("voltage_max")? * number("current_max")? / 1e12;
+            if watts <= 0.0 {
+                return None;
+            }
+
+            // Supported types, with the one in use in brackets, e.g.
+            // `C [PD] PD_PPS`
+            let usb_type = read("usb_type").and_then(|types| {
+                types
+                    .split_whitespace()
+                    .find_map(|usb_type| usb_type.strip_prefix('[')?.strip_suffix(']'))
+                    .map(str::to_string)
+            });
+            Some(Charger { watts, usb_type })
+        })
+        .max_by(|a, b| a.watts.total_cmp(&b.watts))
+}
+
+/// Whether any mains power supply is online
+pub fn on_ac_power() -> bool {
+    let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+
+    supplies.flatten().any(|supply| {
+        let read = |name: &str| fs::read_to_string(supply.path().join(name)).unwrap_or_default();
+        read("type").trim() == "Mains" && read("online").trim() == "1"
+    })
+}
@@ -0,0 +1,268 @@
+//! Battery and memory readings on FreeBSD and OpenBSD, which have neither
+//! sysfs nor procfs. They are read from the kernel directly rather than by
+//! running `sysctl`, `apm` or `swapctl` on every tick.
+//!
+//! FreeBSD names everything through `sysctlbyname`: the battery through ACPI,
+//! memory through the VM statistics and swap through `vm.swap_info`. OpenBSD
+//! has no `sysctlbyname`, so memory and swap come from the `vm.uvmexp` MIB
+//! and the battery from the `APM_IOC_GETPOWER` ioctl on `/dev/apm`.
+
+#[cfg(target_os = "openbsd")]
+use std::fs::File;
+use std::io;
+use std::mem;
+#[cfg(target_os = "openbsd")]
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use byte_unit::Byte;
+
+use crate::collectors::battery::{CapacityBasis, ChargeStatus, Reading};
+use crate::collectors::memory::MemoryInfo;
+
+/// The value `sysctl` would print for `name`, which must be a `T`
+#[cfg(target_os = "freebsd")]
+fn sysctl<T: Copy + Default>(name: &str) -> Result<T> {
+    let name = std::ffi::CString::new(name)?;
+    let mut value = T::default();
+    let mut size = mem::size_of::<T>();
+    // SAFETY: the kernel writes at most `size` bytes to `value`
+    let result = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            (&mut value as *mut T).cast(),
+            &mut size,
+            std::ptr::null(),
+            0,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Could not read sysctl {name:?}"));
+    }
+    if size != mem::size_of::<T>() {
+        bail!(
+            "sysctl {name:?} is {size} bytes, expected {}",
+            mem::size_of::<T>()
+        );
+    }
+    Ok(value)
+}
+
+/// Reads the first battery through ACPI. The firmware only reports a
+/// percentage and a runtime, so the percentage is always relative to the
+/// last full charge
+#[cfg(target_os = "freebsd")]
+pub(crate) fn battery(basis: CapacityBasis) -> Result<Reading> {
+    if basis == CapacityBasis::Design {
+        bail!("the design capacity isn't available on FreeBSD");
+    }
+
+    let capacity = sysctl::<libc::c_int>("hw.acpi.battery.life")?;
+    // Bit 0 is set while discharging and bit 1 while charging
+    let state = sysctl::<libc::c_int>("hw.acpi.battery.state")?;
+    let status = if state & 1 != 0 {
+        ChargeStatus::Discharging
+    } else if state & 2 != 0 {
+        ChargeStatus::Charging
+    } else if capacity >= 100 {
+        ChargeStatus::Full
+    } else {
+        ChargeStatus::NotCharging
+    };
+    // Minutes, -1 unless discharging
+    let minutes = sysctl::<libc::c_int>("hw.acpi.battery.time")?;
+
+    Ok(Reading {
+        status,
+        capacity: capacity.clamp(0, 100) as u64,
+        current: None,
+        time_left: u64::try_from(minutes)
+            .ok()
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+    })
+}
+
+/// `struct apm_power_info` from `<machine/apmvar.h>`
+#[cfg(target_os = "openbsd")]
+#[repr(C)]
+#[derive(Default)]
+struct ApmPowerInfo {
+    battery_state: u8,
+    ac_state: u8,
+    battery_life: u8,
+    spare1: u8,
+    minutes_left: u32,
+    spare2: [u32; 6],
+}
+
+/// `_IOR('A', 3, struct apm_power_info)`
+#[cfg(target_os = "openbsd")]
+const APM_IOC_GETPOWER: libc::c_ulong = 0x4000_0000
+    | ((mem::size_of::<ApmPowerInfo>() as libc::c_ulong & 0x1fff) << 16)
+    | ((b'A' as libc::c_ulong) << 8)
+    | 3;
+
+/// Reads the battery through APM. The firmware only reports a percentage and
+/// a runtime, so the percentage is always relative to the last full charge
+#[cfg(target_os = "openbsd")]
+pub(crate) fn battery(basis: CapacityBasis) -> Result<Reading> {
+    if basis == CapacityBasis::Design {
+        bail!("the design capacity isn't available on OpenBSD");
+    }
+
+    let apm = File::open("/dev/apm").context("Could not open /dev/apm")?;
+    let mut info = ApmPowerInfo::default();
+    // SAFETY: the ioctl fills in an `apm_power_info`, which `info` is laid
+    // out as
+    let result = unsafe { libc::ioctl(apm.as_raw_fd(), APM_IOC_GETPOWER, &mut info) };
+    if result != 0 {
+        return Err(io::Error::last_os_error()).context("Could not read the APM power state");
+    }
+
+    let capacity = u64::from(info.battery_life.min(100));
+    // 0 to 2 are high, low and critical, 3 is charging and 4 is absent
+    let on_ac = info.ac_state == 1;
+    let status = match info.battery_state {
+        3 => ChargeStatus::Charging,
+        4 => bail!("no battery present"),
+        _ if !on_ac => ChargeStatus::Discharging,
+        _ if capacity >= 100 => ChargeStatus::Full,
+        0..=2 => ChargeStatus::NotCharging,
+        _ => ChargeStatus::Unknown,
+    };
+
+    Ok(Reading {
+        status,
+        capacity,
+        current: None,
+        // Only estimated while discharging
+        time_left: matches!(status, ChargeStatus::Discharging)
+            .then(|| Duration::from_secs(u64::from(info.minutes_left) * 60)),
+    })
+}
+
+/// `struct xswdev` from `<vm/vm_param.h>`, one swap device
+#[cfg(target_os = "freebsd")]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct SwapDevice {
+    version: libc::c_uint,
+    dev: u64,
+    flags: libc::c_int,
+    /// Size in pages
+    blocks: libc::c_int,
+    /// Pages in use
+    used: libc::c_int,
+}
+
+/// Swap total and used in pages, summed over the `vm.swap_info.<n>` nodes
+#[cfg(target_os = "freebsd")]
+fn swap_pages() -> Result<(u64, u64)> {
+    let name = std::ffi::CString::new("vm.swap_info")?;
+    let mut mib = [0 as libc::c_int; 3];
+    let mut length = 2;
+    // SAFETY: the kernel writes at most `length` entries to `mib`
+    if unsafe { libc::sysctlnametomib(name.as_ptr(), mib.as_mut_ptr(), &mut length) } != 0 {
+        return Err(io::Error::last_os_error()).context("Could not look up vm.swap_info");
+    }
+
+    let (mut total, mut used) = (0, 0);
+    // Devices are numbered from 0, and the first missing one ends the list
+    for index in 0.. {
+        mib[length] = index;
+        let mut device = SwapDevice::default();
+        let mut size = mem::size_of::<SwapDevice>();
+        // SAFETY: the kernel writes at most `size` bytes to `device`
+        let result = unsafe {
+            libc::sysctl(
+                mib.as_ptr(),
+                length as libc::c_uint + 1,
+                (&mut device as *mut SwapDevice).cast(),
+                &mut size,
+                std::ptr::null(),
+                0,
+            )
+        };
+        if result != 0 {
+            break;
+        }
+        total += device.blocks.max(0) as u64;
+        used += device.used.max(0) as u64;
+    }
+    Ok((total, used))
+}
+
+/// Memory and swap usage. Free and inactive pages count as available, as the
+/// kernel can reclaim inactive pages for new allocations
+#[cfg(target_os = "freebsd")]
+pub(crate) fn memory() -> Result<MemoryInfo> {
+    let total_memory = sysctl::<libc::c_ulong>("hw.physmem")? as u64;
+    let page_size = sysctl::<libc::c_int>("hw.pagesize")? as u64;
+    let available_pages = u64::from(sysctl::<libc::c_uint>("vm.stats.vm.v_free_count")?)
+        + u64::from(sysctl::<libc::c_uint>("vm.stats.vm.v_inactive_count")?);
+    let (total_swap, used_swap) = swap_pages()?;
+
+    Ok(MemoryInfo {
+        used_memory: Byte::from_u64(total_memory.saturating_sub(available_pages * page_size)),
+        total_memory: Byte::from_u64(total_memory),
+        used_swap: Byte::from_u64(used_swap * page_size),
+        total_swap: Byte::from_u64(total_swap * page_size),
+        pressure: None,
+    })
+}
+
+/// Indices into `struct uvmexp` from `<uvm/uvmexp.h>`, whose leading fields
+/// are all `int`s
+#[cfg(target_os = "openbsd")]
+mod uvmexp {
+    pub const PAGESIZE: usize = 0;
+    pub const NPAGES: usize = 3;
+    pub const FREE: usize = 4;
+    pub const INACTIVE: usize = 6;
+    pub const SWPAGES: usize = 26;
+    pub const SWPGINUSE: usize = 27;
+    /// Fields read, enough to cover the ones above
+    pub const LEN: usize = 28;
+}
+
+/// Memory and swap usage. Free and inactive pages count as available, as the
+/// kernel can reclaim inactive pages for new allocations
+#[cfg(target_os = "openbsd")]
+pub(crate) fn memory() -> Result<MemoryInfo> {
+    // The struct is larger than the fields read, so ask for all of it
+    let mut fields = [0 as libc::c_int; 128];
+    let mut size = mem::size_of_val(&fields);
+    let mib = [libc::CTL_VM, libc::VM_UVMEXP];
+    // SAFETY: the kernel writes at most `size` bytes to `fields`
+    let result = unsafe {
+        libc::sysctl(
+            mib.as_ptr(),
+            2,
+            fields.as_mut_ptr().cast(),
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error()).context("Could not read vm.uvmexp");
+    }
+    if size < uvmexp::LEN * mem::size_of::<libc::c_int>() {
+        bail!("vm.uvmexp is only {size} bytes");
+    }
+
+    let field = |index: usize| fields[index].max(0) as u64;
+    let page_size = field(uvmexp::PAGESIZE);
+    let total_memory = field(uvmexp::NPAGES) * page_size;
+    let available = (field(uvmexp::FREE) + field(uvmexp::INACTIVE)) * page_size;
+
+    Ok(MemoryInfo {
+        used_memory: Byte::from_u64(total_memory.saturating_sub(available)),
+        total_memory: Byte::from_u64(total_memory),
+        used_swap: Byte::from_u64(field(uvmexp::SWPGINUSE) * page_size),
+        total_swap: Byte::from_u64(field(uvmexp::SWPAGES) * page_size),
+        pressure: None,
+    })
+}
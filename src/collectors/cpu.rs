@@ -0,0 +1,106 @@
+//! CPU usage from `/proc/stat` and pressure from `/proc/pressure/cpu`.
+
+use std::fs;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::collectors::Collector;
+use crate::color;
+use crate::psi::{self, Pressure};
+
+/// Cumulative jiffies from the aggregate `cpu` line of `/proc/stat`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuTimes {
+    pub total: u64,
+    /// Idle time, including time spent waiting on I/O
+    pub idle: u64,
+    pub iowait: u64,
+    pub steal: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CpuInfo {
+    /// Percentage of time spent busy since the last tick
+    pub usage: f64,
+    /// Percentage of time idle with I/O outstanding since the last tick
+    pub iowait: f64,
+    /// Percentage of time stolen by the hypervisor since the last tick
+    pub steal: f64,
+    /// CPU pressure, `None` on kernels without PSI support
+    pub pressure: Option<Pressure>,
+}
+
+/// Measures CPU usage between successive calls to
+/// [`collect`](Collector::collect)
+pub struct CpuCollector {
+    previous: CpuTimes,
+}
+
+impl CpuCollector {
+    #[must_use]
+    pub fn new() -> CpuCollector {
+        CpuCollector {
+            previous: read_times().unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for CpuCollector {
+    fn default() -> CpuCollector {
+        CpuCollector::new()
+    }
+}
+
+impl Collector for CpuCollector {
+    type Output = CpuInfo;
+
+    fn collect(&mut self) -> Result<CpuInfo> {
+        let times = read_times()?;
+
+        let total = times.total.saturating_sub(self.previous.total);
+        let share = |now: u64, before: u64| color::percentage(now.saturating_sub(before), total);
+
+        let cpu_info = CpuInfo {
+            usage: if total == 0 {
+                0.0
+            } else {
+                100.0 - share(times.idle, self.previous.idle)
+            },
+            iowait: share(times.iowait, self.previous.iowait),
+            steal: share(times.steal, self.previous.steal),
+            pressure: psi::read("cpu").ok(),
+        };
+        self.previous = times;
+
+        Ok(cpu_info)
+    }
+}
+
+fn read_times() -> Result<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat")?;
+    Ok(parse_times(&stat))
+}
+
+/// Parses the aggregate line of `/proc/stat`, which looks like
+/// `cpu  user nice system idle iowait irq softirq steal guest guest_nice`
+#[must_use]
+pub fn parse_times(stat: &str) -> CpuTimes {
+    let fields: Vec<u64> = stat
+        .lines()
+        .find(|line| line.starts_with("cpu "))
+        .unwrap_or_default()
+        .split_whitespace()
+        .skip(1)
+        .map(|field| field.parse().unwrap_or(0))
+        .collect();
+    let field = |index: usize| fields.get(index).copied().unwrap_or(0);
+
+    // Guest time is already accounted for in user and nice
+    CpuTimes {
+        total: (0..8).map(field).sum(),
+        idle: field(3) + field(4),
+        iowait: field(4),
+        steal: field(7),
+    }
+}
@@ -0,0 +1,67 @@
+//! Space used on mounted filesystems through `sysinfo`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use byte_unit::Byte;
+use serde::{Deserialize, Serialize};
+use sysinfo::Disks;
+
+use crate::collectors::{byte_count, Collector};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiskInfo {
+    pub mount_point: PathBuf,
+    #[serde(with = "byte_count")]
+    pub used: Byte,
+    #[serde(with = "byte_count")]
+    pub total: Byte,
+    /// Space available to unprivileged users. Blocks reserved for root count
+    /// towards `used`
+    #[serde(with = "byte_count")]
+    pub available: Byte,
+}
+
+/// Reads the filesystems mounted at a list of mount points, in that order.
+/// Mount points that aren't mounted are left out
+pub struct DiskCollector {
+    mount_points: Vec<PathBuf>,
+}
+
+impl DiskCollector {
+    #[must_use]
+    pub fn new(mount_points: &[impl AsRef<Path>]) -> DiskCollector {
+        DiskCollector {
+            mount_points: mount_points
+                .iter()
+                .map(|mount_point| mount_point.as_ref().to_path_buf())
+                .collect(),
+        }
+    }
+}
+
+impl Collector for DiskCollector {
+    type Output = Vec<DiskInfo>;
+
+    fn collect(&mut self) -> Result<Vec<DiskInfo>> {
+        // Listed afresh each time, so filesystems mounted later are picked up
+        let disks = Disks::new_with_refreshed_list();
+
+        Ok(self
+            .mount_points
+            .iter()
+            .filter_map(|mount_point| {
+                let disk = disks
+                    .iter()
+                    .find(|disk| disk.mount_point() == mount_point)?;
+
+                Some(DiskInfo {
+                    mount_point: mount_point.clone(),
+                    used: Byte::from_u64(disk.total_space().saturating_sub(disk.available_space())),
+                    total: Byte::from_u64(disk.total_space()),
+                    available: Byte::from_u64(disk.available_space()),
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,136 @@
+//! Memory and swap usage from `/proc/meminfo`, and pressure from
+//! `/proc/pressure/memory`. On FreeBSD and OpenBSD usage comes from
+//! [`bsd`](super::bsd) instead, and there is no pressure.
+
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+use std::fs;
+
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+use anyhow::anyhow;
+use anyhow::Result;
+use byte_unit::Byte;
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+use crate::collectors::bsd::memory as read_memory;
+use crate::collectors::{byte_count, Collector};
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+use crate::psi;
+use crate::psi::Pressure;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryInfo {
+    /// Memory in use: the total less what is available for new allocations,
+    /// counting reclaimable memory such as page cache as available. This is
+    /// not the total less free memory, which counts the cache as used
+    #[serde(with = "byte_count")]
+    pub used_memory: Byte,
+    #[serde(with = "byte_count")]
+    pub total_memory: Byte,
+    #[serde(with = "byte_count")]
+    pub used_swap: Byte,
+    #[serde(with = "byte_count")]
+    pub total_swap: Byte,
+    /// Memory pressure, `None` on kernels without PSI support
+    pub pressure: Option<Pressure>,
+}
+
+pub struct MemoryCollector;
+
+impl MemoryCollector {
+    #[must_use]
+    pub fn new() -> MemoryCollector {
+        MemoryCollector
+    }
+}
+
+impl Default for MemoryCollector {
+    fn default() -> MemoryCollector {
+        MemoryCollector::new()
+    }
+}
+
+/// The value of `field` in `/proc/meminfo`, which is in KiB, e.g.
+/// `MemTotal:       16314896 kB`
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+fn field(meminfo: &str, field: &str) -> Result<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+        .map(|kibibytes| kibibytes * 1024)
+        .ok_or_else(|| anyhow!("no {field} in /proc/meminfo"))
+}
+
+/// Usage from the contents of `/proc/meminfo`, without pressure
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+fn parse_meminfo(meminfo: &str) -> Result<MemoryInfo> {
+    let total_memory = field(meminfo, "MemTotal")?;
+    // Memory the kernel could free for new allocations, such as page
+    // cache, doesn't count as used
+    let available_memory = field(meminfo, "MemAvailable")?;
+    let total_swap = field(meminfo, "SwapTotal")?;
+    let free_swap = field(meminfo, "SwapFree")?;
+
+    Ok(MemoryInfo {
+        used_memory: Byte::from_u64(total_memory.saturating_sub(available_memory)),
+        total_memory: Byte::from_u64(total_memory),
+        used_swap: Byte::from_u64(total_swap.saturating_sub(free_swap)),
+        total_swap: Byte::from_u64(total_swap),
+        pressure: None,
+    })
+}
+
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+fn read_memory() -> Result<MemoryInfo> {
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    Ok(MemoryInfo {
+        pressure: psi::read("memory").ok(),
+        ..parse_meminfo(&meminfo)?
+    })
+}
+
+impl Collector for MemoryCollector {
+    type Output = MemoryInfo;
+
+    fn collect(&mut self) -> Result<MemoryInfo> {
+        read_memory()
+    }
+}
+
+#[cfg(all(test, not(any(target_os = "freebsd", target_os = "openbsd"))))]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = "\
+MemTotal:       16000000 kB
+MemFree:         1000000 kB
+MemAvailable:   12000000 kB
+Buffers:          500000 kB
+Cached:         10000000 kB
+SwapTotal:       2000000 kB
+SwapFree:        1500000 kB
+";
+
+    #[test]
+    fn used_memory_excludes_available_memory() {
+        let memory = parse_meminfo(MEMINFO).unwrap();
+
+        // MemTotal - MemAvailable, not MemTotal - MemFree or MemFree itself
+        assert_eq!(memory.used_memory.as_u64(), 4_000_000 * 1024);
+        assert_eq!(memory.total_memory.as_u64(), 16_000_000 * 1024);
+    }
+
+    #[test]
+    fn used_swap_is_total_less_free() {
+        let memory = parse_meminfo(MEMINFO).unwrap();
+
+        assert_eq!(memory.used_swap.as_u64(), 500_000 * 1024);
+        assert_eq!(memory.total_swap.as_u64(), 2_000_000 * 1024);
+    }
+
+    #[test]
+    fn missing_field_is_an_error() {
+        assert!(parse_meminfo("MemTotal: 16000000 kB\n").is_err());
+    }
+}
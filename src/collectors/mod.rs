@@ -0,0 +1,42 @@
+//! Data collection for the system widgets, kept apart from rendering so the
+//! same readings can be used by the bar, [`crate::export`] and anything else
+//! that wants typed data.
+//!
+//! Each widget module re-exports the types of its collector, so e.g.
+//! [`crate::battery::BatteryInfo`] is still available where it always was.
+
+pub mod battery;
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+mod bsd;
+pub mod cpu;
+#[cfg(feature = "sysinfo")]
+pub mod disk;
+pub mod memory;
+#[cfg(feature = "dbus")]
+pub mod power;
+
+use anyhow::Result;
+
+/// A source of readings, sampled once per widget tick
+pub trait Collector {
+    type Output;
+
+    /// Takes a reading. Collectors which compute rates do so relative to the
+    /// previous call
+    fn collect(&mut self) -> Result<Self::Output>;
+}
+
+/// Serializes [`byte_unit::Byte`] as a number of bytes, which is easier for
+/// other programs to consume than a human readable size
+pub(crate) mod byte_count {
+    use byte_unit::Byte;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Byte, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(bytes.as_u64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Byte, D::Error> {
+        u64::deserialize(deserializer).map(Byte::from_u64)
+    }
+}
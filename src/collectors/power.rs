@@ -0,0 +1,103 @@
+//! Combined power state: the battery, whether mains power is connected, and
+//! the active power profile from power-profiles-daemon.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use zbus::blocking::{Connection, Proxy};
+
+use crate::collectors::battery::{on_ac_power, BatteryCollector, BatteryInfo};
+use crate::collectors::Collector;
+
+const POWER_PROFILES: &str = "net.hadess.PowerProfiles";
+const POWER_PROFILES_PATH: &str = "/net/hadess/PowerProfiles";
+
+/// Kernel interface to the firmware's profile, for systems without
+/// power-profiles-daemon
+const PLATFORM_PROFILE: &str = "/sys/firmware/acpi/platform_profile";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerProfile {
+    PowerSaver,
+    Balanced,
+    Performance,
+}
+
+impl PowerProfile {
+    /// Parses the profile names of power-profiles-daemon and the kernel
+    fn parse(name: &str) -> Option<PowerProfile> {
+        match name.trim() {
+            "power-saver" | "low-power" | "quiet" | "cool" => Some(PowerProfile::PowerSaver),
+            "balanced" | "balanced-performance" => Some(PowerProfile::Balanced),
+            "performance" => Some(PowerProfile::Performance),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PowerInfo {
+    /// `None` on systems without a battery
+    pub battery: Option<BatteryInfo>,
+    pub on_ac: bool,
+    /// `None` if neither power-profiles-daemon nor the kernel reports one
+    pub profile: Option<PowerProfile>,
+}
+
+pub struct PowerCollector {
+    battery: Option<BatteryCollector>,
+    connection: Option<Connection>,
+}
+
+impl PowerCollector {
+    /// Creates a collector reading the battery at `battery_path`, if any
+    #[must_use]
+    pub fn new(battery_path: Option<&Path>) -> PowerCollector {
+        PowerCollector {
+            battery: battery_path.map(BatteryCollector::new),
+            connection: None,
+        }
+    }
+
+    fn profile(&mut self) -> Option<PowerProfile> {
+        self.daemon_profile()
+            .ok()
+            .or_else(|| fs::read_to_string(PLATFORM_PROFILE).ok())
+            .and_then(|name| PowerProfile::parse(&name))
+    }
+
+    fn daemon_profile(&mut self) -> Result<String> {
+        // Connect lazily so the widget recovers if the bus wasn't up yet
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Connection::system()?),
+        };
+
+        let proxy = Proxy::new(
+            connection,
+            POWER_PROFILES,
+            POWER_PROFILES_PATH,
+            POWER_PROFILES,
+        )?;
+        Ok(proxy.get_property("ActiveProfile")?)
+    }
+}
+
+impl Collector for PowerCollector {
+    type Output = PowerInfo;
+
+    fn collect(&mut self) -> Result<PowerInfo> {
+        let battery = match &mut self.battery {
+            Some(battery) => Some(battery.collect()?),
+            None => None,
+        };
+
+        Ok(PowerInfo {
+            battery,
+            on_ac: on_ac_power(),
+            profile: self.profile(),
+        })
+    }
+}
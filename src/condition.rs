@@ -0,0 +1,263 @@
+//! Conditions under which a widget is on the bar, so that one config can be
+//! shared between machines:
+//!
+//! ```toml
+//! [widgets.battery]
+//! enabled_if = "hostname == 'laptop'"
+//!
+//! [widgets.workspaces]
+//! enabled_if = "env:DESKTOP_SESSION == 'i3' || env:DESKTOP_SESSION == 'sway'"
+//! ```
+//!
+//! Values are `hostname`, `user`, `env:NAME` for the environment variable
+//! `NAME`, and strings in single or double quotes. They are compared with
+//! `==` and `!=`, and comparisons combine with `&&`, `||`, `!` and
+//! parentheses. A value on its own is true when it is set and not empty, so
+//! `env:SSH_CONNECTION` holds in SSH sessions.
+
+use std::env;
+use std::fs;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    Variable(Variable),
+    Equal,
+    NotEqual,
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Variable {
+    Hostname,
+    User,
+    Env(String),
+}
+
+impl Variable {
+    /// The value on this machine, `None` if it isn't set
+    fn value(&self) -> Option<String> {
+        match self {
+            Variable::Hostname => fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .map(|hostname| hostname.trim().to_string()),
+            Variable::User => env::var("USER").ok(),
+            Variable::Env(name) => env::var(name).ok(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Literal(String),
+    Variable(Variable),
+}
+
+impl Operand {
+    fn value(&self) -> Option<String> {
+        match self {
+            Operand::Literal(literal) => Some(literal.clone()),
+            Operand::Variable(variable) => variable.value(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Set(Operand),
+    Compare {
+        left: Operand,
+        right: Operand,
+        equal: bool,
+    },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn holds(&self) -> bool {
+        match self {
+            Expr::Set(operand) => operand.value().is_some_and(|value| !value.is_empty()),
+            // An unset variable equals nothing, not even the empty string
+            Expr::Compare { left, right, equal } => match (left.value(), right.value()) {
+                (Some(left), Some(right)) => (left == right) == *equal,
+                _ => !equal,
+            },
+            Expr::Not(expr) => !expr.holds(),
+            Expr::And(left, right) => left.holds() && right.holds(),
+            Expr::Or(left, right) => left.holds() || right.holds(),
+        }
+    }
+}
+
+/// A parsed condition, see the [module documentation](self)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Condition {
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(condition: &str) -> Result<Condition> {
+        let tokens = tokenize(condition)?;
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+        };
+
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.next() {
+            bail!("unexpected {token:?} in condition {condition:?}");
+        }
+        Ok(Condition { expr })
+    }
+
+    /// Whether the condition holds on this machine
+    #[must_use]
+    pub fn holds(&self) -> bool {
+        self.expr.holds()
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = anyhow::Error;
+
+    fn try_from(condition: String) -> Result<Condition> {
+        Condition::parse(&condition)
+    }
+}
+
+fn tokenize(condition: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = condition.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Equal,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::NotEqual,
+            '!' => Token::Not,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '\'' | '"' => {
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(c) => literal.push(c),
+                        None => bail!("unclosed string in condition {condition:?}"),
+                    }
+                }
+                Token::Literal(literal)
+            }
+            c if c.is_alphabetic() => {
+                let name = word(c, &mut chars);
+                Token::Variable(match name.as_str() {
+                    "hostname" => Variable::Hostname,
+                    "user" => Variable::User,
+                    "env" if chars.next_if_eq(&':').is_some() => {
+                        let Some(first) = chars.next() else {
+                            bail!("no variable name after `env:` in condition {condition:?}");
+                        };
+                        Variable::Env(word(first, &mut chars))
+                    }
+                    _ => bail!(
+                        "unknown value `{name}` in condition {condition:?}, expected hostname, user or env:NAME"
+                    ),
+                })
+            }
+            c => bail!("unexpected `{c}` in condition {condition:?}"),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// `first` followed by the letters, digits and underscores after it
+fn word(first: char, chars: &mut Peekable<Chars<'_>>) -> String {
+    let mut word = first.to_string();
+    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+        word.push(c);
+    }
+    word
+}
+
+/// Recursive descent over the tokens, with `||` binding loosest, then `&&`,
+/// then `!`
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.tokens.next_if_eq(&Token::And).is_some() {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        match self.tokens.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.not()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => bail!("unclosed `(` in condition"),
+                }
+            }
+            Some(token) => self.comparison(token),
+            None => bail!("condition ends early"),
+        }
+    }
+
+    fn comparison(&mut self, token: Token) -> Result<Expr> {
+        let left = operand(token)?;
+        let equal = match self
+            .tokens
+            .next_if(|token| matches!(token, Token::Equal | Token::NotEqual))
+        {
+            Some(token) => token == Token::Equal,
+            None => return Ok(Expr::Set(left)),
+        };
+
+        let right = self
+            .tokens
+            .next()
+            .ok_or_else(|| anyhow!("condition ends after a comparison"))?;
+        Ok(Expr::Compare {
+            left,
+            right: operand(right)?,
+            equal,
+        })
+    }
+}
+
+fn operand(token: Token) -> Result<Operand> {
+    match token {
+        Token::Literal(literal) => Ok(Operand::Literal(literal)),
+        Token::Variable(variable) => Ok(Operand::Variable(variable)),
+        token => bail!("expected a value in condition, found {token:?}"),
+    }
+}
@@ -0,0 +1,634 @@
+//! User configuration, read from `config.toml` in [`paths::config_dir`].
+//!
+//! ```toml
+//! icon_set = "nerd"
+//!
+//! [font]
+//! family = "monospace"
+//! size = 11
+//! icon_family = "Symbols Nerd Font"
+//!
+//! [widgets.clock.font]
+//! size = 14
+//!
+//! [theme]
+//! muted = "#5c6370"
+//! critical = "tomato"
+//!
+//! [themes.light]
+//! foreground = "#383a42"
+//!
+//! [appearance]
+//! source = "schedule"
+//! light = "light"
+//! day = "07:30"
+//! night = "19:00"
+//!
+//! [export]
+//! address = "127.0.0.1:9273"
+//!
+//! [http]
+//! proxy = "http://proxy.corp.example:3128"
+//!
+//! [widgets.cpu]
+//! template = "{icon} {usage|fixed(0)|color}%"
+//! interval = "5s"
+//!
+//! [widgets.battery]
+//! enabled_if = "hostname == 'laptop'"
+//!
+//! [profiles.presentation]
+//! hide = ["cpu", "memory"]
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use byte_unit::Byte;
+use chrono::NaiveTime;
+use cnx::text::{Color, Font};
+use serde::{Deserialize, Deserializer};
+
+use crate::actions::Action;
+use crate::appearance::{self, AppearanceSource, Schedule, Trigger};
+use crate::condition::Condition;
+use crate::icons::IconSet;
+use crate::presets::Preset;
+use crate::render::Theme;
+use crate::secrets::Secret;
+use crate::template::Template;
+use crate::{color, paths};
+
+pub const DEFAULT_FONT: &str = "monospace";
+
+/// Commented config written by `status_bar init`. Everything in it is
+/// commented out, so it is equivalent to [`Config::default`]
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Font used by every widget unless overridden
+    pub font: FontConfig,
+    /// Per-widget settings, keyed by widget name
+    pub widgets: BTreeMap<String, WidgetConfig>,
+    pub theme: ThemeConfig,
+    /// Named themes, each applied on top of [`Config::theme`]
+    pub themes: BTreeMap<String, ThemeConfig>,
+    pub appearance: AppearanceConfig,
+    pub export: ExportConfig,
+    pub http: HttpConfig,
+    /// Profile applied unless another is picked over IPC, see
+    /// [`crate::profiles`]
+    pub profile: Option<String>,
+    /// Named profiles, each applied on top of the rest of the config
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// Keep widgets polling while the screen is off or the session is
+    /// locked, see [`crate::dormant`]
+    pub keep_polling: bool,
+    /// Count the machine as offline without a default route, as well as
+    /// without a carrier, see [`crate::offline`]
+    pub offline_requires_route: bool,
+    /// Icons used by the default renders
+    pub icon_set: IconSet,
+    /// The profile applied with [`Config::apply_profile`]
+    #[serde(skip)]
+    active_profile: Option<String>,
+}
+
+/// Any field left unset falls back to the global [`Config::font`]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    pub family: Option<String>,
+    pub size: Option<f64>,
+    /// Font used for icon glyphs, such as a Nerd Font symbols-only font
+    pub icon_family: Option<String>,
+}
+
+impl FontConfig {
+    /// Replaces the fields set in `overrides`
+    fn merge(&mut self, overrides: &FontConfig) {
+        if overrides.family.is_some() {
+            self.family.clone_from(&overrides.family);
+        }
+        if overrides.size.is_some() {
+            self.size = overrides.size;
+        }
+        if overrides.icon_family.is_some() {
+            self.icon_family.clone_from(&overrides.icon_family);
+        }
+    }
+}
+
+/// Colours of the [`Theme`], as `#rrggbb`, `#rgb` or CSS colour names. Any
+/// left unset keep their default
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub foreground: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub muted: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub good: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub warning: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub critical: Option<Color>,
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let colour = String::deserialize(deserializer)?;
+    color::parse(&colour)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+impl ThemeConfig {
+    /// `theme` with the colours set here replacing its own
+    fn apply(&self, theme: Theme) -> Theme {
+        Theme {
+            foreground: self.foreground.clone().unwrap_or(theme.foreground),
+            muted: self.muted.clone().unwrap_or(theme.muted),
+            good: self.good.clone().unwrap_or(theme.good),
+            warning: self.warning.clone().unwrap_or(theme.warning),
+            critical: self.critical.clone().unwrap_or(theme.critical),
+        }
+    }
+}
+
+/// Switching between a light and a dark theme automatically, see
+/// [`crate::appearance`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppearanceConfig {
+    /// What decides between light and dark, disabled if unset
+    pub source: Option<AppearanceSource>,
+    /// Name of the theme in [`Config::themes`] for light conditions,
+    /// [`Config::theme`] if unset
+    pub light: Option<String>,
+    /// Name of the theme for dark conditions, [`Config::theme`] if unset
+    pub dark: Option<String>,
+    /// Ambient light in lux below which the light sensor counts as dark
+    pub threshold: Option<f64>,
+    /// Time the schedule switches to the light theme, e.g. `"07:30"`
+    #[serde(deserialize_with = "deserialize_time")]
+    pub day: Option<NaiveTime>,
+    /// Time the schedule switches to the dark theme
+    #[serde(deserialize_with = "deserialize_time")]
+    pub night: Option<NaiveTime>,
+    /// Location the schedule follows sunrise and sunset at, instead of `day`
+    /// and `night`, in degrees north
+    pub latitude: Option<f64>,
+    /// Degrees east, negative for west
+    pub longitude: Option<f64>,
+}
+
+fn deserialize_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveTime>, D::Error> {
+    deserialize_required_time(deserializer).map(Some)
+}
+
+fn deserialize_required_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<NaiveTime, D::Error> {
+    let time = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&time, "%H:%M").map_err(|e| {
+        serde::de::Error::custom(format!("invalid time `{time}`, expected HH:MM: {e}"))
+    })
+}
+
+impl AppearanceConfig {
+    fn trigger(&self, source: AppearanceSource) -> Result<Trigger> {
+        Ok(match source {
+            AppearanceSource::Portal => Trigger::Portal,
+            AppearanceSource::LightSensor => Trigger::LightSensor {
+                threshold: self.threshold.unwrap_or(appearance::DEFAULT_THRESHOLD),
+            },
+            AppearanceSource::Schedule => {
+                let schedule = match (self.latitude, self.longitude, self.day, self.night) {
+                    (Some(latitude), Some(longitude), _, _) => Schedule::Sun {
+                        latitude,
+                        longitude,
+                    },
+                    (_, _, Some(day), Some(night)) => Schedule::Fixed { day, night },
+                    _ => {
+                        bail!("the schedule needs either day and night, or latitude and longitude")
+                    }
+                };
+                Trigger::Schedule(schedule)
+            }
+        })
+    }
+}
+
+/// Serving the bar's data to other programs, see [`crate::export`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    /// Address to serve HTTP on, disabled if unset
+    pub address: Option<String>,
+}
+
+/// Settings of every widget making web requests, see [`crate::http`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Proxy URL, e.g. `"http://proxy:3128"` or `"socks5://proxy:1080"`,
+    /// taken from the environment if unset
+    pub proxy: Option<String>,
+    /// PEM file of extra certificate authorities to trust
+    pub ca_bundle: Option<PathBuf>,
+    /// Longest a request may take, replacing each widget's own default
+    #[serde(deserialize_with = "deserialize_interval")]
+    pub timeout: Option<Duration>,
+    /// Longest connecting may take
+    #[serde(deserialize_with = "deserialize_interval")]
+    pub connect_timeout: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WidgetConfig {
+    pub font: FontConfig,
+    /// Replaces the widget's render, see [`crate::template`]
+    pub template: Option<Template>,
+    /// Ready-made render used instead of the widget's own, see
+    /// [`crate::presets`]. `template` takes precedence
+    pub preset: Option<Preset>,
+    /// How often the widget updates, e.g. `"5s"` or `"1m 30s"`, overriding
+    /// its default
+    #[serde(deserialize_with = "deserialize_interval")]
+    pub interval: Option<Duration>,
+    /// Intervals the widget may go without updating before it is dimmed as
+    /// stale, see [`crate::state::Tracked`]. `0` never dims it
+    pub stale_after: Option<u32>,
+    /// `strftime` format of widgets showing a time, see [`crate::clock`]
+    pub format: Option<String>,
+    /// Alarms of widgets showing a time
+    pub alarms: Vec<AlarmConfig>,
+    /// Actions run when a reading crosses a threshold, see
+    /// [`crate::actions`]
+    pub actions: Vec<ActionConfig>,
+    /// Leaves the widget off the bar unless this holds, see
+    /// [`crate::condition`]
+    pub enabled_if: Option<Condition>,
+    /// Token or API key of widgets using a web service, see
+    /// [`crate::secrets`]
+    pub token: Option<Secret>,
+    /// Base URL of widgets querying a server, e.g. Home Assistant's
+    pub server: Option<String>,
+    /// Entities shown by the Home Assistant widget
+    pub entities: Vec<EntityConfig>,
+}
+
+/// A Home Assistant entity, see [`crate::home_assistant`]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityConfig {
+    /// e.g. `"sensor.living_room_temperature"`
+    pub entity_id: String,
+    #[serde(default)]
+    pub icon: String,
+    /// Pango markup in which `{icon}`, `{state}`, `{unit}` and `{name}` are
+    /// replaced, `"{icon} {state}{unit}"` unless set
+    pub template: Option<String>,
+}
+
+/// An alarm going off every day, see [`crate::clock`]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlarmConfig {
+    /// Time of day, e.g. `"07:30"`
+    #[serde(deserialize_with = "deserialize_required_time")]
+    pub time: NaiveTime,
+    /// Shown in the notification and on the bar
+    #[serde(default = "default_alarm_message")]
+    pub message: String,
+}
+
+fn default_alarm_message() -> String {
+    "Alarm".to_string()
+}
+
+/// An action run when a reading crosses a threshold. Which threshold applies
+/// depends on the widget: `percent` for the battery, `available` and `ticks`
+/// for memory
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionConfig {
+    /// Battery charge at or below which the action runs
+    pub percent: Option<u64>,
+    /// Available memory below which the action runs, e.g. `"512 MiB"`
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub available: Option<Byte>,
+    /// Readings in a row `available` must be crossed for, 3 unless set
+    pub ticks: Option<u32>,
+    pub run: Action,
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Byte>, D::Error> {
+    let size = String::deserialize(deserializer)?;
+    Byte::parse_str(&size, true)
+        .map(Some)
+        .map_err(|e| serde::de::Error::custom(format!("invalid size `{size}`: {e}")))
+}
+
+impl WidgetConfig {
+    /// Replaces the settings made in `overrides`
+    fn merge(&mut self, overrides: &WidgetConfig) {
+        self.font.merge(&overrides.font);
+        if overrides.template.is_some() {
+            self.template.clone_from(&overrides.template);
+        }
+        if overrides.preset.is_some() {
+            self.preset = overrides.preset;
+        }
+        if overrides.interval.is_some() {
+            self.interval = overrides.interval;
+        }
+        if overrides.stale_after.is_some() {
+            self.stale_after = overrides.stale_after;
+        }
+        if overrides.format.is_some() {
+            self.format.clone_from(&overrides.format);
+        }
+        if !overrides.alarms.is_empty() {
+            self.alarms.clone_from(&overrides.alarms);
+        }
+        if !overrides.actions.is_empty() {
+            self.actions.clone_from(&overrides.actions);
+        }
+        if overrides.enabled_if.is_some() {
+            self.enabled_if.clone_from(&overrides.enabled_if);
+        }
+        if overrides.token.is_some() {
+            self.token.clone_from(&overrides.token);
+        }
+        if overrides.server.is_some() {
+            self.server.clone_from(&overrides.server);
+        }
+        if !overrides.entities.is_empty() {
+            self.entities.clone_from(&overrides.entities);
+        }
+    }
+}
+
+/// Overrides switched on and off together, see [`crate::profiles`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Widgets left off the bar
+    pub hide: Vec<String>,
+    /// Per-widget settings replacing those in [`Config::widgets`]
+    pub widgets: BTreeMap<String, WidgetConfig>,
+}
+
+fn deserialize_interval<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let interval = String::deserialize(deserializer)?;
+    humantime::parse_duration(&interval)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+impl Config {
+    /// Path of the config file
+    #[must_use]
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("config.toml")
+    }
+
+    /// Loads the config file, falling back to the defaults if it doesn't exist
+    pub fn load() -> Result<Config> {
+        Config::load_from(&Config::path())
+    }
+
+    /// Writes [`DEFAULT_CONFIG`] to [`Config::path`], unless there already is
+    /// a config there, and returns the path
+    pub fn init() -> Result<PathBuf> {
+        let path = Config::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                bail!("{} already exists", path.display())
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Could not create {}", path.display()))
+            }
+        };
+        file.write_all(DEFAULT_CONFIG.as_bytes())
+            .with_context(|| format!("Could not write {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("Could not parse {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+        }
+    }
+
+    /// Applies the profile called `name` on top of the rest of the config
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let Some(profile) = self.profiles.get(name) else {
+            bail!("No profile called {name}");
+        };
+        for (widget, overrides) in &profile.widgets {
+            self.widgets
+                .entry(widget.clone())
+                .or_default()
+                .merge(overrides);
+        }
+
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Name of the profile applied with [`Config::apply_profile`]
+    #[must_use]
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+
+    /// Whether the widget called `widget` is on the bar, rather than hidden
+    /// by the active profile or its `enabled_if` condition
+    #[must_use]
+    pub fn shown(&self, widget: &str) -> bool {
+        let disabled = self
+            .widgets
+            .get(widget)
+            .and_then(|config| config.enabled_if.as_ref())
+            .is_some_and(|condition| !condition.holds());
+        let hidden = self
+            .active_profile
+            .as_ref()
+            .and_then(|name| self.profiles.get(name))
+            .is_some_and(|profile| profile.hide.iter().any(|hidden| hidden == widget));
+
+        !disabled && !hidden
+    }
+
+    fn widget_font(&self, widget: &str) -> Option<&FontConfig> {
+        self.widgets.get(widget).map(|config| &config.font)
+    }
+
+    /// Font for the widget called `widget`, with its overrides applied
+    #[must_use]
+    pub fn font(&self, widget: &str) -> Font {
+        let overrides = self.widget_font(widget);
+
+        let family = overrides
+            .and_then(|font| font.family.as_deref())
+            .or(self.font.family.as_deref())
+            .unwrap_or(DEFAULT_FONT);
+        let size = overrides.and_then(|font| font.size).or(self.font.size);
+
+        match size {
+            Some(size) => Font::new(&format!("{family} {size}")),
+            None => Font::new(family),
+        }
+    }
+
+    /// Render template configured for the widget called `widget`
+    #[must_use]
+    pub fn template(&self, widget: &str) -> Option<Template> {
+        self.widgets
+            .get(widget)
+            .and_then(|config| config.template.clone())
+    }
+
+    /// Render preset configured for the widget called `widget`
+    #[must_use]
+    pub fn preset(&self, widget: &str) -> Option<Preset> {
+        self.widgets.get(widget).and_then(|config| config.preset)
+    }
+
+    /// Token configured for the widget called `widget`
+    #[must_use]
+    pub fn token(&self, widget: &str) -> Option<Secret> {
+        self.widgets
+            .get(widget)
+            .and_then(|config| config.token.clone())
+    }
+
+    /// Server configured for the widget called `widget`
+    #[must_use]
+    pub fn server(&self, widget: &str) -> Option<&str> {
+        self.widgets
+            .get(widget)
+            .and_then(|config| config.server.as_deref())
+    }
+
+    /// Entities configured for the widget called `widget`
+    #[must_use]
+    pub fn entities(&self, widget: &str) -> &[EntityConfig] {
+        self.widgets
+            .get(widget)
+            .map(|config| config.entities.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Update interval configured for the widget called `widget`, or
+    /// `default`
+    #[must_use]
+    pub fn interval(&self, widget: &str, default: Duration) -> Duration {
+        self.widgets
+            .get(widget)
+            .and_then(|config| config.interval)
+            .unwrap_or(default)
+    }
+
+    /// Intervals the widget called `widget` may miss before it counts as
+    /// stale, or `default`
+    #[must_use]
+    pub fn stale_after(&self, widget: &str, default: u32) -> u32 {
+        self.widgets
+            .get(widget)
+            .and_then(|config| config.stale_after)
+            .unwrap_or(default)
+    }
+
+    /// Time format configured for the widget called `widget`, or `default`
+    #[must_use]
+    pub fn format<'a>(&'a self, widget: &str, default: &'a str) -> &'a str {
+        self.widgets
+            .get(widget)
+            .and_then(|config| config.format.as_deref())
+            .unwrap_or(default)
+    }
+
+    /// Alarms configured for the widget called `widget`
+    #[must_use]
+    pub fn alarms(&self, widget: &str) -> &[AlarmConfig] {
+        self.widgets
+            .get(widget)
+            .map(|config| config.alarms.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Threshold actions configured for the widget called `widget`
+    #[must_use]
+    pub fn actions(&self, widget: &str) -> &[ActionConfig] {
+        self.widgets
+            .get(widget)
+            .map(|config| config.actions.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The default [`Theme`] with the configured colours applied
+    #[must_use]
+    pub fn theme(&self) -> Theme {
+        self.theme.apply(Theme::default())
+    }
+
+    /// The theme called `name` in [`Config::themes`], or [`Config::theme`]
+    /// if `name` is `None`
+    pub fn named_theme(&self, name: Option<&str>) -> Result<Theme> {
+        let Some(name) = name else {
+            return Ok(self.theme());
+        };
+        match self.themes.get(name) {
+            Some(theme) => Ok(theme.apply(self.theme())),
+            None => bail!("No theme called {name}"),
+        }
+    }
+
+    /// Starts switching between the light and dark themes, if configured
+    pub fn follow_appearance(&self) -> Result<()> {
+        let Some(source) = self.appearance.source else {
+            return Ok(());
+        };
+
+        appearance::follow(
+            self.appearance.trigger(source)?,
+            self.named_theme(self.appearance.light.as_deref())?,
+            self.named_theme(self.appearance.dark.as_deref())?,
+        )
+    }
+
+    /// Icon font for the widget called `widget`, if one is configured
+    #[must_use]
+    pub fn icon_font(&self, widget: &str) -> Option<String> {
+        self.widget_font(widget)
+            .and_then(|font| font.icon_family.clone())
+            .or_else(|| self.font.icon_family.clone())
+    }
+}
@@ -0,0 +1,160 @@
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::cache::Cache;
+use crate::render::{Render, RenderContext};
+use crate::{dns, http, state};
+
+/// URL which answers with an empty 204 when reached without interception
+pub const DEFAULT_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+// Abstracted type to represent the render closure
+type ConnectivityRender = Box<dyn Render<Connectivity>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// The internet is reachable
+    Online,
+    /// Requests are intercepted by a login page
+    Portal,
+    /// Connected to a network without internet access
+    Limited,
+    Offline,
+    /// The check could not be performed
+    Unknown,
+}
+
+/// How connectivity is determined
+#[derive(Clone)]
+pub enum ConnectivityCheck {
+    /// Ask NetworkManager, which runs its own check against the URL in its
+    /// configuration
+    NetworkManager,
+    /// Fetch a `generate_204` style URL. Anything other than a 204 means the
+    /// request was intercepted by a captive portal
+    Url(String),
+}
+
+/// cnx widget that distinguishes being online from being stuck behind a
+/// captive portal, which the state of the interface alone can't tell apart
+pub struct ConnectivityStatus {
+    attrs: Attributes,
+    render: Option<ConnectivityRender>,
+    update_interval: Duration,
+    check: ConnectivityCheck,
+    agent: ureq::Agent,
+    cache: Cache<Connectivity>,
+}
+
+impl ConnectivityStatus {
+    /// Creates a new [`ConnectivityStatus`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<ConnectivityRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often connectivity is checked
+    ///
+    /// `check`: [`ConnectivityCheck`] - How connectivity is determined
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<ConnectivityRender>,
+        update_interval: Duration,
+        check: ConnectivityCheck,
+    ) -> ConnectivityStatus {
+        ConnectivityStatus {
+            attrs,
+            render,
+            update_interval,
+            check,
+            // Portals answer with a redirect to their login page, which must
+            // not be followed, and often intercept DNS, which the shared
+            // cache would hide for minutes
+            agent: http::builder(Duration::from_secs(5))
+                .resolver(dns::Uncached)
+                .redirects(0)
+                .build(),
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let (agent, check) = (self.agent.clone(), self.check.clone());
+        let cached = self.cache.get(move || Ok(connectivity(&agent, &check)));
+        let Some(cached) = cached else {
+            return vec![];
+        };
+        let connectivity = cached.value;
+
+        let text = if let Some(render) = &self.render {
+            render.render(connectivity, &RenderContext::current())
+        } else {
+            let (label, colour) = match connectivity {
+                Connectivity::Online => ("online", Color::green()),
+                Connectivity::Portal => ("portal", Color::yellow()),
+                Connectivity::Limited => ("limited", Color::yellow()),
+                Connectivity::Offline => ("offline", Color::red()),
+                Connectivity::Unknown => ("?", Color::white()),
+            };
+            format!("<span foreground=\"{}\">{label}</span>", colour.to_hex())
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for ConnectivityStatus {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Runs `check`, which blocks on a request or on `nmcli`
+fn connectivity(agent: &ureq::Agent, check: &ConnectivityCheck) -> Connectivity {
+    match check {
+        ConnectivityCheck::NetworkManager => network_manager_connectivity(),
+        ConnectivityCheck::Url(url) => match agent.get(url).call() {
+            Ok(response) if response.status() == 204 => Connectivity::Online,
+            Ok(_) | Err(ureq::Error::Status(..)) => Connectivity::Portal,
+            Err(_) => Connectivity::Offline,
+        },
+    }
+}
+
+fn network_manager_connectivity() -> Connectivity {
+    let Ok(output) = Command::new("nmcli")
+        .args(["networking", "connectivity", "check"])
+        .output()
+    else {
+        return Connectivity::Unknown;
+    };
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "full" => Connectivity::Online,
+        "portal" => Connectivity::Portal,
+        "limited" => Connectivity::Limited,
+        "none" => Connectivity::Offline,
+        _ => Connectivity::Unknown,
+    }
+}
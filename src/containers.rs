@@ -0,0 +1,196 @@
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use crate::cache::{self, Cache};
+use crate::render::{Render, RenderContext};
+use crate::{icons, state};
+
+// Abstracted type to represent the render closure
+type ContainersRender = Box<dyn Render<ContainerInfo>>;
+
+#[derive(Clone)]
+pub struct ContainerInfo {
+    pub running: usize,
+    pub total: usize,
+    /// Containers whose healthcheck is currently failing
+    pub unhealthy: usize,
+    /// Whether the counts are older than the cache's TTL, because the daemon
+    /// hasn't been answering
+    pub stale: bool,
+}
+
+/// cnx widget that shows running and total container counts from a Docker or
+/// Podman API socket
+pub struct Containers {
+    attrs: Attributes,
+    render: Option<ContainersRender>,
+    update_interval: Duration,
+    socket_path: String,
+    flag_unhealthy: bool,
+    cache: Cache<ContainerInfo>,
+}
+
+impl Containers {
+    /// Creates a new [`Containers`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<ContainersRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the socket is queried
+    ///
+    /// `socket_path`: [`String`] - Path to the Docker or Podman API socket,
+    /// see [`default_socket`]
+    ///
+    /// `flag_unhealthy`: [`bool`] - Whether the default render should call
+    /// out unhealthy containers
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<ContainersRender>,
+        update_interval: Duration,
+        socket_path: String,
+        flag_unhealthy: bool,
+    ) -> Containers {
+        Containers {
+            attrs,
+            render,
+            update_interval,
+            socket_path,
+            flag_unhealthy,
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let socket_path = self.socket_path.clone();
+        let cached = self.cache.get(move || query(&socket_path));
+        // Hidden until the daemon has answered once
+        let Some(cached) = cached else {
+            return vec![];
+        };
+        let container_info = ContainerInfo {
+            stale: cached.stale,
+            ..cached.value
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(container_info, &RenderContext::current())
+        } else {
+            let text = if self.flag_unhealthy && container_info.unhealthy > 0 {
+                format!(
+                    "{} {}/{} <span foreground=\"{}\">({} unhealthy)</span>",
+                    icons::icon_markup(icons::icons().containers),
+                    container_info.running,
+                    container_info.total,
+                    Color::red().to_hex(),
+                    container_info.unhealthy
+                )
+            } else {
+                format!(
+                    "{} {}/{}",
+                    icons::icon_markup(icons::icons().containers),
+                    container_info.running,
+                    container_info.total
+                )
+            };
+            if container_info.stale {
+                cache::mark_stale(&text)
+            } else {
+                text
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Containers {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Counts the containers known to the daemon behind `socket_path`
+fn query(socket_path: &str) -> Result<ContainerInfo> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    // HTTP/1.0 keeps the daemon from using chunked encoding and makes it
+    // close the connection once the body is sent
+    stream.write_all(b"GET /containers/json?all=true HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let Some((head, body)) = response.split_once("\r\n\r\n") else {
+        bail!("Malformed response from {socket_path}");
+    };
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        bail!("Container API returned status {status}");
+    }
+
+    let containers: Vec<Value> = serde_json::from_str(body)?;
+    let running = containers
+        .iter()
+        .filter(|container| container["State"] == "running")
+        .count();
+    let unhealthy = containers
+        .iter()
+        .filter(|container| {
+            container["Status"]
+                .as_str()
+                .is_some_and(|status| status.contains("(unhealthy)"))
+        })
+        .count();
+
+    Ok(ContainerInfo {
+        running,
+        total: containers.len(),
+        unhealthy,
+        stale: false,
+    })
+}
+
+/// Finds the container API socket to use: `DOCKER_HOST` when it points at a
+/// Unix socket, then the system Docker socket, then the rootless Podman socket
+#[must_use]
+pub fn default_socket() -> String {
+    if let Some(path) = std::env::var("DOCKER_HOST")
+        .ok()
+        .and_then(|host| host.strip_prefix("unix://").map(str::to_string))
+    {
+        return path;
+    }
+
+    if Path::new("/var/run/docker.sock").exists() {
+        return "/var/run/docker.sock".to_string();
+    }
+
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/run".to_string());
+    format!("{runtime_dir}/podman/podman.sock")
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::collectors::cpu::CpuCollector;
+pub use crate::collectors::cpu::{parse_times, CpuInfo, CpuTimes};
+use crate::collectors::Collector;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{color, icons, state};
+
+// Abstracted type to represent the render closure
+type CpuRender = Box<dyn Render<CpuInfo>>;
+
+impl Fields for CpuInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.cpu))),
+            "usage" => Some(Value::Number(self.usage)),
+            "iowait" => Some(Value::Number(self.iowait)),
+            "steal" => Some(Value::Number(self.steal)),
+            "pressure" => self
+                .pressure
+                .as_ref()
+                .map(|pressure| Value::Number(pressure.some_avg10)),
+            _ => None,
+        }
+    }
+}
+
+/// cnx widget that shows CPU usage, broken down into iowait and steal time
+pub struct Cpu {
+    attrs: Attributes,
+    render: Option<CpuRender>,
+    update_interval: Duration,
+    collector: CpuCollector,
+}
+
+impl Cpu {
+    /// Creates a new [`Cpu`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<CpuRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often usage is sampled
+    #[must_use]
+    pub fn new(attrs: Attributes, render: Option<CpuRender>, update_interval: Duration) -> Cpu {
+        Cpu {
+            attrs,
+            render,
+            update_interval,
+            collector: CpuCollector::new(),
+        }
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        let Ok(cpu_info) = self.collector.collect() else {
+            return vec![];
+        };
+        state::bar().set_value("cpu", &cpu_info);
+
+        let text = if let Some(render) = &self.render {
+            render.render(cpu_info, &RenderContext::current())
+        } else {
+            let mut text = format!(
+                "{} <span foreground=\"{}\">{:.0}%</span>",
+                icons::icon_markup(icons::icons().cpu),
+                color::gradient(cpu_info.usage).to_hex(),
+                cpu_info.usage
+            );
+            if cpu_info.iowait >= 1.0 {
+                text += &format!(" io {:.0}%", cpu_info.iowait);
+            }
+            if cpu_info.steal >= 1.0 {
+                text += &format!(" st {:.0}%", cpu_info.steal);
+            }
+            text
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Cpu {
+    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,76 @@
+//! D-Bus service exposing the bar as `org.status_bar.Bar1` on the session bus,
+//! and signal plumbing for widgets following other services.
+
+use std::collections::HashMap;
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+
+use anyhow::Result;
+use zbus::blocking::{connection, Connection, MessageIterator};
+use zbus::{interface, message, MatchRule};
+
+use crate::state;
+
+pub const BUS_NAME: &str = "org.status_bar.Bar1";
+pub const OBJECT_PATH: &str = "/org/status_bar/Bar1";
+
+// The connection dispatches method calls on its own executor thread for as
+// long as it is alive
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+struct BarService;
+
+#[interface(name = "org.status_bar.Bar1")]
+impl BarService {
+    /// Updates every widget immediately
+    fn refresh(&self) {
+        state::bar().request_refresh();
+    }
+
+    /// Sets the markup shown by the [`crate::slot::Slot`] called `slot`
+    fn set_text(&self, slot: &str, text: &str) {
+        state::bar().set_slot(slot, text.to_string());
+    }
+
+    /// The text currently rendered by each tracked widget, keyed by name
+    fn get_content(&self) -> HashMap<String, String> {
+        state::bar().contents().into_iter().collect()
+    }
+}
+
+/// Claims [`BUS_NAME`] and starts answering method calls
+pub fn serve() -> Result<()> {
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, BarService)?
+        .build()?;
+
+    let _ = CONNECTION.set(connection);
+    Ok(())
+}
+
+/// Sends to `changes` from a background thread whenever an object at `path`
+/// emits `PropertiesChanged`, whichever service owns it, until `changes` is
+/// disconnected
+pub fn send_property_changes(
+    connection: &Connection,
+    path: &str,
+    changes: mpsc::Sender<()>,
+) -> Result<()> {
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(path)?
+        .build();
+    let messages = MessageIterator::for_match_rule(rule, connection, None)?;
+
+    thread::spawn(move || {
+        for _ in messages.flatten() {
+            if changes.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use byte_unit::{Byte, UnitType};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+
+use crate::actions::Action;
+use crate::collectors::disk::DiskCollector;
+pub use crate::collectors::disk::DiskInfo;
+use crate::collectors::Collector;
+use crate::polling::PollingWidget;
+use crate::progress::ProgressBar;
+use crate::render::{Render, RenderContext};
+use crate::{color, icons};
+
+/// Usage bar drawn for each filesystem by the default render
+const BAR: ProgressBar = ProgressBar::blocks(5);
+
+// Abstracted type to represent the render closure
+type DiskRender = Box<dyn Render<Vec<DiskInfo>>>;
+
+/// When a watched filesystem counts as full
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiskLimit {
+    /// More than the given percentage of it is used
+    Usage(f64),
+    /// Less than the given amount of it is available
+    Free(Byte),
+}
+
+impl DiskLimit {
+    fn exceeded_by(self, disk: &DiskInfo) -> bool {
+        match self {
+            DiskLimit::Usage(percentage) => {
+                color::percentage(disk.used.as_u64(), disk.total.as_u64()) > percentage
+            }
+            DiskLimit::Free(floor) => disk.available < floor,
+        }
+    }
+}
+
+/// An [`Action`] run when any watched filesystem passes `limit`, at most once
+/// per `cooldown`
+struct Alert {
+    limit: DiskLimit,
+    cooldown: Duration,
+    action: Action,
+    last_run: Option<Instant>,
+}
+
+impl Alert {
+    fn check(&mut self, disks: &[DiskInfo]) {
+        if !disks.iter().any(|disk| self.limit.exceeded_by(disk)) {
+            return;
+        }
+
+        if self
+            .last_run
+            .is_some_and(|last_run| last_run.elapsed() < self.cooldown)
+        {
+            return;
+        }
+
+        self.last_run = Some(Instant::now());
+        self.action.spawn();
+    }
+}
+
+/// cnx widget that shows how full a set of filesystems are
+pub struct DiskUsage {
+    attrs: Attributes,
+    render: Option<DiskRender>,
+    update_interval: Duration,
+    mount_points: Vec<String>,
+    alerts: Vec<Alert>,
+}
+
+impl DiskUsage {
+    /// Creates a new [`DiskUsage`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<DiskRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often usage is read
+    ///
+    /// `mount_points`: [`Vec<String>`] - Filesystems to watch, e.g. `/` and
+    /// `/home`, shown in this order
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<DiskRender>,
+        update_interval: Duration,
+        mount_points: Vec<String>,
+    ) -> DiskUsage {
+        DiskUsage {
+            attrs,
+            render,
+            update_interval,
+            mount_points,
+            alerts: Vec::new(),
+        }
+    }
+
+    /// Runs `action` when any watched filesystem passes `limit`. While it
+    /// stays past it, `action` is repeated at most once per `cooldown`
+    #[must_use]
+    pub fn with_alert(mut self, limit: DiskLimit, cooldown: Duration, action: Action) -> Self {
+        self.alerts.push(Alert {
+            limit,
+            cooldown,
+            action,
+            last_run: None,
+        });
+        self
+    }
+}
+
+fn default_render(disks: &[DiskInfo]) -> String {
+    if disks.is_empty() {
+        return String::new();
+    }
+
+    let usage = disks
+        .iter()
+        .map(|disk| {
+            let percentage = color::percentage(disk.used.as_u64(), disk.total.as_u64());
+            format!(
+                "{} <span foreground=\"{}\">{} {}</span>",
+                disk.mount_point.display(),
+                color::gradient(percentage).to_hex(),
+                BAR.render(percentage),
+                disk.available.get_appropriate_unit(UnitType::Binary),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!("{} {usage}", icons::icon_markup(icons::icons().disk))
+}
+
+impl Widget for DiskUsage {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let DiskUsage {
+            attrs,
+            render,
+            update_interval,
+            mount_points,
+            mut alerts,
+        } = *self;
+
+        let alerts_empty = alerts.is_empty();
+        let mut collector = DiskCollector::new(&mount_points);
+        let collect = move || -> Result<Vec<DiskInfo>> {
+            let disks = collector.collect()?;
+            for alert in &mut alerts {
+                alert.check(&disks);
+            }
+            Ok(disks)
+        };
+
+        let show = move |disks: Vec<DiskInfo>| match &render {
+            Some(render) => render.render(disks, &RenderContext::current()),
+            None => default_render(&disks),
+        };
+
+        let widget = PollingWidget::new(attrs, update_interval, collect, show)
+            .with_export("disk")
+            .with_pausing(alerts_empty);
+        Box::new(widget).into_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn default_render_shows_usage_bars() {
+        let disk = DiskInfo {
+            mount_point: PathBuf::from("/"),
+            used: Byte::from_u64(60 << 30),
+            total: Byte::from_u64(100 << 30),
+            available: Byte::from_u64(40 << 30),
+        };
+        let rendered = default_render(&[disk]);
+        assert!(rendered.contains(&format!(
+            "/ <span foreground=\"{}\">███░░ 40",
+            color::gradient(60.0).to_hex()
+        )));
+    }
+}
@@ -0,0 +1,87 @@
+//! Caching resolver used by every agent from [`crate::http`] and by
+//! [`crate::ping`], so that widgets
+//! polling the same hosts share one lookup, and a flaky network doesn't make
+//! each of them block on DNS in turn.
+//!
+//! The system resolver doesn't report record TTLs, so answers are kept for
+//! [`TTL`]. Failed lookups are remembered for [`FAILURE_TTL`], failing
+//! requests straight away until then.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long an answer is reused
+pub const TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How long a failed lookup is remembered
+pub const FAILURE_TTL: Duration = Duration::from_secs(30);
+
+struct Entry {
+    /// The addresses, or the error the lookup failed with
+    result: Result<Vec<SocketAddr>, (ErrorKind, String)>,
+    expires: Instant,
+}
+
+static CACHE: LazyLock<Mutex<HashMap<String, Entry>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Addresses of `netloc`, a `host:port`, from the cache while it is fresh
+pub fn resolve(netloc: &str) -> io::Result<Vec<SocketAddr>> {
+    if let Some(entry) = CACHE.lock().unwrap().get(netloc) {
+        if entry.expires > Instant::now() {
+            return entry
+                .result
+                .clone()
+                .map_err(|(kind, message)| io::Error::new(kind, message));
+        }
+    }
+
+    // Not holding the lock, so other hosts can still be looked up meanwhile
+    let result: Result<Vec<SocketAddr>, _> = netloc
+        .to_socket_addrs()
+        .map(Iterator::collect)
+        .map_err(|e| (e.kind(), e.to_string()));
+    let ttl = if result.is_ok() { TTL } else { FAILURE_TTL };
+
+    CACHE.lock().unwrap().insert(
+        netloc.to_string(),
+        Entry {
+            result: result.clone(),
+            expires: Instant::now() + ttl,
+        },
+    );
+    result.map_err(|(kind, message)| io::Error::new(kind, message))
+}
+
+/// Forgets every answer, e.g. after moving to another network where hosts
+/// may resolve differently
+pub fn flush() {
+    CACHE.lock().unwrap().clear();
+}
+
+/// [`resolve`] as a [`ureq::Resolver`]
+#[cfg(feature = "http")]
+pub struct Resolver;
+
+#[cfg(feature = "http")]
+impl ureq::Resolver for Resolver {
+    fn resolve(&self, netloc: &str) -> io::Result<Vec<SocketAddr>> {
+        resolve(netloc)
+    }
+}
+
+/// The system resolver without the cache, for requests that are about how
+/// the network answers right now, such as a captive portal check, where a
+/// cached answer from before a portal intercepted DNS would hide it
+#[cfg(feature = "http")]
+pub struct Uncached;
+
+#[cfg(feature = "http")]
+impl ureq::Resolver for Uncached {
+    fn resolve(&self, netloc: &str) -> io::Result<Vec<SocketAddr>> {
+        netloc.to_socket_addrs().map(Iterator::collect)
+    }
+}
@@ -0,0 +1,64 @@
+//! Pauses the bar while nobody can see it: while the screen is blanked by
+//! DPMS or the X screensaver, or the session is locked according to logind's
+//! `LockedHint`, which screen lockers such as xss-lock set.
+//!
+//! While paused, widgets driven by [`state::ticks`] neither poll nor redraw,
+//! so sensors, commands and web requests stop too. Widgets whose readings
+//! trigger actions, such as battery, memory and disk alerts or clock alarms,
+//! keep polling without drawing. On wake every widget refreshes at once, so
+//! stale data is never shown.
+//!
+//! `keep_polling = true` in the config turns this off, e.g. for programs
+//! reading [`crate::export`] while the screen is off.
+
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "dbus")]
+use zbus::blocking::{Connection, Proxy};
+
+use crate::idle::Idle;
+use crate::state;
+
+/// How often the screen and session are checked
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The logind session the bar runs in. zbus caches its `LockedHint` and
+/// keeps it up to date from change signals, so reading it is cheap
+#[cfg(feature = "dbus")]
+fn session() -> zbus::Result<Proxy<'static>> {
+    Proxy::new(
+        &Connection::system()?,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.login1.Session",
+    )
+}
+
+/// Starts checking whether the bar is seen on a background thread
+pub fn watch() {
+    thread::spawn(|| {
+        let idle = Idle::connect()
+            .map_err(|e| eprintln!("Could not watch the screen for DPMS: {e}"))
+            .ok();
+        #[cfg(feature = "dbus")]
+        let session = session()
+            .map_err(|e| eprintln!("Could not watch the session for locking: {e}"))
+            .ok();
+
+        loop {
+            let screen_off = idle
+                .as_ref()
+                .is_some_and(|idle| idle.screen_off().unwrap_or(false));
+            #[cfg(feature = "dbus")]
+            let locked = session
+                .as_ref()
+                .is_some_and(|session| session.get_property::<bool>("LockedHint").unwrap_or(false));
+            #[cfg(not(feature = "dbus"))]
+            let locked = false;
+
+            state::bar().set_paused(screen_off || locked);
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,43 @@
+//! `status_bar test <widget>`, which runs a single update of one widget and
+//! prints the data it collected alongside the markup it rendered, so render
+//! templates can be worked on without restarting the bar.
+
+use anyhow::Result;
+use cnx::widgets::Widget;
+use tokio::runtime;
+use tokio::task::LocalSet;
+use tokio_stream::StreamExt;
+
+use crate::state;
+
+/// Subcommand that runs a dry run
+pub const COMMAND: &str = "test";
+
+/// Runs `widget` for one update and prints the data recorded under `name`
+/// in the [`state::BarState`] and the markup of each text it produced
+pub fn run(name: &str, widget: Box<dyn Widget>) -> Result<()> {
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let texts = LocalSet::new().block_on(&runtime, async {
+        let mut stream = widget.into_stream()?;
+        stream.next().await.unwrap_or_else(|| Ok(vec![]))
+    })?;
+
+    println!("data:");
+    match state::bar().values().get(name) {
+        Some(value) => println!("{}", serde_json::to_string_pretty(value)?),
+        None => println!("(not recorded by this widget)"),
+    }
+
+    println!();
+    println!("markup:");
+    if texts.is_empty() {
+        println!("(hidden)");
+    }
+    for text in texts {
+        println!("{}", text.text);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! Scaffolding for widgets driven by a source that pushes updates, such as
+//! D-Bus signals, inotify or a pipe, rather than one that is polled. The
+//! counterpart of [`crate::polling::PollingWidget`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio::time::{self, Instant, Sleep};
+use tokio_stream::{Stream, StreamExt};
+
+/// cnx widget that shows what `render` makes of each item of a stream.
+///
+/// The widget hides itself while `render` returns an empty string
+pub struct EventWidget<T> {
+    attrs: Attributes,
+    events: Pin<Box<dyn Stream<Item = T>>>,
+    render: Box<dyn Fn(T) -> String>,
+    debounce: Option<Duration>,
+}
+
+impl<T: 'static> EventWidget<T> {
+    /// Creates a new [`EventWidget`]
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `events`: Stream of updates, the widget is redrawn for each
+    ///
+    /// `render`: Turns an update into Pango markup
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        events: impl Stream<Item = T> + 'static,
+        render: impl Fn(T) -> String + 'static,
+    ) -> EventWidget<T> {
+        EventWidget {
+            attrs,
+            events: Box::pin(events),
+            render: Box::new(render),
+            debounce: None,
+        }
+    }
+
+    /// Only redraws once updates have stopped arriving for `period`, showing
+    /// the latest. For sources that fire in bursts, e.g. a player emitting a
+    /// signal per changed property
+    #[must_use]
+    pub fn with_debounce(mut self, period: Duration) -> Self {
+        self.debounce = Some(period);
+        self
+    }
+}
+
+impl<T: 'static> Widget for EventWidget<T> {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let EventWidget {
+            attrs,
+            events,
+            render,
+            debounce,
+        } = *self;
+
+        let events: Pin<Box<dyn Stream<Item = T>>> = match debounce {
+            Some(period) => Box::pin(Debounce::new(events, period)),
+            None => events,
+        };
+
+        let stream = events.map(move |event| {
+            let text = render(event);
+            if text.is_empty() {
+                return Ok(vec![]);
+            }
+
+            Ok(vec![Text {
+                attr: attrs.clone(),
+                text,
+                stretch: false,
+                markup: true,
+            }])
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Yields the latest item of `inner` once it has been quiet for `period`
+struct Debounce<T> {
+    inner: Pin<Box<dyn Stream<Item = T>>>,
+    period: Duration,
+    pending: Option<T>,
+    deadline: Pin<Box<Sleep>>,
+    finished: bool,
+}
+
+impl<T> Debounce<T> {
+    fn new(inner: Pin<Box<dyn Stream<Item = T>>>, period: Duration) -> Debounce<T> {
+        Debounce {
+            inner,
+            period,
+            pending: None,
+            deadline: Box::pin(time::sleep(period)),
+            finished: false,
+        }
+    }
+}
+
+// Items are never pinned, only moved in and out of `pending`
+impl<T> Unpin for Debounce<T> {}
+
+impl<T> Stream for Debounce<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        while !self.finished {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    self.pending = Some(item);
+                    let deadline = Instant::now() + self.period;
+                    self.deadline.as_mut().reset(deadline);
+                }
+                Poll::Ready(None) => self.finished = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if self.pending.is_none() {
+            return if self.finished {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+
+        // Whatever is left is shown straight away once the source has ended
+        if self.finished || self.deadline.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(self.pending.take());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(100);
+
+    /// A debounced stream, and a sender whose items it yields
+    fn debounced() -> (mpsc::UnboundedSender<u32>, Debounce<u32>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let inner = Box::pin(UnboundedReceiverStream::new(receiver));
+        (sender, Debounce::new(inner, PERIOD))
+    }
+
+    /// Sends `items` 10ms apart from a background task, keeping the sender
+    /// open afterwards unless `close`
+    fn burst(sender: mpsc::UnboundedSender<u32>, items: Vec<u32>, close: bool) {
+        tokio::spawn(async move {
+            for item in items {
+                sender.send(item).unwrap();
+                time::sleep(Duration::from_millis(10)).await;
+            }
+            if !close {
+                std::future::pending::<()>().await;
+            }
+        });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_collapses_to_latest() {
+        let (sender, mut debounced) = debounced();
+        let start = Instant::now();
+        burst(sender, vec![1, 2, 3, 4, 5], false);
+
+        assert_eq!(debounced.next().await, Some(5));
+        // The last item came 40ms in, and was held for the quiet period
+        assert!(start.elapsed() >= Duration::from_millis(40) + PERIOD);
+        assert!(time::timeout(PERIOD * 3, debounced.next()).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn trailing_event_is_delivered() {
+        let (sender, mut debounced) = debounced();
+        burst(sender.clone(), vec![1, 2, 3], false);
+        assert_eq!(debounced.next().await, Some(3));
+
+        time::sleep(PERIOD * 2).await;
+        let start = Instant::now();
+        sender.send(4).unwrap();
+        assert_eq!(debounced.next().await, Some(4));
+        assert!(start.elapsed() >= PERIOD);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pending_event_is_delivered_when_source_ends() {
+        let (sender, mut debounced) = debounced();
+        let start = Instant::now();
+        burst(sender, vec![1, 2, 3], true);
+
+        assert_eq!(debounced.next().await, Some(3));
+        // Shown as soon as the source ended, without waiting out the period
+        assert!(start.elapsed() < Duration::from_millis(30) + PERIOD);
+        assert_eq!(debounced.next().await, None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Reading and driving the window manager through the EWMH properties and
+//! messages of the root window, shared by the workspace and window widgets.
+
+use anyhow::{anyhow, Result};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, EventMask, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+/// `_NET_WM_DESKTOP` of windows shown on every desktop
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+/// Flag of `WM_HINTS` set by windows that want the user's attention
+const URGENCY_HINT: u32 = 1 << 8;
+
+/// Source indication in client messages, telling the window manager the
+/// request comes from a pager rather than an application
+const SOURCE_PAGER: u32 = 2;
+
+/// Connection to the X server and its root window, whose properties describe
+/// the desktops and windows
+pub struct Ewmh {
+    connection: RustConnection,
+    root: Window,
+}
+
+impl Ewmh {
+    pub fn connect() -> Result<Ewmh> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen].root;
+        Ok(Ewmh { connection, root })
+    }
+
+    fn atom(&self, name: &str) -> Result<Atom> {
+        Ok(self
+            .connection
+            .intern_atom(false, name.as_bytes())?
+            .reply()?
+            .atom)
+    }
+
+    /// The 32-bit values of `window`'s property `name`, empty if it isn't set
+    fn values(&self, window: Window, name: &str, kind: impl Into<Atom>) -> Result<Vec<u32>> {
+        Ok(self
+            .connection
+            .get_property(false, window, self.atom(name)?, kind, 0, u32::MAX)?
+            .reply()?
+            .value32()
+            .map(Iterator::collect)
+            .unwrap_or_default())
+    }
+
+    fn cardinal(&self, name: &str) -> Result<u32> {
+        self.values(self.root, name, AtomEnum::CARDINAL)?
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow!("{name} isn't set, is an EWMH window manager running?"))
+    }
+
+    /// `window`'s property `name` as text, empty if it isn't set
+    fn text(&self, window: Window, name: &str, kind: impl Into<Atom>) -> Result<Vec<u8>> {
+        Ok(self
+            .connection
+            .get_property(false, window, self.atom(name)?, kind, 0, u32::MAX)?
+            .reply()?
+            .value)
+    }
+
+    /// Sends the client message `name` about `window` to the window manager
+    fn request(&self, window: Window, name: &str, data: [u32; 5]) -> Result<()> {
+        let event = ClientMessageEvent::new(32, window, self.atom(name)?, data);
+        self.connection.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            event,
+        )?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /// Index of the desktop being shown
+    pub fn current_desktop(&self) -> Result<u32> {
+        self.cardinal("_NET_CURRENT_DESKTOP")
+    }
+
+    pub fn desktop_count(&self) -> Result<u32> {
+        self.cardinal("_NET_NUMBER_OF_DESKTOPS")
+    }
+
+    /// Names of the desktops, in order. Window managers may name fewer
+    /// desktops than they have
+    pub fn desktop_names(&self) -> Result<Vec<String>> {
+        let names = self.text(self.root, "_NET_DESKTOP_NAMES", self.atom("UTF8_STRING")?)?;
+
+        // Names are NUL terminated, so the last is always empty
+        Ok(names
+            .split(|&byte| byte == 0)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect())
+    }
+
+    /// Asks the window manager to show the desktop at `index`
+    pub fn switch_desktop(&self, index: u32) -> Result<()> {
+        self.request(
+            self.root,
+            "_NET_CURRENT_DESKTOP",
+            [index, x11rb::CURRENT_TIME, 0, 0, 0],
+        )
+    }
+
+    /// Managed windows, from oldest to newest
+    pub fn windows(&self) -> Result<Vec<Window>> {
+        self.values(self.root, "_NET_CLIENT_LIST", AtomEnum::WINDOW)
+    }
+
+    /// The focused window, if any
+    pub fn active_window(&self) -> Result<Option<Window>> {
+        let active = self.values(self.root, "_NET_ACTIVE_WINDOW", AtomEnum::WINDOW)?;
+        Ok(active.first().copied().filter(|&window| window != 0))
+    }
+
+    /// Index of the desktop `window` is on, `None` for windows on every
+    /// desktop or none
+    pub fn desktop(&self, window: Window) -> Result<Option<u32>> {
+        let desktop = self.values(window, "_NET_WM_DESKTOP", AtomEnum::CARDINAL)?;
+        Ok(desktop
+            .first()
+            .copied()
+            .filter(|&desktop| desktop != ALL_DESKTOPS))
+    }
+
+    /// Whether `window` is shown on the desktop at `desktop`, including
+    /// windows that are on every desktop
+    pub fn on_desktop(&self, window: Window, desktop: u32) -> Result<bool> {
+        let window_desktop = self.values(window, "_NET_WM_DESKTOP", AtomEnum::CARDINAL)?;
+        Ok(matches!(window_desktop.first(), Some(&d) if d == desktop || d == ALL_DESKTOPS))
+    }
+
+    /// Whether `_NET_WM_STATE` of `window` includes `state`
+    fn has_state(&self, window: Window, state: &str) -> Result<bool> {
+        let states = self.values(window, "_NET_WM_STATE", AtomEnum::ATOM)?;
+        Ok(states.contains(&self.atom(state)?))
+    }
+
+    /// Whether `window` wants the user's attention, e.g. a chat window with
+    /// a new message. Checks both the EWMH state and the older ICCCM hint
+    pub fn demands_attention(&self, window: Window) -> Result<bool> {
+        if self.has_state(window, "_NET_WM_STATE_DEMANDS_ATTENTION")? {
+            return Ok(true);
+        }
+
+        let hints = self.values(window, "WM_HINTS", AtomEnum::WM_HINTS)?;
+        Ok(hints.first().is_some_and(|flags| flags & URGENCY_HINT != 0))
+    }
+
+    /// Title of `window`, preferring the UTF-8 `_NET_WM_NAME`
+    pub fn title(&self, window: Window) -> Result<String> {
+        let mut title = self.text(window, "_NET_WM_NAME", self.atom("UTF8_STRING")?)?;
+        if title.is_empty() {
+            title = self.text(window, "WM_NAME", AtomEnum::STRING)?;
+        }
+        Ok(String::from_utf8_lossy(&title).into_owned())
+    }
+
+    /// Class of `window`, such as `firefox`, from the second half of its
+    /// `WM_CLASS`
+    pub fn class(&self, window: Window) -> Result<String> {
+        let class = self.text(window, "WM_CLASS", AtomEnum::STRING)?;
+        Ok(class
+            .split(|&byte| byte == 0)
+            .nth(1)
+            .map(|class| String::from_utf8_lossy(class).into_owned())
+            .unwrap_or_default())
+    }
+
+    /// Whether `window` is minimised
+    pub fn hidden(&self, window: Window) -> Result<bool> {
+        self.has_state(window, "_NET_WM_STATE_HIDDEN")
+    }
+
+    /// Asks the window manager to focus and raise `window`, switching to its
+    /// desktop or restoring it if need be
+    pub fn activate(&self, window: Window) -> Result<()> {
+        self.request(
+            window,
+            "_NET_ACTIVE_WINDOW",
+            [SOURCE_PAGER, x11rb::CURRENT_TIME, 0, 0, 0],
+        )
+    }
+}
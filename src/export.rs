@@ -0,0 +1,194 @@
+//! HTTP endpoint serving the data collected by the bar, so that other tools
+//! such as dashboards can use it without polling the system again.
+//!
+//! `GET /json` (or `/`) answers with the latest data of each widget under
+//! `values`, and the text each tracked widget shows under `contents`.
+//!
+//! `GET /metrics` serves the same data in the Prometheus text format. Each
+//! numeric field becomes a gauge named after the widget and field, such as
+//! `status_bar_cpu_usage`, labelled with the widget's text fields, such as
+//! the battery's `status`. Nothing is sampled on request: scrapes see the
+//! values from the widgets' last ticks.
+//!
+//! The endpoint has no authentication, so it should only be bound to a
+//! loopback address.
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::state;
+
+/// How long a client may take to send each part of its request
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request line accepted, in bytes
+const MAX_REQUEST_LINE: u64 = 8 * 1024;
+
+/// Most bytes of headers accepted, all together
+const MAX_HEADERS: u64 = 16 * 1024;
+
+/// Starts answering requests on `address`, e.g. `127.0.0.1:9273`
+pub fn serve(address: &str) -> Result<()> {
+    let listener = TcpListener::bind(address)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(io::Result::ok) {
+            thread::spawn(move || {
+                if let Err(e) = answer(stream) {
+                    eprintln!("Export client error: {e}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn answer(stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_LINE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    if !request_line.ends_with('\n') {
+        // Either the line is too long or the client hung up
+        if reader.get_ref().limit() == 0 {
+            respond(
+                &stream,
+                "414 URI Too Long",
+                "text/plain",
+                "request line too long\n",
+            )?;
+        }
+        return Ok(());
+    }
+
+    // Headers are of no interest, but must be read before replying
+    reader.get_mut().set_limit(MAX_HEADERS);
+    let mut header = String::new();
+    loop {
+        header.clear();
+        reader.read_line(&mut header)?;
+        if !header.ends_with('\n') {
+            if reader.get_ref().limit() == 0 {
+                let status = "431 Request Header Fields Too Large";
+                respond(&stream, status, "text/plain", "headers too large\n")?;
+            }
+            return Ok(());
+        }
+        if header.len() <= 2 {
+            break;
+        }
+    }
+
+    let mut words = request_line.split_whitespace();
+    let (method, path) = (words.next(), words.next().unwrap_or("/"));
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        (Some("GET"), "/" | "/json") => {
+            let bar = state::bar();
+            let body = json!({
+                "values": bar.values(),
+                "contents": bar.contents(),
+            });
+            ("200 OK", "application/json", body.to_string())
+        }
+        (Some("GET"), "/metrics") => ("200 OK", "text/plain; version=0.0.4", metrics()),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+
+    respond(&stream, status, content_type, &body)
+}
+
+fn respond(mut stream: &TcpStream, status: &str, content_type: &str, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// All widget values in the Prometheus text format
+fn metrics() -> String {
+    let mut output = String::new();
+
+    for (widget, value) in state::bar().values() {
+        let Value::Object(fields) = &value else {
+            continue;
+        };
+
+        let labels: Vec<String> = fields
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.as_str()?;
+                Some(format!("{}=\"{}\"", metric_name(name), escape_label(value)))
+            })
+            .collect();
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels.join(","))
+        };
+
+        let mut samples = vec![];
+        flatten(
+            &metric_name(&format!("status_bar_{widget}")),
+            &value,
+            &mut samples,
+        );
+        for (name, sample) in samples {
+            let _ = writeln!(output, "# TYPE {name} gauge\n{name}{labels} {sample}");
+        }
+    }
+
+    output
+}
+
+/// Collects the numeric leaves of `value`, named by their path from `prefix`.
+/// Text is used for labels instead, and arrays such as histories are left out
+fn flatten(prefix: &str, value: &Value, samples: &mut Vec<(String, f64)>) {
+    match value {
+        Value::Number(number) => samples.extend(number.as_f64().map(|n| (prefix.to_string(), n))),
+        Value::Bool(flag) => samples.push((prefix.to_string(), f64::from(u8::from(*flag)))),
+        Value::Object(fields) => {
+            // Durations serialize as whole seconds and nanoseconds
+            let seconds = fields.get("secs").and_then(Value::as_f64);
+            let nanos = fields.get("nanos").and_then(Value::as_f64);
+            if let (Some(seconds), Some(nanos), 2) = (seconds, nanos, fields.len()) {
+                samples.push((format!("{prefix}_seconds"), seconds + nanos / 1e9));
+                return;
+            }
+
+            for (name, value) in fields {
+                flatten(&format!("{prefix}_{}", metric_name(name)), value, samples);
+            }
+        }
+        Value::Null | Value::String(_) | Value::Array(_) => {}
+    }
+}
+
+/// `name` with the characters Prometheus doesn't allow replaced
+fn metric_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,221 @@
+//! The cpufreq scaling governor and turbo boost state, for trading
+//! performance against battery life.
+//!
+//! cnx doesn't deliver clicks, so they are changed with the `governor` IPC
+//! command: `governor next` cycles through the available governors,
+//! `governor <name>` picks one, and `governor turbo on|off|toggle` switches
+//! boost. Changes are written as root, see [`crate::privileged`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::polling::PollingWidget;
+use crate::privileged::Knob;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, ipc, markup, privileged, render, state};
+
+pub(crate) const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// Set by `intel_pstate`, where 1 turns turbo off
+pub(crate) const NO_TURBO: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+
+/// Set by `acpi-cpufreq` and `amd-pstate`, where 1 turns boost on
+pub(crate) const BOOST: &str = "/sys/devices/system/cpu/cpufreq/boost";
+
+// Abstracted type to represent the render closure
+type GovernorRender = Box<dyn Render<GovernorInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GovernorInfo {
+    /// Governor of the first CPU, e.g. `powersave`
+    pub governor: String,
+    /// Governors the driver offers
+    pub available: Vec<String>,
+    /// Whether turbo boost is on, `None` if the driver has no switch for it
+    pub turbo: Option<bool>,
+}
+
+impl Fields for GovernorInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.cpu))),
+            "governor" => Some(Value::Text(self.governor.clone())),
+            "turbo" => Some(Value::Number(f64::from(u8::from(self.turbo?)))),
+            _ => None,
+        }
+    }
+}
+
+fn read(path: impl AsRef<Path>) -> Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+/// The `scaling_governor` file of every CPU
+pub(crate) fn governor_paths() -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(CPU_DIR)?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("cpu")
+                .is_some_and(|index| index.parse::<u32>().is_ok())
+        })
+        .map(|entry| entry.path().join("cpufreq/scaling_governor"))
+        .filter(|path| path.exists())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn turbo() -> Option<bool> {
+    if let Ok(no_turbo) = read(NO_TURBO) {
+        return Some(no_turbo == "0");
+    }
+    read(BOOST).ok().map(|boost| boost == "1")
+}
+
+fn set_turbo(on: bool) -> Result<()> {
+    if !Path::new(NO_TURBO).exists() && !Path::new(BOOST).exists() {
+        bail!("the cpufreq driver has no turbo switch");
+    }
+    privileged::set(&Knob::Turbo(on))
+}
+
+fn read_governor() -> Result<GovernorInfo> {
+    let cpufreq = Path::new(CPU_DIR).join("cpu0/cpufreq");
+    Ok(GovernorInfo {
+        governor: read(cpufreq.join("scaling_governor"))?,
+        available: read(cpufreq.join("scaling_available_governors"))?
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        turbo: turbo(),
+    })
+}
+
+fn set_governor(args: &[&str]) -> Result<String> {
+    let governor_info = read_governor()?;
+    match args {
+        ["next"] => {
+            let index = governor_info
+                .available
+                .iter()
+                .position(|governor| *governor == governor_info.governor)
+                .map_or(0, |index| index + 1);
+            let next = governor_info
+                .available
+                .get(index)
+                .or_else(|| governor_info.available.first())
+                .ok_or_else(|| anyhow!("no governors available"))?;
+            privileged::set(&Knob::Governor(next.clone()))?;
+            Ok(next.clone())
+        }
+        ["turbo", "on"] => set_turbo(true).map(|()| String::new()),
+        ["turbo", "off"] => set_turbo(false).map(|()| String::new()),
+        ["turbo", "toggle"] => {
+            let on = governor_info
+                .turbo
+                .ok_or_else(|| anyhow!("the cpufreq driver has no turbo switch"))?;
+            set_turbo(!on).map(|()| String::new())
+        }
+        [name]
+            if governor_info
+                .available
+                .iter()
+                .any(|governor| governor == *name) =>
+        {
+            privileged::set(&Knob::Governor(name.to_string()))?;
+            Ok(String::new())
+        }
+        [name] if *name != "turbo" => bail!(
+            "unknown governor {name}, available: {}",
+            governor_info.available.join(" ")
+        ),
+        _ => bail!("usage: governor next|<name>|turbo on|off|toggle"),
+    }
+}
+
+/// cnx widget that shows the CPU frequency governor and whether turbo boost
+/// is on, and registers the `governor` IPC command
+pub struct Governor {
+    attrs: Attributes,
+    render: Option<GovernorRender>,
+    update_interval: Duration,
+}
+
+impl Governor {
+    /// Creates a new [`Governor`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<GovernorRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the governor is read. The
+    /// widget also updates straight after a `governor` command
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<GovernorRender>,
+        update_interval: Duration,
+    ) -> Governor {
+        Governor {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+}
+
+fn default_render(governor_info: &GovernorInfo) -> String {
+    let turbo = match governor_info.turbo {
+        Some(true) => format!(
+            " <span foreground=\"{}\">turbo</span>",
+            render::theme().warning.to_hex()
+        ),
+        _ => String::new(),
+    };
+
+    format!(
+        "{} {}{turbo}",
+        icons::icon_markup(icons::icons().cpu),
+        markup::escape(&governor_info.governor)
+    )
+}
+
+impl Widget for Governor {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Governor {
+            attrs,
+            render,
+            update_interval,
+        } = *self;
+
+        ipc::register("governor", |args| {
+            let output = set_governor(args)?;
+            state::bar().request_refresh();
+            Ok(output)
+        });
+
+        let show = move |governor_info: GovernorInfo| match &render {
+            Some(render) => render.render(governor_info, &RenderContext::current()),
+            None => default_render(&governor_info),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, read_governor, show).with_export("governor");
+        Box::new(widget).into_stream()
+    }
+}
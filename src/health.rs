@@ -0,0 +1,224 @@
+//! Kernel resources that are rarely watched until they run out: open files
+//! against the system-wide limit, the user's processes against their
+//! `ulimit -u`, and the entropy pool.
+//!
+//! The default render stays hidden until one of them is close to exhaustion.
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::polling::PollingWidget;
+use crate::processes::parse_stat;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, render};
+
+/// Share of a limit in use at which the default render warns
+const WARNING_USAGE: f64 = 0.8;
+
+/// Share of a limit in use at which the default render turns critical
+const CRITICAL_USAGE: f64 = 0.95;
+
+/// Bits of entropy below which the default render warns. Kernels since 5.18
+/// always report 256
+const LOW_ENTROPY: u32 = 128;
+
+// Abstracted type to represent the render closure
+type HealthRender = Box<dyn Render<HealthInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HealthInfo {
+    /// File handles allocated system-wide
+    pub open_files: u64,
+    /// Most file handles the kernel will allocate, `fs.file-max`
+    pub max_files: u64,
+    /// Processes and threads owned by the user running the bar
+    pub user_processes: u64,
+    /// The user's soft `RLIMIT_NPROC`, `None` if unlimited
+    pub max_user_processes: Option<u64>,
+    /// Bits in the entropy pool
+    pub entropy: u32,
+}
+
+impl HealthInfo {
+    fn file_usage(&self) -> f64 {
+        self.open_files as f64 / self.max_files.max(1) as f64
+    }
+
+    fn process_usage(&self) -> Option<f64> {
+        Some(self.user_processes as f64 / self.max_user_processes?.max(1) as f64)
+    }
+}
+
+impl Fields for HealthInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.health))),
+            "files" => Some(Value::Number(self.file_usage() * 100.0)),
+            "processes" => Some(Value::Number(self.process_usage()? * 100.0)),
+            "entropy" => Some(Value::Number(f64::from(self.entropy))),
+            _ => None,
+        }
+    }
+}
+
+/// The soft limit named `name` in a `/proc/<pid>/limits`, `None` if it is
+/// unlimited
+fn soft_limit(limits: &str, name: &str) -> Result<Option<u64>> {
+    let line = limits
+        .lines()
+        .find(|line| line.starts_with(name))
+        .ok_or_else(|| anyhow!("no {name} limit"))?;
+    let soft = line[name.len()..]
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("no soft {name} limit"))?;
+    Ok(soft.parse().ok())
+}
+
+/// Processes and threads owned by `uid`, which is what `RLIMIT_NPROC` counts
+fn count_user_tasks(uid: u32) -> Result<u64> {
+    let mut tasks = 0;
+    for entry in fs::read_dir("/proc")?.flatten() {
+        // Processes exit between listing and reading
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.uid() != uid {
+            continue;
+        }
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        if let Some((_, _, threads)) = parse_stat(&stat) {
+            tasks += u64::from(threads);
+        }
+    }
+    Ok(tasks)
+}
+
+fn read() -> Result<HealthInfo> {
+    // `allocated  unused  maximum`
+    let file_nr = fs::read_to_string("/proc/sys/fs/file-nr")?;
+    let mut file_nr = file_nr.split_whitespace().map(str::parse::<u64>);
+    let (Some(Ok(open_files)), Some(Ok(_)), Some(Ok(max_files))) =
+        (file_nr.next(), file_nr.next(), file_nr.next())
+    else {
+        return Err(anyhow!("unexpected contents in /proc/sys/fs/file-nr"));
+    };
+
+    let uid = fs::metadata("/proc/self")?.uid();
+    let limits = fs::read_to_string("/proc/self/limits")?;
+
+    Ok(HealthInfo {
+        open_files,
+        max_files,
+        user_processes: count_user_tasks(uid)?,
+        max_user_processes: soft_limit(&limits, "Max processes")?,
+        entropy: fs::read_to_string("/proc/sys/kernel/random/entropy_avail")?
+            .trim()
+            .parse()?,
+    })
+}
+
+/// cnx widget that warns when open files, the user's processes or the
+/// entropy pool near their limits
+pub struct Health {
+    attrs: Attributes,
+    render: Option<HealthRender>,
+    update_interval: Duration,
+}
+
+impl Health {
+    /// Creates a new [`Health`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<HealthRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the limits are read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<HealthRender>,
+        update_interval: Duration,
+    ) -> Health {
+        Health {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+}
+
+fn default_render(health_info: &HealthInfo) -> String {
+    let theme = render::theme();
+    let usage = |label: &str, usage: f64| {
+        let colour = if usage >= CRITICAL_USAGE {
+            &theme.critical
+        } else {
+            &theme.warning
+        };
+        format!(
+            "<span foreground=\"{}\">{label} {:.0}%</span>",
+            colour.to_hex(),
+            usage * 100.0
+        )
+    };
+
+    let mut warnings = vec![];
+    if health_info.file_usage() >= WARNING_USAGE {
+        warnings.push(usage("files", health_info.file_usage()));
+    }
+    if let Some(processes) = health_info.process_usage() {
+        if processes >= WARNING_USAGE {
+            warnings.push(usage("procs", processes));
+        }
+    }
+    if health_info.entropy < LOW_ENTROPY {
+        warnings.push(format!(
+            "<span foreground=\"{}\">entropy {}</span>",
+            theme.warning.to_hex(),
+            health_info.entropy
+        ));
+    }
+
+    if warnings.is_empty() {
+        return String::new();
+    }
+    format!(
+        "{} {}",
+        icons::icon_markup(icons::icons().health),
+        warnings.join(" ")
+    )
+}
+
+impl Widget for Health {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Health {
+            attrs,
+            render,
+            update_interval,
+        } = *self;
+
+        let show = move |health_info: HealthInfo| match &render {
+            Some(render) => render.render(health_info, &RenderContext::current()),
+            None => default_render(&health_info),
+        };
+
+        let widget = PollingWidget::new(attrs, update_interval, read, show).with_export("health");
+        Box::new(widget).into_stream()
+    }
+}
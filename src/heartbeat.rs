@@ -0,0 +1,206 @@
+//! The bar keeping an eye on itself: its memory and CPU usage, and whether a
+//! widget has stopped drawing, which happens silently when a widget's stream
+//! ends or hangs.
+//!
+//! Only widgets wrapped in a [`Tracked`](crate::state::Tracked) with an
+//! interval can be noticed as stale. The default render stays hidden unless
+//! one is, or the bar uses more than it should.
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use byte_unit::{Byte, UnitType};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::collectors::byte_count;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, render, state};
+
+/// Resident memory above which the default render shows itself
+const RSS_WARNING: u64 = 200 * 1024 * 1024;
+
+/// CPU usage in percent above which the default render shows itself
+const CPU_WARNING: f64 = 5.0;
+
+/// Clock ticks per second in `/proc/<pid>/stat`, fixed at 100 on Linux
+/// whatever the kernel's own tick rate
+const USER_HZ: f64 = 100.0;
+
+// Abstracted type to represent the render closure
+type HeartbeatRender = Box<dyn Render<HeartbeatInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeartbeatInfo {
+    /// Resident memory of the bar
+    #[serde(with = "byte_count")]
+    pub rss: Byte,
+    /// CPU time the bar used since the previous reading, as a percentage of
+    /// one core
+    pub cpu: f64,
+    /// The widget longest overdue, if any has missed too many intervals
+    pub stale: Option<String>,
+    /// Seconds since the stale widget last drew
+    pub stale_age: Option<u64>,
+}
+
+impl Fields for HeartbeatInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.heartbeat))),
+            "rss" => Some(Value::Text(format!(
+                "{:.1}",
+                self.rss.get_appropriate_unit(UnitType::Binary)
+            ))),
+            "cpu" => Some(Value::Number(self.cpu)),
+            "stale" => Some(Value::Text(self.stale.clone()?)),
+            "stale_age" => Some(Value::Number(self.stale_age? as f64)),
+            _ => None,
+        }
+    }
+}
+
+/// `VmRSS` from `/proc/self/status`, which is in KiB
+fn rss() -> Result<u64> {
+    fs::read_to_string("/proc/self/status")?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+        .map(|kibibytes| kibibytes * 1024)
+        .ok_or_else(|| anyhow!("no VmRSS in /proc/self/status"))
+}
+
+/// User and system time the bar has used, from `/proc/self/stat`, whose 14th
+/// and 15th fields count clock ticks
+fn cpu_time() -> Result<Duration> {
+    let stat = fs::read_to_string("/proc/self/stat")?;
+    // The name may itself contain spaces and parentheses
+    let (_, rest) = stat
+        .rsplit_once(") ")
+        .ok_or_else(|| anyhow!("unexpected contents in /proc/self/stat"))?;
+    let mut fields = rest.split_whitespace().skip(11);
+    let (Some(Ok(user)), Some(Ok(system))) = (
+        fields.next().map(str::parse::<u64>),
+        fields.next().map(str::parse::<u64>),
+    ) else {
+        return Err(anyhow!("unexpected contents in /proc/self/stat"));
+    };
+    Ok(Duration::from_secs_f64((user + system) as f64 / USER_HZ))
+}
+
+/// Reads the bar's usage, with CPU usage relative to the previous reading
+struct HeartbeatCollector {
+    previous: Option<(Instant, Duration)>,
+}
+
+impl HeartbeatCollector {
+    fn collect(&mut self) -> Result<HeartbeatInfo> {
+        let now = Instant::now();
+        let cpu_time = cpu_time()?;
+        let cpu = match self.previous.replace((now, cpu_time)) {
+            Some((then, previous)) if now > then => {
+                100.0 * cpu_time.saturating_sub(previous).as_secs_f64()
+                    / now.duration_since(then).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+
+        let stale = state::bar().stale().into_iter().next();
+        Ok(HeartbeatInfo {
+            rss: Byte::from_u64(rss()?),
+            cpu,
+            stale_age: stale.as_ref().map(|stale| stale.age.as_secs()),
+            stale: stale.map(|stale| stale.name),
+        })
+    }
+}
+
+/// cnx widget that shows the bar's own resource usage and the widget which
+/// has gone longest without drawing
+pub struct Heartbeat {
+    attrs: Attributes,
+    render: Option<HeartbeatRender>,
+    update_interval: Duration,
+}
+
+impl Heartbeat {
+    /// Creates a new [`Heartbeat`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<HeartbeatRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often usage is read and widgets
+    /// are checked
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<HeartbeatRender>,
+        update_interval: Duration,
+    ) -> Heartbeat {
+        Heartbeat {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+}
+
+fn default_render(heartbeat_info: &HeartbeatInfo) -> String {
+    let theme = render::theme();
+    let mut parts = vec![];
+
+    if heartbeat_info.rss.as_u64() >= RSS_WARNING || heartbeat_info.cpu >= CPU_WARNING {
+        parts.push(format!(
+            "<span foreground=\"{}\">{:.1} {:.0}%</span>",
+            theme.warning.to_hex(),
+            heartbeat_info.rss.get_appropriate_unit(UnitType::Binary),
+            heartbeat_info.cpu
+        ));
+    }
+    if let (Some(stale), Some(age)) = (&heartbeat_info.stale, heartbeat_info.stale_age) {
+        parts.push(format!(
+            "<span foreground=\"{}\">{stale} {age}s</span>",
+            theme.critical.to_hex()
+        ));
+    }
+
+    if parts.is_empty() {
+        return String::new();
+    }
+    format!(
+        "{} {}",
+        icons::icon_markup(icons::icons().heartbeat),
+        parts.join(" ")
+    )
+}
+
+impl Widget for Heartbeat {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Heartbeat {
+            attrs,
+            render,
+            update_interval,
+        } = *self;
+
+        let mut collector = HeartbeatCollector { previous: None };
+        let show = move |heartbeat_info: HeartbeatInfo| match &render {
+            Some(render) => render.render(heartbeat_info, &RenderContext::current()),
+            None => default_render(&heartbeat_info),
+        };
+
+        let widget = PollingWidget::new(attrs, update_interval, move || collector.collect(), show)
+            .with_export("heartbeat");
+        Box::new(widget).into_stream()
+    }
+}
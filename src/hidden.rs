@@ -0,0 +1,143 @@
+//! Count of minimised windows, which are otherwise easy to lose track of.
+//!
+//! cnx has no popups, so the windows are restored with the `hidden` IPC
+//! command instead, e.g. from rofi or dmenu: `hidden list` prints them,
+//! numbered from 1, and `hidden show <number>` restores and focuses one.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::ewmh::Ewmh;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::{ipc, render, state};
+
+// Abstracted type to represent the render closure
+type HiddenRender = Box<dyn Render<HiddenInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HiddenInfo {
+    /// Titles of the minimised windows, from oldest to newest
+    pub titles: Vec<String>,
+}
+
+/// Minimised windows from oldest to newest, with their titles
+fn hidden_windows(ewmh: &Ewmh) -> Result<Vec<(u32, String)>> {
+    let mut windows = vec![];
+    for window in ewmh.windows()? {
+        // Errors here are windows closed since the list was read
+        if let Ok(true) = ewmh.hidden(window) {
+            windows.push((window, ewmh.title(window).unwrap_or_default()));
+        }
+    }
+    Ok(windows)
+}
+
+/// cnx widget that counts minimised windows, hidden while there are none,
+/// and registers the `hidden` IPC command
+pub struct Hidden {
+    attrs: Attributes,
+    render: Option<HiddenRender>,
+    update_interval: Duration,
+}
+
+impl Hidden {
+    /// Creates a new [`Hidden`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<HiddenRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the windows are read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<HiddenRender>,
+        update_interval: Duration,
+    ) -> Hidden {
+        Hidden {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+}
+
+fn default_render(hidden_info: &HiddenInfo) -> String {
+    if hidden_info.titles.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        "<span foreground=\"{}\">[{}]</span>",
+        render::theme().muted.to_hex(),
+        hidden_info.titles.len()
+    )
+}
+
+impl Widget for Hidden {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Hidden {
+            attrs,
+            render,
+            update_interval,
+        } = *self;
+
+        ipc::register("hidden", |args| {
+            let ewmh = Ewmh::connect()?;
+            let windows = hidden_windows(&ewmh)?;
+            match args {
+                ["list"] => Ok(windows
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (_, title))| format!("{}: {title}", index + 1))
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                ["show", number] => {
+                    let (window, _) = number
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|number| windows.get(number.checked_sub(1)?))
+                        .ok_or_else(|| anyhow!("no hidden window number {number}"))?;
+                    ewmh.activate(*window)?;
+                    state::bar().request_refresh();
+                    Ok(String::new())
+                }
+                _ => bail!("usage: hidden list|show <number>"),
+            }
+        });
+
+        // Connects on first use, and again after an error
+        let mut ewmh = None;
+        let collect = move || -> Result<HiddenInfo> {
+            let connection = match ewmh.take() {
+                Some(connection) => connection,
+                None => Ewmh::connect()?,
+            };
+            let windows = hidden_windows(&connection)?;
+            ewmh = Some(connection);
+            Ok(HiddenInfo {
+                titles: windows.into_iter().map(|(_, title)| title).collect(),
+            })
+        };
+
+        let show = move |hidden_info: HiddenInfo| match &render {
+            Some(render) => render.render(hidden_info, &RenderContext::current()),
+            None => default_render(&hidden_info),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, collect, show).with_export("hidden");
+        Box::new(widget).into_stream()
+    }
+}
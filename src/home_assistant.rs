@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use crate::cache::{self, Cache};
+use crate::render::{Render, RenderContext};
+use crate::secrets::Secret;
+use crate::{http, markup, net, state};
+
+// Abstracted type to represent the render closure
+type HomeAssistantRender = Box<dyn Render<HomeAssistantInfo>>;
+
+/// A Home Assistant entity to display, and how to display it
+pub struct HassEntity {
+    pub entity_id: String,
+    pub icon: String,
+    /// Pango markup used by the default render. `{icon}`, `{state}`,
+    /// `{unit}` and `{name}` are replaced with the entity's values, escaped
+    pub template: String,
+}
+
+impl HassEntity {
+    /// Creates an entity shown as `{icon} {state}{unit}`
+    #[must_use]
+    pub fn new(entity_id: &str, icon: &str) -> HassEntity {
+        HassEntity {
+            entity_id: entity_id.to_string(),
+            icon: icon.to_string(),
+            template: "{icon} {state}{unit}".to_string(),
+        }
+    }
+
+    /// Shows the entity with `template` rather than `{icon} {state}{unit}`
+    #[must_use]
+    pub fn with_template(mut self, template: &str) -> HassEntity {
+        self.template = template.to_string();
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct EntityState {
+    pub entity_id: String,
+    /// Entity state, `unavailable` if it could not be fetched
+    pub state: String,
+    pub unit: Option<String>,
+    pub friendly_name: Option<String>,
+}
+
+pub struct HomeAssistantInfo {
+    pub entities: Vec<EntityState>,
+    /// Whether the states are out of date because the server can't be
+    /// reached
+    pub stale: bool,
+}
+
+/// cnx widget that polls the state of Home Assistant entities through its
+/// REST API
+pub struct HomeAssistant {
+    attrs: Attributes,
+    render: Option<HomeAssistantRender>,
+    update_interval: Duration,
+    server: String,
+    token: Secret,
+    entities: Vec<HassEntity>,
+    agent: ureq::Agent,
+    cache: Cache<Vec<EntityState>>,
+}
+
+impl HomeAssistant {
+    /// Creates a new [`HomeAssistant`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<HomeAssistantRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often entity states are fetched
+    ///
+    /// `server`: [`String`] - Base URL of the Home Assistant instance
+    ///
+    /// `token`: [`Secret`] - Long-lived access token, or where to fetch it
+    /// from
+    ///
+    /// `entities`: [`Vec<HassEntity>`] - Entities to show
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<HomeAssistantRender>,
+        update_interval: Duration,
+        server: String,
+        token: impl Into<Secret>,
+        entities: Vec<HassEntity>,
+    ) -> HomeAssistant {
+        HomeAssistant {
+            attrs,
+            render,
+            update_interval,
+            server: server.trim_end_matches('/').to_string(),
+            token: token.into(),
+            entities,
+            agent: http::agent(Duration::from_secs(10)),
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let client = Client {
+            agent: self.agent.clone(),
+            server: self.server.clone(),
+            token: self.token.clone(),
+        };
+        let entity_ids: Vec<String> = self
+            .entities
+            .iter()
+            .map(|entity| entity.entity_id.clone())
+            .collect();
+        let cached = self
+            .cache
+            .get(move || net::request(&client.server, || client.fetch_all(&entity_ids)));
+
+        let hass_info = match cached {
+            Some(cached) => HomeAssistantInfo {
+                entities: cached.value,
+                stale: cached.stale,
+            },
+            None => HomeAssistantInfo {
+                entities: self
+                    .entities
+                    .iter()
+                    .map(|entity| unavailable(&entity.entity_id))
+                    .collect(),
+                stale: false,
+            },
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(hass_info, &RenderContext::current())
+        } else {
+            let text = self
+                .entities
+                .iter()
+                .zip(&hass_info.entities)
+                .map(|(entity, state)| {
+                    entity
+                        .template
+                        .replace("{icon}", &entity.icon)
+                        .replace("{state}", &markup::escape(&state.state))
+                        .replace(
+                            "{unit}",
+                            &markup::escape(state.unit.as_deref().unwrap_or_default()),
+                        )
+                        .replace(
+                            "{name}",
+                            &markup::escape(
+                                state.friendly_name.as_deref().unwrap_or(&state.entity_id),
+                            ),
+                        )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            if hass_info.stale {
+                cache::mark_stale(&text)
+            } else {
+                text
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for HomeAssistant {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// What is needed to query the API from the cache's background thread
+struct Client {
+    agent: ureq::Agent,
+    server: String,
+    token: Secret,
+}
+
+impl Client {
+    fn fetch(&self, entity_id: &str, token: &str) -> Result<EntityState> {
+        let body = self
+            .agent
+            .get(&format!("{}/api/states/{entity_id}", self.server))
+            .set("Authorization", &format!("Bearer {token}"))
+            .call()?
+            .into_string()?;
+
+        let json: Value = serde_json::from_str(&body)?;
+        let attribute = |name: &str| json["attributes"][name].as_str().map(str::to_string);
+
+        Ok(EntityState {
+            entity_id: entity_id.to_string(),
+            state: json["state"].as_str().unwrap_or("unknown").to_string(),
+            unit: attribute("unit_of_measurement"),
+            friendly_name: attribute("friendly_name"),
+        })
+    }
+
+    /// Fetches every entity, marking those which fail as unavailable. Fails
+    /// if none could be fetched, so the cache keeps the previous states
+    fn fetch_all(&self, entity_ids: &[String]) -> Result<Vec<EntityState>> {
+        let token = self.token.resolve()?;
+        let results: Vec<Result<EntityState>> =
+            entity_ids.iter().map(|id| self.fetch(id, &token)).collect();
+        if !results.is_empty() && results.iter().all(|result| result.is_err()) {
+            bail!("Home Assistant could not be reached");
+        }
+
+        Ok(results
+            .into_iter()
+            .zip(entity_ids)
+            .map(|(result, id)| result.unwrap_or_else(|_| unavailable(id)))
+            .collect())
+    }
+}
+
+fn unavailable(entity_id: &str) -> EntityState {
+    EntityState {
+        entity_id: entity_id.to_string(),
+        state: "unavailable".to_string(),
+        unit: None,
+        friendly_name: None,
+    }
+}
@@ -0,0 +1,118 @@
+//! HTTP client settings shared by every widget making web requests, for
+//! networks which only reach the internet through a proxy or intercept TLS
+//! with their own certificate authority:
+//!
+//! ```toml
+//! [http]
+//! proxy = "http://proxy.corp.example:3128"
+//! ca_bundle = "/etc/ssl/certs/corp-ca.pem"
+//! timeout = "20s"
+//! connect_timeout = "5s"
+//! ```
+//!
+//! Without a `proxy`, the usual `HTTPS_PROXY`, `HTTP_PROXY` and `ALL_PROXY`
+//! environment variables are followed. Certificates in `ca_bundle` are
+//! trusted on top of the usual web roots.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use rustls::{ClientConfig, RootCertStore};
+
+use crate::config::HttpConfig;
+use crate::dns;
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+#[derive(Default)]
+struct Settings {
+    proxy: Option<ureq::Proxy>,
+    tls: Option<Arc<ClientConfig>>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+}
+
+/// Applies `config` to every agent built from then on. Agents built before
+/// keep the defaults, so this should be called before creating widgets
+pub fn configure(config: &HttpConfig) -> Result<()> {
+    let proxy = match &config.proxy {
+        Some(proxy) => {
+            Some(ureq::Proxy::new(proxy).with_context(|| format!("Invalid proxy {proxy}"))?)
+        }
+        None => None,
+    };
+    let tls = match &config.ca_bundle {
+        Some(path) => {
+            Some(Arc::new(tls_config(path).with_context(|| {
+                format!("Could not load {}", path.display())
+            })?))
+        }
+        None => None,
+    };
+
+    let settings = Settings {
+        proxy,
+        tls,
+        timeout: config.timeout,
+        connect_timeout: config.connect_timeout,
+    };
+    if SETTINGS.set(settings).is_err() {
+        bail!("HTTP settings are already configured");
+    }
+    Ok(())
+}
+
+/// TLS trusting the certificates in the PEM file at `path` as well as the
+/// usual web roots
+fn tls_config(path: &Path) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    let mut added = 0;
+    for certificate in rustls_pemfile::certs(&mut BufReader::new(File::open(path)?)) {
+        roots.add(certificate?)?;
+        added += 1;
+    }
+    if added == 0 {
+        bail!("no certificates found");
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    Ok(ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+/// An agent builder with the shared settings applied, and `timeout` unless
+/// the config sets one. Widgets whose timeout is part of what they measure
+/// can still replace it
+#[must_use]
+pub fn builder(timeout: Duration) -> ureq::AgentBuilder {
+    let settings = SETTINGS.get_or_init(Settings::default);
+
+    let mut builder = ureq::AgentBuilder::new()
+        .timeout(settings.timeout.unwrap_or(timeout))
+        .try_proxy_from_env(true)
+        .resolver(dns::Resolver);
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    if let Some(tls) = &settings.tls {
+        builder = builder.tls_config(Arc::clone(tls));
+    }
+    if let Some(connect_timeout) = settings.connect_timeout {
+        builder = builder.timeout_connect(connect_timeout);
+    }
+    builder
+}
+
+/// An agent with the shared settings applied, see [`builder`]
+#[must_use]
+pub fn agent(timeout: Duration) -> ureq::Agent {
+    builder(timeout).build()
+}
@@ -0,0 +1,177 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::cache::{self, Cache};
+use crate::render::{Render, RenderContext};
+use crate::{http, markup, net, state};
+
+// Abstracted type to represent the render closure
+type HttpCheckRender = Box<dyn Render<HttpCheckInfo>>;
+
+#[derive(Clone)]
+pub struct EndpointStatus {
+    pub url: String,
+    /// Whether the endpoint answered with a non-5xx status
+    pub up: bool,
+    /// HTTP status code, `None` if no response was received
+    pub status: Option<u16>,
+    pub response_time: Duration,
+}
+
+#[derive(Clone)]
+pub struct HttpCheckInfo {
+    pub endpoints: Vec<EndpointStatus>,
+    /// Whether the statuses are out of date because checking is failing,
+    /// e.g. while offline
+    pub stale: bool,
+}
+
+/// cnx widget that polls a set of URLs and shows whether each is up
+pub struct HttpCheck {
+    attrs: Attributes,
+    render: Option<HttpCheckRender>,
+    update_interval: Duration,
+    urls: Vec<String>,
+    agent: ureq::Agent,
+    cache: Cache<Vec<EndpointStatus>>,
+}
+
+impl HttpCheck {
+    /// Creates a new [`HttpCheck`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<HttpCheckRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the URLs are polled
+    ///
+    /// `timeout`: [`Duration`] - How long to wait for a response before
+    /// counting an endpoint as down
+    ///
+    /// `urls`: [`Vec<String>`] - URLs to poll
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<HttpCheckRender>,
+        update_interval: Duration,
+        timeout: Duration,
+        urls: Vec<String>,
+    ) -> HttpCheck {
+        HttpCheck {
+            attrs,
+            render,
+            update_interval,
+            urls,
+            // The widget's own timeout is what decides an endpoint is down
+            agent: http::builder(timeout).timeout(timeout).build(),
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let (agent, urls) = (self.agent.clone(), self.urls.clone());
+        let cached = self.cache.get(move || check_all(&agent, &urls));
+        let Some(cached) = cached else {
+            return vec![];
+        };
+
+        let http_info = HttpCheckInfo {
+            endpoints: cached.value,
+            stale: cached.stale,
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(http_info, &RenderContext::current())
+        } else {
+            let text = http_info
+                .endpoints
+                .iter()
+                .map(|endpoint| {
+                    let url = markup::escape(&endpoint.url);
+                    if endpoint.up {
+                        format!(
+                            "{url} <span foreground=\"{}\">{}ms</span>",
+                            Color::green().to_hex(),
+                            endpoint.response_time.as_millis()
+                        )
+                    } else {
+                        format!(
+                            "{url} <span foreground=\"{}\">down</span>",
+                            Color::red().to_hex()
+                        )
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            if http_info.stale {
+                cache::mark_stale(&text)
+            } else {
+                text
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for HttpCheck {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Checks `url` once. The timer starts inside the request, after
+/// [`net::request`]'s jitter, so only the endpoint's latency is measured.
+/// Fails only if the request isn't made at all, e.g. while offline
+fn check(agent: &ureq::Agent, url: &str) -> Result<EndpointStatus> {
+    net::request(url, || {
+        let start = Instant::now();
+        let status = match agent.get(url).call() {
+            Ok(response) => Some(response.status()),
+            Err(ureq::Error::Status(code, _)) => Some(code),
+            Err(_) => None,
+        };
+
+        // A down endpoint is a result, not a failure to back off from
+        Ok(EndpointStatus {
+            url: url.to_string(),
+            up: status.is_some_and(|code| code < 500),
+            status,
+            response_time: start.elapsed(),
+        })
+    })
+}
+
+/// Checks every URL at once, so a slow endpoint doesn't delay the others
+fn check_all(agent: &ureq::Agent, urls: &[String]) -> Result<Vec<EndpointStatus>> {
+    thread::scope(|scope| {
+        let checks: Vec<_> = urls
+            .iter()
+            .map(|url| scope.spawn(move || check(agent, url)))
+            .collect();
+        checks
+            .into_iter()
+            .map(|check| check.join().expect("endpoint check panicked"))
+            .collect()
+    })
+}
@@ -0,0 +1,221 @@
+//! Icons used by the default renders, selectable globally so they can match
+//! the fonts available on the system.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::RwLock;
+
+use serde::Deserialize;
+
+static ICON_SET: AtomicU8 = AtomicU8::new(IconSet::Emoji as u8);
+static ICON_FONT: RwLock<Option<String>> = RwLock::new(None);
+
+/// Configured as `icon_set = "emoji" | "nerd" | "text"`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum IconSet {
+    #[default]
+    #[serde(rename = "emoji")]
+    Emoji,
+    /// Glyphs from the Nerd Fonts private use area
+    #[serde(rename = "nerd")]
+    NerdFont,
+    /// Plain text labels which render with any font
+    #[serde(rename = "text")]
+    Ascii,
+}
+
+#[non_exhaustive]
+pub struct Icons {
+    pub battery: &'static str,
+    pub charging: &'static str,
+    pub cpu: &'static str,
+    pub memory: &'static str,
+    pub swap: &'static str,
+    pub disk: &'static str,
+    pub containers: &'static str,
+    pub kubernetes: &'static str,
+    pub ssh_key: &'static str,
+    pub ups: &'static str,
+    pub mouse: &'static str,
+    pub keyboard: &'static str,
+    pub headphones: &'static str,
+    pub volume: &'static str,
+    pub muted: &'static str,
+    pub caffeine: &'static str,
+    /// Power saver profile
+    pub power_saver: &'static str,
+    /// Performance power profile
+    pub performance: &'static str,
+    /// Any other battery powered device, e.g. a game controller
+    pub peripheral: &'static str,
+    /// Phases of the moon, in the order of [`crate::clock::MoonPhase`]
+    pub moon: [&'static str; 8],
+    pub alarm: &'static str,
+    pub stopwatch: &'static str,
+    /// Taking a break from work
+    pub rest: &'static str,
+    pub lock: &'static str,
+    /// Battery conservation mode, charging stopping short of full
+    pub conservation: &'static str,
+    pub processes: &'static str,
+    /// System resources nearing their limits
+    pub health: &'static str,
+    /// Errors in the system log
+    pub journal: &'static str,
+    /// Warnings from the kernel
+    pub kernel: &'static str,
+    /// The bar's own resource usage
+    pub heartbeat: &'static str,
+}
+
+const EMOJI: Icons = Icons {
+    battery: "🔋",
+    charging: "🔌",
+    cpu: "⚡",
+    memory: "🧠",
+    swap: "💾",
+    disk: "💽",
+    containers: "🐳",
+    kubernetes: "⎈",
+    ssh_key: "🔑",
+    ups: "🗄",
+    mouse: "🖱",
+    keyboard: "⌨",
+    headphones: "🎧",
+    volume: "🔊",
+    muted: "🔇",
+    caffeine: "☕",
+    power_saver: "🌿",
+    performance: "🚀",
+    peripheral: "🎮",
+    moon: ["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"],
+    alarm: "⏰",
+    stopwatch: "⏱",
+    rest: "🧘",
+    lock: "🔒",
+    conservation: "🛡",
+    processes: "⚙",
+    health: "🩺",
+    journal: "📜",
+    kernel: "🐧",
+    heartbeat: "💓",
+};
+
+const NERD_FONT: Icons = Icons {
+    battery: "\u{f240}",
+    charging: "\u{f1e6}",
+    cpu: "\u{f4bc}",
+    memory: "\u{f035b}",
+    swap: "\u{f0a0}",
+    disk: "\u{f02ca}",
+    containers: "\u{f308}",
+    kubernetes: "\u{f10fe}",
+    ssh_key: "\u{f084}",
+    ups: "\u{f0241}",
+    mouse: "\u{f037d}",
+    keyboard: "\u{f030c}",
+    headphones: "\u{f02cb}",
+    volume: "\u{f057e}",
+    muted: "\u{f075f}",
+    caffeine: "\u{f0176}",
+    power_saver: "\u{f032a}",
+    performance: "\u{f04c5}",
+    peripheral: "\u{f0297}",
+    moon: [
+        "\u{e38d}", "\u{e390}", "\u{e394}", "\u{e397}", "\u{e39b}", "\u{e39e}", "\u{e3a2}",
+        "\u{e3a5}",
+    ],
+    alarm: "\u{f0020}",
+    stopwatch: "\u{f051b}",
+    rest: "\u{f0f4}",
+    lock: "\u{f023}",
+    conservation: "\u{f132}",
+    processes: "\u{f013}",
+    health: "\u{f21e}",
+    journal: "\u{f071}",
+    kernel: "\u{f17c}",
+    heartbeat: "\u{f05f6}",
+};
+
+const ASCII: Icons = Icons {
+    battery: "BAT",
+    charging: "AC",
+    cpu: "CPU",
+    memory: "MEM",
+    swap: "SWP",
+    disk: "DSK",
+    containers: "CTR",
+    kubernetes: "K8S",
+    ssh_key: "KEY",
+    ups: "UPS",
+    mouse: "MSE",
+    keyboard: "KBD",
+    headphones: "HPH",
+    volume: "VOL",
+    muted: "MUT",
+    caffeine: "CAF",
+    power_saver: "ECO",
+    performance: "PRF",
+    peripheral: "DEV",
+    moon: ["NM", "WXC", "FQ", "WXG", "FM", "WNG", "LQ", "WNC"],
+    alarm: "ALM",
+    stopwatch: "SW",
+    rest: "BRK",
+    lock: "LCK",
+    conservation: "LIM",
+    processes: "PRC",
+    health: "SYS",
+    journal: "LOG",
+    kernel: "KRN",
+    heartbeat: "BAR",
+};
+
+/// Selects the icon set used by every widget's default render
+pub fn set_icon_set(set: IconSet) {
+    ICON_SET.store(set as u8, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn icon_set() -> IconSet {
+    match ICON_SET.load(Ordering::Relaxed) {
+        x if x == IconSet::NerdFont as u8 => IconSet::NerdFont,
+        x if x == IconSet::Ascii as u8 => IconSet::Ascii,
+        _ => IconSet::Emoji,
+    }
+}
+
+/// The icons of the currently selected [`IconSet`]
+#[must_use]
+pub fn icons() -> &'static Icons {
+    icons_of(icon_set())
+}
+
+/// The icons of `set`, whichever is selected
+#[must_use]
+pub fn icons_of(set: IconSet) -> &'static Icons {
+    match set {
+        IconSet::Emoji => &EMOJI,
+        IconSet::NerdFont => &NERD_FONT,
+        IconSet::Ascii => &ASCII,
+    }
+}
+
+/// Sets the font family used for icons in the default renders, `None` to use
+/// each widget's own font
+pub fn set_icon_font(font: Option<String>) {
+    *ICON_FONT.write().unwrap() = font;
+}
+
+/// Pango markup showing `icon` in `font`, or unchanged if `font` is `None`
+#[must_use]
+pub fn with_font(icon: &str, font: Option<&str>) -> String {
+    match font {
+        Some(font) => format!("<span font_family=\"{font}\">{icon}</span>"),
+        None => icon.to_string(),
+    }
+}
+
+/// Pango markup showing `icon` in the font set by [`set_icon_font`]
+#[must_use]
+pub fn icon_markup(icon: &str) -> String {
+    with_font(icon, ICON_FONT.read().unwrap().as_deref())
+}
@@ -0,0 +1,54 @@
+//! How long the user has been idle, from the X screensaver extension, shared
+//! by the widgets that react to it, and whether the screen is blanked.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use x11rb::connection::Connection;
+use x11rb::protocol::dpms::{ConnectionExt as _, DPMSMode};
+use x11rb::protocol::screensaver::{ConnectionExt as _, State};
+use x11rb::protocol::xproto::{ConnectionExt as _, Window};
+use x11rb::rust_connection::RustConnection;
+
+/// Connection to the X server, for reading the time since the last input
+pub struct Idle {
+    connection: RustConnection,
+    root: Window,
+}
+
+impl Idle {
+    pub fn connect() -> Result<Idle> {
+        let (connection, screen) = x11rb::connect(None)?;
+        let root = connection.setup().roots[screen].root;
+        Ok(Idle { connection, root })
+    }
+
+    /// Time since the last keyboard or mouse input
+    pub fn idle_time(&self) -> Result<Duration> {
+        let info = self.connection.screensaver_query_info(self.root)?.reply()?;
+        Ok(Duration::from_millis(u64::from(info.ms_since_user_input)))
+    }
+
+    /// Idle time after which the X screensaver starts, which is when
+    /// xss-lock locks the screen, `None` if it is turned off
+    pub fn screensaver_timeout(&self) -> Result<Option<Duration>> {
+        let timeout = self.connection.get_screen_saver()?.reply()?.timeout;
+        Ok((timeout > 0).then(|| Duration::from_secs(u64::from(timeout))))
+    }
+
+    /// Whether the screen is blanked, by DPMS putting the monitor into
+    /// standby or off, or by the X screensaver
+    pub fn screen_off(&self) -> Result<bool> {
+        let screensaver = self.connection.screensaver_query_info(self.root)?.reply()?;
+        if screensaver.state == u8::from(State::ON) {
+            return Ok(true);
+        }
+
+        // Servers without DPMS leave the monitor on
+        let Ok(dpms) = self.connection.dpms_info() else {
+            return Ok(false);
+        };
+        let dpms = dpms.reply()?;
+        Ok(dpms.state && dpms.power_level != DPMSMode::ON)
+    }
+}
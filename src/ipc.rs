@@ -0,0 +1,274 @@
+//! Control socket for the running bar.
+//!
+//! The bar listens on a unix socket named after the user and display. Binding
+//! it doubles as the single-instance lock. On Linux the socket is abstract, so
+//! the kernel releases the name when the process dies and a crash can't leave
+//! a stale lock behind. The BSDs have no abstract sockets, so there it is a
+//! file in `$XDG_RUNTIME_DIR`, and a file nobody is listening on is replaced.
+//!
+//! Clients send one command per connection as a line of whitespace separated
+//! words, and read the reply until the bar closes the connection. Any user can
+//! connect to an abstract socket, so clients running as another user are
+//! turned away.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+#[cfg(target_os = "linux")]
+use std::mem;
+use std::os::fd::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{SocketAddr, UnixListener, UnixStream};
+#[cfg(not(target_os = "linux"))]
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::state;
+
+/// How long `--replace` waits for the running instance to exit
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client may take to send its command
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest command accepted, in bytes including the newline
+const MAX_LINE: u64 = 4096;
+
+type Handler = Arc<dyn Fn(&[&str]) -> Result<String> + Send + Sync>;
+
+static COMMANDS: OnceLock<Mutex<HashMap<String, Handler>>> = OnceLock::new();
+
+fn commands() -> &'static Mutex<HashMap<String, Handler>> {
+    COMMANDS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Makes `handler` answer the command `name`. It is called with the words
+/// following the name, and its output is sent back to the client
+pub fn register(name: &str, handler: impl Fn(&[&str]) -> Result<String> + Send + Sync + 'static) {
+    commands()
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), Arc::new(handler));
+}
+
+/// The user the bar runs as
+fn own_uid() -> u32 {
+    // SAFETY: getuid can't fail and touches no memory
+    unsafe { libc::getuid() }
+}
+
+/// The user the process at the other end of `stream` ran as when it
+/// connected, from `SO_PEERCRED`
+#[cfg(target_os = "linux")]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: the kernel writes at most `length` bytes to `credentials`
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut credentials as *mut libc::ucred).cast(),
+            &mut length,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(credentials.uid)
+}
+
+/// The user the process at the other end of `stream` ran as when it
+/// connected, from `getpeereid`
+#[cfg(not(target_os = "linux"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: getpeereid only writes the two ids
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+#[cfg(target_os = "linux")]
+fn socket_address() -> io::Result<SocketAddr> {
+    let uid = own_uid();
+    let display = env::var("DISPLAY").unwrap_or_default();
+
+    SocketAddr::from_abstract_name(format!("status_bar.{uid}.{display}"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn socket_address() -> io::Result<SocketAddr> {
+    let uid = own_uid();
+    let display = env::var("DISPLAY").unwrap_or_default();
+    let dir = env::var_os("XDG_RUNTIME_DIR").map_or_else(env::temp_dir, PathBuf::from);
+
+    SocketAddr::from_pathname(dir.join(format!("status_bar.{uid}.{display}.sock")))
+}
+
+/// Binds `address`. A socket file whose owner is gone is removed and bound
+/// again, and a new one is made private to the user
+fn bind(address: &SocketAddr) -> io::Result<UnixListener> {
+    let listener = match UnixListener::bind_addr(address) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            let Some(path) = address.as_pathname() else {
+                return Err(e);
+            };
+            match UnixStream::connect_addr(address) {
+                Err(refused) if refused.kind() == io::ErrorKind::ConnectionRefused => {
+                    fs::remove_file(path)?;
+                    UnixListener::bind_addr(address)?
+                }
+                _ => return Err(e),
+            }
+        }
+        result => result?,
+    };
+
+    if let Some(path) = address.as_pathname() {
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(listener)
+}
+
+/// Claims the control socket and starts answering commands. With `replace`,
+/// a running instance is asked to quit first, otherwise finding one is an
+/// error
+pub fn serve(replace: bool) -> Result<()> {
+    let address = socket_address()?;
+
+    let listener = match bind(&address) {
+        Ok(listener) => listener,
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            if !replace {
+                bail!("status_bar is already running, start it with --replace to take over");
+            }
+            take_over(&address)?
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    register_builtins();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(io::Result::ok) {
+            thread::spawn(move || {
+                if let Err(e) = answer(stream) {
+                    eprintln!("IPC client error: {e}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Asks the instance holding `address` to quit and binds it once it has
+fn take_over(address: &SocketAddr) -> Result<UnixListener> {
+    let mut stream = UnixStream::connect_addr(address)?;
+    stream.write_all(b"quit\n")?;
+
+    let start = Instant::now();
+    loop {
+        match bind(address) {
+            Ok(listener) => return Ok(listener),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                if start.elapsed() >= REPLACE_TIMEOUT {
+                    bail!("the running status_bar did not quit");
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn register_builtins() {
+    register("quit", |_| {
+        state::bar().shutdown();
+        process::exit(0)
+    });
+
+    register("refresh", |_| {
+        state::bar().request_refresh();
+        Ok(String::new())
+    });
+
+    register("set", |args| match args {
+        [slot, text @ ..] => {
+            state::bar().set_slot(slot, text.join(" "));
+            Ok(String::new())
+        }
+        [] => bail!("usage: set <slot> <text>"),
+    });
+
+    register("get", |args| {
+        let contents = state::bar().contents();
+        match args {
+            [name] => contents
+                .get(*name)
+                .cloned()
+                .ok_or_else(|| anyhow!("no widget called {name}")),
+            _ => Ok(contents
+                .iter()
+                .map(|(name, content)| format!("{name}: {content}"))
+                .collect::<Vec<_>>()
+                .join("\n")),
+        }
+    });
+}
+
+fn answer(stream: UnixStream) -> Result<()> {
+    let uid = peer_uid(&stream)?;
+    if uid != own_uid() {
+        bail!("refused a client running as uid {uid}");
+    }
+
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_LINE)).read_line(&mut line)?;
+    if !line.ends_with('\n') && line.len() as u64 == MAX_LINE {
+        (&stream)
+            .write_all(format!("error: commands are limited to {MAX_LINE} bytes").as_bytes())?;
+        bail!("refused a command over {MAX_LINE} bytes");
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = words.split_first() else {
+        return Ok(());
+    };
+
+    let handler = commands().lock().unwrap().get(*name).cloned();
+    let reply = match handler {
+        Some(handler) => handler(args).unwrap_or_else(|e| format!("error: {e}")),
+        None => format!("error: unknown command {name}"),
+    };
+
+    (&stream).write_all(reply.as_bytes())?;
+    Ok(())
+}
+
+/// Sends `command` to the running bar and returns its reply
+pub fn send(command: &str) -> Result<String> {
+    let mut stream = UnixStream::connect_addr(&socket_address()?)?;
+    stream.write_all(command.as_bytes())?;
+    stream.write_all(b"\n")?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
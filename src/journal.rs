@@ -0,0 +1,228 @@
+//! Rate of error messages in the systemd journal, followed through
+//! `journalctl -f -o json`.
+//!
+//! The count covers a sliding window, and `journal clear` over IPC resets it
+//! once the errors have been looked at, since cnx can't take clicks.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, ipc, markup, render, state};
+
+/// How long to wait before following the journal again if `journalctl` exits
+const RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// Messages are cut down to this many characters by the default render
+const MAX_MESSAGE: usize = 40;
+
+// Abstracted type to represent the render closure
+type JournalRender = Box<dyn Render<JournalInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalInfo {
+    /// Error, critical, alert and emergency messages within the window
+    pub errors: usize,
+    /// The latest of them
+    pub last_message: Option<String>,
+}
+
+impl Fields for JournalInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.journal))),
+            "errors" => Some(Value::Number(self.errors as f64)),
+            "message" => Some(Value::Text(self.last_message.clone()?)),
+            _ => None,
+        }
+    }
+}
+
+/// Errors seen within the window, oldest first, as their journal timestamps
+/// and messages
+#[derive(Default)]
+struct Errors {
+    entries: VecDeque<(SystemTime, String)>,
+    /// When the errors were last cleared, so they stay cleared when the
+    /// journal is read again
+    cleared: Option<SystemTime>,
+}
+
+impl Errors {
+    fn push(&mut self, (time, message): (SystemTime, String)) {
+        if !self.cleared.is_some_and(|cleared| time <= cleared) {
+            self.entries.push_back((time, message));
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.cleared = Some(SystemTime::now());
+    }
+
+    fn prune(&mut self, window: Duration) {
+        let oldest = SystemTime::now() - window;
+        while self.entries.front().is_some_and(|(time, _)| *time < oldest) {
+            self.entries.pop_front();
+        }
+    }
+}
+
+/// The timestamp and message of a journal entry in `journalctl`'s JSON output
+fn parse_entry(line: &str) -> Option<(SystemTime, String)> {
+    let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+    let micros: u64 = entry["__REALTIME_TIMESTAMP"].as_str()?.parse().ok()?;
+    // Binary messages are arrays of bytes rather than strings
+    let message = entry["MESSAGE"].as_str().unwrap_or_default().to_string();
+    Some((UNIX_EPOCH + Duration::from_micros(micros), message))
+}
+
+/// Follows the journal on a background thread, adding errors to `errors`.
+/// Starts with the errors already within `window`
+fn follow(errors: Arc<Mutex<Errors>>, window: Duration) {
+    thread::spawn(move || loop {
+        let child = Command::new("journalctl")
+            .args(["--follow", "--output=json", "--priority=err", "--lines=all"])
+            .arg(format!("--since=-{}s", window.as_secs()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match child {
+            Ok(mut child) => {
+                if let Some(stdout) = child.stdout.take() {
+                    for line in BufReader::new(stdout).lines().map_while(io::Result::ok) {
+                        if let Some(entry) = parse_entry(&line) {
+                            errors.lock().unwrap().push(entry);
+                        }
+                    }
+                }
+                let _ = child.wait();
+            }
+            Err(e) => eprintln!("Could not follow the journal: {e}"),
+        }
+
+        // Entries are read again from the start of the window
+        errors.lock().unwrap().entries.clear();
+        thread::sleep(RESTART_DELAY);
+    });
+}
+
+/// cnx widget that counts recent errors in the journal, hidden while there
+/// are none, and registers the `journal` IPC command
+pub struct Journal {
+    attrs: Attributes,
+    render: Option<JournalRender>,
+    update_interval: Duration,
+    window: Duration,
+}
+
+impl Journal {
+    /// Creates a new [`Journal`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<JournalRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the count is updated
+    ///
+    /// `window`: [`Duration`] - How far back errors are counted
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<JournalRender>,
+        update_interval: Duration,
+        window: Duration,
+    ) -> Journal {
+        Journal {
+            attrs,
+            render,
+            update_interval,
+            window,
+        }
+    }
+}
+
+fn default_render(journal_info: &JournalInfo) -> String {
+    if journal_info.errors == 0 {
+        return String::new();
+    }
+
+    let mut text = format!(
+        "{} {}",
+        icons::icon_markup(icons::icons().journal),
+        journal_info.errors
+    );
+    if let Some(last_message) = &journal_info.last_message {
+        let mut message: String = last_message.chars().take(MAX_MESSAGE).collect();
+        if message.len() < last_message.len() {
+            message.push('…');
+        }
+        text.push(' ');
+        text.push_str(&markup::escape(&message));
+    }
+
+    format!(
+        "<span foreground=\"{}\">{text}</span>",
+        render::theme().critical.to_hex()
+    )
+}
+
+impl Widget for Journal {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Journal {
+            attrs,
+            render,
+            update_interval,
+            window,
+        } = *self;
+
+        let errors = Arc::new(Mutex::new(Errors::default()));
+        follow(Arc::clone(&errors), window);
+
+        let cleared = Arc::clone(&errors);
+        ipc::register("journal", move |args| match args {
+            ["clear"] => {
+                cleared.lock().unwrap().clear();
+                state::bar().request_refresh();
+                Ok(String::new())
+            }
+            _ => bail!("usage: journal clear"),
+        });
+
+        let collect = move || -> Result<JournalInfo> {
+            let mut errors = errors.lock().unwrap();
+            errors.prune(window);
+            Ok(JournalInfo {
+                errors: errors.entries.len(),
+                last_message: errors.entries.back().map(|(_, message)| message.clone()),
+            })
+        };
+
+        let show = move |journal_info: JournalInfo| match &render {
+            Some(render) => render.render(journal_info, &RenderContext::current()),
+            None => default_render(&journal_info),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, collect, show).with_export("journal");
+        Box::new(widget).into_stream()
+    }
+}
@@ -0,0 +1,184 @@
+//! Kernel warnings read from `/dev/kmsg`, such as oopses, hung tasks and
+//! thermal throttling, which otherwise go unnoticed in `dmesg`.
+//!
+//! A warning glyph stays up until it is acknowledged with the `kernel ack`
+//! IPC command. Reading `/dev/kmsg` needs `kernel.dmesg_restrict` to be 0, or
+//! the `CAP_SYSLOG` capability.
+
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, ipc, markup, render, state};
+
+const KMSG: &str = "/dev/kmsg";
+
+/// Log levels up to `KERN_WARNING` count as warnings
+const WARNING_LEVEL: u8 = 4;
+
+/// Messages at lower levels which still deserve attention
+const PATTERNS: &[&str] = &[
+    "Oops",
+    "BUG:",
+    "WARNING:",
+    "hung_task",
+    "blocked for more than",
+    "throttled",
+    "Out of memory",
+];
+
+/// Messages are cut down to this many characters by the default render
+const MAX_MESSAGE: usize = 40;
+
+// Abstracted type to represent the render closure
+type KmsgRender = Box<dyn Render<KmsgInfo>>;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct KmsgInfo {
+    /// Warnings since the last acknowledgement
+    pub warnings: usize,
+    /// The latest of them
+    pub last_message: Option<String>,
+}
+
+impl Fields for KmsgInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.kernel))),
+            "warnings" => Some(Value::Number(self.warnings as f64)),
+            "message" => Some(Value::Text(self.last_message.clone()?)),
+            _ => None,
+        }
+    }
+}
+
+/// The message of a `/dev/kmsg` record if it is a warning. Records look like
+/// `4,1234,5678901,-;message`, starting with the facility and level
+fn warning(record: &str) -> Option<&str> {
+    let (prefix, message) = record.split_once(';')?;
+    let priority: u8 = prefix.split(',').next()?.parse().ok()?;
+    // Continuation lines of the record follow, indented
+    let message = message.lines().next()?;
+
+    let urgent = priority & 7 <= WARNING_LEVEL;
+    (urgent || PATTERNS.iter().any(|pattern| message.contains(pattern))).then_some(message)
+}
+
+/// Reads new records from `/dev/kmsg` on a background thread, recording the
+/// warnings in `kmsg_info`
+fn follow(kmsg_info: Arc<Mutex<KmsgInfo>>) -> Result<()> {
+    let mut kmsg = File::open(KMSG)?;
+    // Only messages from now on, not those from boot
+    kmsg.seek(SeekFrom::End(0))?;
+
+    thread::spawn(move || {
+        // Each read returns one record, which the kernel limits to 8 KiB
+        let mut buffer = vec![0; 8192];
+        loop {
+            let length = match kmsg.read(&mut buffer) {
+                Ok(0) => return,
+                Ok(length) => length,
+                // Records were overwritten before they could be read
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => continue,
+                Err(e) => {
+                    eprintln!("Could not read {KMSG}: {e}");
+                    return;
+                }
+            };
+
+            let record = String::from_utf8_lossy(&buffer[..length]);
+            if let Some(message) = warning(&record) {
+                let mut kmsg_info = kmsg_info.lock().unwrap();
+                kmsg_info.warnings += 1;
+                kmsg_info.last_message = Some(message.to_string());
+                state::bar().request_refresh();
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// cnx widget that shows a warning glyph once the kernel logs a warning,
+/// until it is acknowledged, and registers the `kernel` IPC command
+pub struct Kmsg {
+    attrs: Attributes,
+    render: Option<KmsgRender>,
+}
+
+impl Kmsg {
+    /// Creates a new [`Kmsg`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<KmsgRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    #[must_use]
+    pub fn new(attrs: Attributes, render: Option<KmsgRender>) -> Kmsg {
+        Kmsg { attrs, render }
+    }
+}
+
+fn default_render(kmsg_info: &KmsgInfo) -> String {
+    let Some(last_message) = &kmsg_info.last_message else {
+        return String::new();
+    };
+
+    let mut message: String = last_message.chars().take(MAX_MESSAGE).collect();
+    if message.len() < last_message.len() {
+        message.push('…');
+    }
+    format!(
+        "<span foreground=\"{}\">{} {}</span>",
+        render::theme().warning.to_hex(),
+        icons::icon_markup(icons::icons().kernel),
+        markup::escape(&message)
+    )
+}
+
+impl Widget for Kmsg {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Kmsg { attrs, render } = *self;
+
+        let kmsg_info = Arc::new(Mutex::new(KmsgInfo::default()));
+        follow(Arc::clone(&kmsg_info))?;
+
+        let acknowledged = Arc::clone(&kmsg_info);
+        ipc::register("kernel", move |args| match args {
+            ["ack"] => {
+                *acknowledged.lock().unwrap() = KmsgInfo::default();
+                state::bar().request_refresh();
+                Ok(String::new())
+            }
+            _ => bail!("usage: kernel ack"),
+        });
+
+        let collect = move || Ok(kmsg_info.lock().unwrap().clone());
+
+        let show = move |kmsg_info: KmsgInfo| match &render {
+            Some(render) => render.render(kmsg_info, &RenderContext::current()),
+            None => default_render(&kmsg_info),
+        };
+
+        // Warnings refresh the bar as they arrive, so this only catches
+        // anything missed
+        let widget =
+            PollingWidget::new(attrs, Duration::from_secs(60), collect, show).with_export("kernel");
+        Box::new(widget).into_stream()
+    }
+}
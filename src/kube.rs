@@ -0,0 +1,178 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use regex::Regex;
+use serde_yaml::Value;
+use tokio_stream::StreamExt;
+
+use crate::render::{Render, RenderContext};
+use crate::{icons, markup, state};
+
+// Abstracted type to represent the render closure
+type KubeRender = Box<dyn Render<KubeInfo>>;
+
+pub struct KubeInfo {
+    pub context: String,
+    pub namespace: String,
+    /// Whether the context matched the widget's production pattern
+    pub production: bool,
+}
+
+/// cnx widget that shows the current kubeconfig context and namespace
+pub struct KubeContext {
+    attrs: Attributes,
+    render: Option<KubeRender>,
+    update_interval: Duration,
+    kubeconfig: PathBuf,
+    production_pattern: Option<Regex>,
+    last_modified: Option<SystemTime>,
+    current: Option<(String, String)>,
+}
+
+impl KubeContext {
+    /// Creates a new [`KubeContext`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<KubeRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the kubeconfig is checked
+    /// for changes
+    ///
+    /// `production_pattern`: [`Option<&str>`] - Regular expression matching
+    /// context names that should be flagged as production
+    pub fn new(
+        attrs: Attributes,
+        render: Option<KubeRender>,
+        update_interval: Duration,
+        production_pattern: Option<&str>,
+    ) -> Result<KubeContext> {
+        let production_pattern = production_pattern.map(Regex::new).transpose()?;
+
+        Ok(KubeContext {
+            attrs,
+            render,
+            update_interval,
+            kubeconfig: default_kubeconfig(),
+            production_pattern,
+            last_modified: None,
+            current: None,
+        })
+    }
+
+    /// Re-reads the kubeconfig, but only if it has been modified since it was
+    /// last read
+    fn refresh(&mut self) {
+        let modified = fs::metadata(&self.kubeconfig)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        if modified.is_some() && modified == self.last_modified {
+            return;
+        }
+
+        self.last_modified = modified;
+        self.current = read_context(&self.kubeconfig).ok();
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        self.refresh();
+
+        let Some((context, namespace)) = self.current.clone() else {
+            return vec![];
+        };
+
+        let production = self
+            .production_pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(&context));
+
+        let kube_info = KubeInfo {
+            context,
+            namespace,
+            production,
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(kube_info, &RenderContext::current())
+        } else if kube_info.production {
+            format!(
+                "<span foreground=\"{}\">{} {}/{}</span>",
+                Color::red().to_hex(),
+                icons::icon_markup(icons::icons().kubernetes),
+                markup::escape(&kube_info.context),
+                markup::escape(&kube_info.namespace)
+            )
+        } else {
+            format!(
+                "{} {}/{}",
+                icons::icon_markup(icons::icons().kubernetes),
+                markup::escape(&kube_info.context),
+                markup::escape(&kube_info.namespace)
+            )
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for KubeContext {
+    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// The kubeconfig `kubectl` would use: the first entry of `KUBECONFIG`, or
+/// `~/.kube/config`
+#[must_use]
+pub fn default_kubeconfig() -> PathBuf {
+    if let Some(path) = std::env::var("KUBECONFIG")
+        .ok()
+        .and_then(|paths| paths.split(':').find(|p| !p.is_empty()).map(PathBuf::from))
+    {
+        return path;
+    }
+
+    let home = std::env::var("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".kube").join("config")
+}
+
+/// Reads the current context and its namespace from a kubeconfig
+fn read_context(path: &Path) -> Result<(String, String)> {
+    let config: Value = serde_yaml::from_reader(File::open(path)?)?;
+
+    let context = config["current-context"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let namespace = config["contexts"]
+        .as_sequence()
+        .and_then(|contexts| {
+            contexts
+                .iter()
+                .find(|entry| entry["name"].as_str() == Some(context.as_str()))
+        })
+        .and_then(|entry| entry["context"]["namespace"].as_str())
+        .unwrap_or("default")
+        .to_string();
+
+    Ok((context, namespace))
+}
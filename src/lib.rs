@@ -1,2 +1,96 @@
+pub mod actions;
+pub mod appearance;
+pub mod attention;
+pub mod audio_output;
 pub mod battery;
+#[cfg(feature = "dbus")]
+pub mod bluetooth;
+pub mod breaks;
+pub mod cache;
+#[cfg(feature = "dbus")]
+pub mod caffeine;
+pub mod check;
+pub mod clipboard;
+pub mod clock;
+pub mod collectors;
+pub mod color;
+pub mod condition;
+pub mod config;
+#[cfg(feature = "http")]
+pub mod connectivity;
+pub mod containers;
+pub mod cpu;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+#[cfg(feature = "sysinfo")]
+pub mod disk;
+pub mod dns;
+pub mod dormant;
+pub mod dry_run;
+pub mod event;
+pub mod ewmh;
+pub mod export;
+pub mod governor;
+pub mod health;
+pub mod heartbeat;
+pub mod hidden;
+#[cfg(feature = "http")]
+pub mod home_assistant;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "http")]
+pub mod http_check;
+pub mod icons;
+pub mod idle;
+pub mod ipc;
+pub mod journal;
+pub mod kmsg;
+pub mod kube;
+#[cfg(feature = "dbus")]
+pub mod lock_countdown;
+pub mod markup;
+pub mod marquee;
+#[cfg(feature = "dbus")]
+pub mod media;
 pub mod memory;
+pub mod net;
+pub mod network;
+pub mod night_light;
+pub mod offline;
+pub mod paths;
+pub mod ping;
+pub mod pipe;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod polling;
+pub mod pool;
+#[cfg(feature = "dbus")]
+pub mod power;
+pub mod power_supply;
+pub mod powerline;
+pub mod presets;
+pub mod privileged;
+pub mod processes;
+pub mod profiles;
+pub mod progress;
+#[cfg(feature = "http")]
+pub mod prometheus;
+pub mod psi;
+pub mod recording;
+pub mod render;
+pub mod sdk;
+pub mod secrets;
+pub mod signals;
+pub mod slot;
+pub mod ssh_agent;
+pub mod state;
+pub mod stopwatch;
+pub mod supervise;
+#[cfg(feature = "dbus")]
+pub mod suspend;
+pub mod taskbar;
+pub mod template;
+pub mod urgent;
+pub mod volume;
+pub mod wifi;
+pub mod workspaces;
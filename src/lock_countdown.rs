@@ -0,0 +1,196 @@
+//! Time until the screen locks for inactivity, so a lock doesn't come as a
+//! surprise while reading.
+//!
+//! The lock timeout is the shortest of the X screensaver timeout, which
+//! xss-lock locks on, the `-time` of a running xautolock, and logind's
+//! `IdleActionUSec` when its `IdleAction` is `lock`. Idle time comes from the
+//! X screensaver extension.
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+use zbus::blocking::{Connection, Proxy};
+
+use crate::idle::Idle;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, render};
+
+/// The default render only shows the countdown once it is this close
+const SHOW_BELOW: Duration = Duration::from_secs(60);
+
+/// How often the lock timeout is read again, as it rarely changes
+const TIMEOUT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// xautolock's `-time` when it isn't given, in minutes
+const XAUTOLOCK_DEFAULT_MINUTES: u64 = 10;
+
+// Abstracted type to represent the render closure
+type LockRender = Box<dyn Render<LockInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LockInfo {
+    /// Seconds until the screen locks, `None` if it never locks for
+    /// inactivity
+    pub remaining: Option<u64>,
+}
+
+impl Fields for LockInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.lock))),
+            "remaining" => Some(Value::Number(self.remaining? as f64)),
+            _ => None,
+        }
+    }
+}
+
+/// `-time` of a running xautolock, found through `/proc`
+fn xautolock_timeout() -> Option<Duration> {
+    for entry in fs::read_dir("/proc").ok()?.flatten() {
+        let Ok(comm) = fs::read_to_string(entry.path().join("comm")) else {
+            continue;
+        };
+        if comm.trim() != "xautolock" {
+            continue;
+        }
+
+        let cmdline = fs::read(entry.path().join("cmdline")).ok()?;
+        let args: Vec<&[u8]> = cmdline.split(|&byte| byte == 0).collect();
+        let minutes = args
+            .iter()
+            .position(|&arg| arg == b"-time")
+            .and_then(|index| args.get(index + 1))
+            .and_then(|minutes| std::str::from_utf8(minutes).ok()?.parse().ok())
+            .unwrap_or(XAUTOLOCK_DEFAULT_MINUTES);
+        return Some(Duration::from_secs(minutes * 60));
+    }
+    None
+}
+
+/// logind's idle timeout, if its idle action is locking the screen
+fn logind_timeout() -> Result<Option<Duration>> {
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+
+    let action: String = manager.get_property("IdleAction")?;
+    if action != "lock" {
+        return Ok(None);
+    }
+    let usec: u64 = manager.get_property("IdleActionUSec")?;
+    Ok(Some(Duration::from_micros(usec)))
+}
+
+/// The shortest idle time after which something locks the screen
+fn lock_timeout(idle: &Idle) -> Option<Duration> {
+    [
+        idle.screensaver_timeout().ok().flatten(),
+        xautolock_timeout(),
+        logind_timeout().ok().flatten(),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+}
+
+/// cnx widget that counts down to the screen locking, hidden unless it is
+/// less than a minute away
+pub struct LockCountdown {
+    attrs: Attributes,
+    render: Option<LockRender>,
+    update_interval: Duration,
+}
+
+impl LockCountdown {
+    /// Creates a new [`LockCountdown`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<LockRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the idle time is read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<LockRender>,
+        update_interval: Duration,
+    ) -> LockCountdown {
+        LockCountdown {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+}
+
+fn default_render(lock_info: &LockInfo) -> String {
+    match lock_info.remaining {
+        Some(remaining) if remaining < SHOW_BELOW.as_secs() => format!(
+            "<span foreground=\"{}\">{} {remaining}s</span>",
+            render::theme().warning.to_hex(),
+            icons::icon_markup(icons::icons().lock)
+        ),
+        _ => String::new(),
+    }
+}
+
+impl Widget for LockCountdown {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let LockCountdown {
+            attrs,
+            render,
+            update_interval,
+        } = *self;
+
+        // Connects on first use, and again after an error
+        let mut idle = None;
+        let mut timeout: Option<(Instant, Option<Duration>)> = None;
+        let collect = move || -> Result<LockInfo> {
+            let connection = match idle.take() {
+                Some(connection) => connection,
+                None => Idle::connect()?,
+            };
+
+            let lock_after = match timeout {
+                Some((read, lock_after)) if read.elapsed() < TIMEOUT_INTERVAL => lock_after,
+                _ => {
+                    let lock_after = lock_timeout(&connection);
+                    timeout = Some((Instant::now(), lock_after));
+                    lock_after
+                }
+            };
+            let idle_time = connection.idle_time()?;
+            idle = Some(connection);
+
+            Ok(LockInfo {
+                remaining: lock_after
+                    .map(|lock_after| lock_after.saturating_sub(idle_time).as_secs()),
+            })
+        };
+
+        let show = move |lock_info: LockInfo| match &render {
+            Some(render) => render.render(lock_info, &RenderContext::current()),
+            None => default_render(&lock_info),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, collect, show).with_export("lock_countdown");
+        Box::new(widget).into_stream()
+    }
+}
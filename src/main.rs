@@ -1,33 +1,66 @@
+use std::path::Path;
+use std::process;
 use std::time::Duration;
 
-use anyhow::Result;
-use byte_unit::{Byte, Unit};
-use cnx::text::{Attributes, Color, Font, Padding, PagerAttributes};
+use anyhow::{bail, Result};
+use byte_unit::Unit;
+use cnx::text::{Attributes, Color, Padding, PagerAttributes};
 use cnx::widgets::ActiveWindowTitle;
 use cnx::{widgets, Cnx, Position};
-use cnx_contrib::widgets::{cpu, volume};
 use status_bar::battery::BatteryInfo;
-use status_bar::{battery, memory};
-
-const DEFAULT_FONT: &str = "monospace";
-
-fn workspace_widget() -> widgets::Pager {
+use status_bar::config::Config;
+use status_bar::cpu::CpuInfo;
+use status_bar::heartbeat::HeartbeatInfo;
+#[cfg(feature = "http")]
+use status_bar::home_assistant::{self, HassEntity};
+#[cfg(feature = "http")]
+use status_bar::http;
+use status_bar::icons;
+use status_bar::memory::MemoryInfo;
+use status_bar::render::{self, Render, RenderContext};
+use status_bar::state::{self, Tracked};
+use status_bar::urgent::{Blink, Urgency};
+use status_bar::volume::VolumeInfo;
+use status_bar::{
+    battery, check, clock, color, cpu, dormant, dry_run, export, heartbeat, ipc, memory, offline,
+    presets, profiles, signals, slot, supervise, volume, workspaces,
+};
+#[cfg(feature = "dbus")]
+use status_bar::{dbus, suspend};
+
+/// Names the bar's widgets are configured under
+const WIDGETS: &[&str] = &[
+    "workspaces",
+    "title",
+    "custom",
+    "battery",
+    "cpu",
+    "memory",
+    "volume",
+    "clock",
+    "heartbeat",
+    "home_assistant",
+];
+
+const BATTERY_PATH: &str = "/sys/class/power_supply/BAT1/";
+
+fn workspace_widget(config: &Config) -> widgets::Pager {
     let focused_workspace_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("workspaces"),
         fg_color: Color::white(),
         bg_color: Some(Color::from_rgb(20, 76, 166)),
         padding: Padding::new(8.0, 8.0, 0.0, 0.0),
     };
 
     let busy_workspace_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("workspaces"),
         fg_color: Color::white(),
         bg_color: Some(Color::from_rgb(100, 100, 100)),
         padding: Padding::new(8.0, 8.0, 0.0, 0.0),
     };
 
     let empty_workspace_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("workspaces"),
         fg_color: Color::from_rgb(100, 100, 100),
         bg_color: None,
         padding: Padding::new(8.0, 8.0, 0.0, 0.0),
@@ -42,9 +75,9 @@ fn workspace_widget() -> widgets::Pager {
     widgets::Pager::new(pager_attrs)
 }
 
-fn window_title_widget() -> ActiveWindowTitle {
+fn window_title_widget(config: &Config) -> ActiveWindowTitle {
     let window_title_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("title"),
         fg_color: Color::white(),
         bg_color: None,
         padding: Padding::new(5.0, 5.0, 0.0, 0.0),
@@ -53,145 +86,442 @@ fn window_title_widget() -> ActiveWindowTitle {
     ActiveWindowTitle::new(window_title_attrs)
 }
 
-fn battery_widget() -> battery::Battery {
+/// How often the widget called `name` updates, as configured or else by
+/// default
+fn interval(config: &Config, name: &str) -> Duration {
+    let default = match name {
+        "battery" => Duration::from_secs(30),
+        "heartbeat" => Duration::from_secs(5),
+        "home_assistant" => Duration::from_secs(60),
+        // Reads between the changes `pactl subscribe` reports
+        "volume" => Duration::from_secs(30),
+        _ => Duration::from_secs(1),
+    };
+    config.interval(name, default)
+}
+
+/// `widget` as [`Tracked`] under `name`, dimmed once it misses too many of
+/// its intervals
+fn tracked(config: &Config, name: &str, widget: impl widgets::Widget + 'static) -> Tracked {
+    Tracked::new(name, widget)
+        .with_interval(interval(config, name))
+        .with_stale_intervals(config.stale_after(name, state::STALE_INTERVALS))
+}
+
+fn battery_widget(config: &Config, urgency: Urgency) -> battery::Battery {
     let battery_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("battery"),
         fg_color: Color::white(),
         bg_color: None,
         padding: Padding::new(5.0, 5.0, 0.0, 0.0),
     };
 
-    let render = Box::new(|battery_info: BatteryInfo| {
+    let icon_font = config.icon_font("battery");
+    let template = config.template("battery");
+    let preset = config.preset("battery").map(presets::battery);
+    let render = Box::new(move |battery_info: BatteryInfo, context: &RenderContext| {
         let charge = battery_info.capacity;
-        let colour = match charge {
-            50.. => Color::green(),
-            20..50 => Color::yellow(),
-            _ => Color::red(),
-        }
-        .to_hex();
+        let muted = context.theme.muted.to_hex();
+        let colour = color::gradient(100.0 - charge as f64).to_hex();
 
-        let emoji = match battery_info.status {
-            battery::ChargeStatus::Charging => "🔌",
-            _ => "🔋",
+        let icon = match battery_info.status {
+            battery::ChargeStatus::Charging => context.icons.charging,
+            _ => context.icons.battery,
+        };
+        urgency.set(charge < 10 && !matches!(battery_info.status, battery::ChargeStatus::Charging));
+        if let Some(template) = &template {
+            return template.render(battery_info, context);
+        }
+        if let Some(preset) = &preset {
+            return preset.render(battery_info, context);
+        }
+        let icon = icons::with_font(icon, icon_font.as_deref());
+        let conservation = if battery_info.conserving() {
+            icons::with_font(context.icons.conservation, icon_font.as_deref())
+        } else {
+            String::new()
+        };
+        // A weak charger may not keep up with the laptop under load
+        let charger = match &battery_info.charger {
+            Some(charger) if matches!(battery_info.status, battery::ChargeStatus::Charging) => {
+                format!("<span foreground=\"{muted}\">@{:.0}W</span>", charger.watts)
+            }
+            _ => String::new(),
         };
 
         format!(
-            "<span foreground=\"#808080\">[</span>{emoji}<span foreground=\"{colour}\">{charge}%</span><span foreground=\"#808080\">]</span>"
+            "<span foreground=\"{muted}\">[</span>{icon}<span foreground=\"{colour}\">{charge}%</span>{charger}{conservation}<span foreground=\"{muted}\">]</span>"
         )
     });
 
-    battery::Battery::new(
+    let mut battery = battery::Battery::new(
         battery_attrs,
         Some(render),
-        Duration::from_secs(30),
-        "/sys/class/power_supply/BAT1/".to_string(),
+        interval(config, "battery"),
+        BATTERY_PATH.to_string(),
     )
+    .with_conservation(80);
+    for action in config.actions("battery") {
+        match action.percent {
+            Some(percent) => battery = battery.with_action(percent, action.run.clone()),
+            None => eprintln!("Ignoring battery action {:?} without a percent", action.run),
+        }
+    }
+    battery
 }
 
-fn cpu_widget() -> Result<cpu::Cpu> {
+fn cpu_widget(config: &Config) -> cpu::Cpu {
     let cpu_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("cpu"),
         fg_color: Color::white(),
         bg_color: None,
         padding: Padding::new(5.0, 5.0, 0.0, 0.0),
     };
 
-    let render = Box::new(|load| {
-        let mut color = Color::yellow().to_hex();
-        if load < 5 {
-            color = Color::green().to_hex();
+    let icon_font = config.icon_font("cpu");
+    let template = config.template("cpu");
+    let preset = config.preset("cpu").map(presets::cpu);
+    let render = Box::new(move |cpu_info: CpuInfo, context: &RenderContext| {
+        if let Some(template) = &template {
+            return template.render(cpu_info, context);
         }
-        if load > 50 {
-            color = Color::red().to_hex();
+        if let Some(preset) = &preset {
+            return preset.render(cpu_info, context);
         }
+        let load = cpu_info.usage.round();
+        let muted = context.theme.muted.to_hex();
+        let colour = color::gradient(load).to_hex();
+        let icon = icons::with_font(context.icons.cpu, icon_font.as_deref());
         format!(
-            "<span foreground=\"#808080\">[</span>⚡<span foreground=\"{color}\">{load}%</span><span foreground=\"#808080\">]</span>"
+            "<span foreground=\"{muted}\">[</span>{icon}<span foreground=\"{colour}\">{load}%</span><span foreground=\"{muted}\">]</span>"
         )
     });
 
-    cpu::Cpu::new(cpu_attrs, Some(render))
+    cpu::Cpu::new(cpu_attrs, Some(render), interval(config, "cpu"))
 }
 
-fn memory_usage_widget() -> memory::MemoryUsage {
+fn memory_usage_widget(config: &Config) -> memory::MemoryUsage {
     let memory_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("memory"),
         fg_color: Color::white(),
         bg_color: None,
         padding: Padding::new(5.0, 5.0, 0.0, 0.0),
     };
 
-    let render = Box::new(
-        |(used_memory, total_memory): (Byte, Byte), (used_swap, total_swap): (Byte, Byte)| {
-            let mut mem_colour = Color::white().to_hex();
+    let icon_font = config.icon_font("memory");
+    let template = config.template("memory");
+    let preset = config.preset("memory").map(presets::memory);
+    let render = Box::new(move |memory_info: MemoryInfo, context: &RenderContext| {
+        if let Some(template) = &template {
+            return template.render(memory_info, context);
+        }
+        if let Some(preset) = &preset {
+            return preset.render(memory_info, context);
+        }
+        let MemoryInfo {
+            used_memory,
+            total_memory,
+            used_swap,
+            total_swap,
+            ..
+        } = memory_info;
+
+        let mem_colour = color::gradient(color::percentage(
+            used_memory.as_u64(),
+            total_memory.as_u64(),
+        ))
+        .to_hex();
+        let swap_colour =
+            color::gradient(color::percentage(used_swap.as_u64(), total_swap.as_u64())).to_hex();
 
-            if used_memory.as_u64() >= total_memory.as_u64() / 2 {
-                mem_colour = Color::yellow().to_hex();
-            }
-            if used_memory.as_u64() >= total_memory.as_u64() / 5 * 4 {
-                mem_colour = Color::red().to_hex();
-            }
+        let used_mem = used_memory.get_adjusted_unit(Unit::GB).get_value();
+        let total_mem = total_memory.get_adjusted_unit(Unit::GB);
+        let used_swap = used_swap.get_adjusted_unit(Unit::GB).get_value();
+        let total_swap = total_swap.get_adjusted_unit(Unit::GB);
 
-            let mut swap_colour = Color::white().to_hex();
+        let muted = context.theme.muted.to_hex();
+        let mem_icon = icons::with_font(context.icons.memory, icon_font.as_deref());
+        let swap_icon = icons::with_font(context.icons.swap, icon_font.as_deref());
 
-            if used_swap.as_u64() >= total_swap.as_u64() / 2 {
-                swap_colour = Color::yellow().to_hex();
-            }
-            if used_swap.as_u64() >= total_swap.as_u64() / 5 * 4 {
-                swap_colour = Color::red().to_hex();
+        format!("<span foreground=\"{muted}\">[</span>{mem_icon} <span foreground=\"{mem_colour}\">{used_mem:.1}</span>/{total_mem:.1}<span foreground=\"{muted}\">]</span> <span foreground=\"{muted}\">[</span>{swap_icon} <span foreground=\"{swap_colour}\">{used_swap:.1}</span>/{total_swap:.1}<span foreground=\"{muted}\">]</span>")
+    });
+
+    let mut memory = memory::MemoryUsage::new(memory_attrs, Some(render))
+        .with_update_interval(interval(config, "memory"));
+    for action in config.actions("memory") {
+        match action.available {
+            Some(available) => {
+                let ticks = action.ticks.unwrap_or(3);
+                memory = memory.with_alert(available, ticks, action.run.clone());
             }
+            None => eprintln!("Ignoring memory action {:?} without available", action.run),
+        }
+    }
+    memory
+}
 
-            let used_mem = used_memory.get_adjusted_unit(Unit::GB).get_value();
-            let total_mem = total_memory.get_adjusted_unit(Unit::GB);
-            let used_swap = used_swap.get_adjusted_unit(Unit::GB).get_value();
-            let total_swap = total_swap.get_adjusted_unit(Unit::GB);
+fn volume_widget(config: &Config) -> volume::Volume {
+    let volume_attrs = Attributes {
+        font: config.font("volume"),
+        fg_color: Color::white(),
+        bg_color: None,
+        padding: Padding::new(5.0, 5.0, 0.0, 0.0),
+    };
 
-            format!("<span foreground=\"#808080\">[</span>🧠 <span foreground=\"{mem_colour}\">{used_mem:.1}</span>/{total_mem:.1}<span foreground=\"#808080\">]</span> <span foreground=\"#808080\">[</span>💾 <span foreground=\"{swap_colour}\">{used_swap:.1}</span>/{total_swap:.1}<span foreground=\"#808080\">]</span>")
-        },
-    );
+    let render = config
+        .template("volume")
+        .map(|template| Box::new(template) as Box<dyn Render<VolumeInfo>>);
 
-    memory::MemoryUsage::new(memory_attrs, Some(render))
+    volume::Volume::new(volume_attrs, render, interval(config, "volume"))
 }
 
-fn volume_widget() -> volume::Volume {
-    let volume_attrs = Attributes {
-        font: Font::new(DEFAULT_FONT),
+fn custom_slot_widget(config: &Config) -> slot::Slot {
+    let slot_attrs = Attributes {
+        font: config.font("custom"),
         fg_color: Color::white(),
         bg_color: None,
         padding: Padding::new(5.0, 5.0, 0.0, 0.0),
     };
 
-    volume::Volume::new(volume_attrs)
+    slot::Slot::new(slot_attrs, "custom")
 }
 
-fn clock_widget() -> widgets::Clock {
+fn clock_widget(config: &Config, urgency: Urgency) -> clock::Clock {
     let clock_attributes = Attributes {
-        font: Font::new(DEFAULT_FONT),
+        font: config.font("clock"),
         fg_color: Color::white(),
         bg_color: None,
         padding: Padding::new(5.0, 5.0, 0.0, 0.0),
     };
 
-    widgets::Clock::new(
-        clock_attributes.clone(),
-        Some("%H:%M %a %d-%m-%Y".to_string()),
+    let mut clock = clock::Clock::new(
+        clock_attributes,
+        config.format("clock", "%H:%M %a %d-%m-%Y"),
     )
+    .with_urgency(urgency);
+    for alarm in config.alarms("clock") {
+        clock = clock.with_alarm(alarm.time, &alarm.message);
+    }
+    clock
 }
 
-fn main() -> Result<()> {
-    let mut bar = Cnx::new(Position::Top);
+fn heartbeat_widget(config: &Config) -> heartbeat::Heartbeat {
+    let heartbeat_attrs = Attributes {
+        font: config.font("heartbeat"),
+        fg_color: Color::white(),
+        bg_color: None,
+        padding: Padding::new(5.0, 5.0, 0.0, 0.0),
+    };
 
-    bar.add_widget(workspace_widget());
-    bar.add_widget(window_title_widget());
-    bar.add_widget(battery_widget());
+    let render = config
+        .template("heartbeat")
+        .map(|template| Box::new(template) as Box<dyn Render<HeartbeatInfo>>);
 
-    if let Ok(cpu_w) = cpu_widget() {
-        bar.add_widget(cpu_w)
+    heartbeat::Heartbeat::new(heartbeat_attrs, render, interval(config, "heartbeat"))
+}
+
+/// The Home Assistant widget, if its server and token are configured
+#[cfg(feature = "http")]
+fn home_assistant_widget(config: &Config) -> Option<home_assistant::HomeAssistant> {
+    let server = config.server("home_assistant")?;
+    let token = config.token("home_assistant")?;
+
+    let home_assistant_attrs = Attributes {
+        font: config.font("home_assistant"),
+        fg_color: Color::white(),
+        bg_color: None,
+        padding: Padding::new(5.0, 5.0, 0.0, 0.0),
     };
 
-    bar.add_widget(memory_usage_widget());
-    bar.add_widget(volume_widget());
-    bar.add_widget(clock_widget());
+    let entities = config
+        .entities("home_assistant")
+        .iter()
+        .map(|entity| {
+            let hass_entity = HassEntity::new(&entity.entity_id, &entity.icon);
+            match &entity.template {
+                Some(template) => hass_entity.with_template(template),
+                None => hass_entity,
+            }
+        })
+        .collect();
+
+    Some(home_assistant::HomeAssistant::new(
+        home_assistant_attrs,
+        None,
+        interval(config, "home_assistant"),
+        server.to_string(),
+        token,
+        entities,
+    ))
+}
 
-    bar.run()?;
+/// Builds the widget called `name` as it appears on the bar, for
+/// [`dry_run`]. Widgets which need the X server aren't available
+fn named_widget(config: &Config, name: &str) -> Option<Box<dyn widgets::Widget>> {
+    let widget: Box<dyn widgets::Widget> = match name {
+        "battery" => Box::new(battery_widget(config, Urgency::new())),
+        "cpu" => Box::new(cpu_widget(config)),
+        "memory" => Box::new(memory_usage_widget(config)),
+        "volume" => Box::new(volume_widget(config)),
+        "custom" => Box::new(custom_slot_widget(config)),
+        "clock" => Box::new(clock_widget(config, Urgency::new())),
+        "heartbeat" => Box::new(heartbeat_widget(config)),
+        #[cfg(feature = "http")]
+        "home_assistant" => Box::new(home_assistant_widget(config)?),
+        _ => return None,
+    };
+    Some(widget)
+}
+
+fn main() -> Result<()> {
+    if std::env::args().any(|arg| arg == supervise::FLAG) {
+        return supervise::run();
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some(check::COMMAND) {
+        let paths = [("battery", Path::new(BATTERY_PATH))];
+        if !check::run(WIDGETS, &paths)? {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.first().map(String::as_str) == Some(profiles::COMMAND) {
+        return profiles::run(&args[1..]);
+    }
+
+    if args.first().map(String::as_str) == Some("init") {
+        let path = Config::init()?;
+        println!("Wrote {}", path.display());
+        return Ok(());
+    }
+
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e:#}, using the default config");
+        Config::default()
+    });
+    if let Some(profile) = profiles::active(&config) {
+        if let Err(e) = config.apply_profile(&profile) {
+            eprintln!("{e}, using no profile");
+        }
+    }
+
+    icons::set_icon_set(config.icon_set);
+    icons::set_icon_font(config.font.icon_family.clone());
+    render::set_theme(config.theme());
+    #[cfg(feature = "http")]
+    if let Err(e) = http::configure(&config.http) {
+        eprintln!("{e:#}, using the default HTTP settings");
+    }
+
+    if args.first().map(String::as_str) == Some(dry_run::COMMAND) {
+        let Some(name) = args.get(1) else {
+            bail!("usage: status_bar {} <widget>", dry_run::COMMAND);
+        };
+        let Some(widget) = named_widget(&config, name) else {
+            bail!("No widget called {name}");
+        };
+        return dry_run::run(name, widget);
+    }
+
+    let mut bar = Cnx::new(Position::Top);
+
+    // Exits rather than stacking a second bar on top of the running one
+    let replace = std::env::args().any(|arg| arg == "--replace");
+    ipc::serve(replace)?;
+    workspaces::register_commands();
+    profiles::register_command(&config);
+
+    if let Err(e) = signals::listen() {
+        eprintln!("Could not install signal handlers: {e}");
+    }
+
+    #[cfg(feature = "dbus")]
+    if let Err(e) = dbus::serve() {
+        eprintln!("Could not start D-Bus service: {e}");
+    }
+
+    offline::watch(config.offline_requires_route);
+    if !config.keep_polling {
+        dormant::watch();
+    }
+
+    #[cfg(feature = "dbus")]
+    if let Err(e) = suspend::watch_resume() {
+        eprintln!("Could not watch for resume from suspend: {e}");
+    }
+
+    if let Err(e) = config.follow_appearance() {
+        eprintln!("Could not switch themes automatically: {e}");
+    }
+
+    if let Some(address) = &config.export.address {
+        if let Err(e) = export::serve(address) {
+            eprintln!("Could not serve widget data on {address}: {e}");
+        }
+    }
+
+    if config.shown("workspaces") {
+        bar.add_widget(workspace_widget(&config));
+    }
+    if config.shown("title") {
+        bar.add_widget(window_title_widget(&config));
+    }
+    if config.shown("custom") {
+        bar.add_widget(custom_slot_widget(&config));
+    }
+    if config.shown("battery") {
+        let battery_urgency = Urgency::new();
+        let battery = battery_widget(&config, battery_urgency.clone());
+        bar.add_widget(tracked(
+            &config,
+            "battery",
+            Blink::new(
+                battery,
+                battery_urgency,
+                Duration::from_millis(500),
+                Color::red(),
+            ),
+        ));
+    }
+
+    if config.shown("cpu") {
+        bar.add_widget(tracked(&config, "cpu", cpu_widget(&config)));
+    }
+
+    if config.shown("memory") {
+        bar.add_widget(tracked(&config, "memory", memory_usage_widget(&config)));
+    }
+    if config.shown("volume") {
+        bar.add_widget(tracked(&config, "volume", volume_widget(&config)));
+    }
+    #[cfg(feature = "http")]
+    if config.shown("home_assistant") {
+        if let Some(home_assistant) = home_assistant_widget(&config) {
+            bar.add_widget(tracked(&config, "home_assistant", home_assistant));
+        }
+    }
+    if config.shown("clock") {
+        let clock_urgency = Urgency::new();
+        let clock = clock_widget(&config, clock_urgency.clone());
+        bar.add_widget(Tracked::new(
+            "clock",
+            Blink::new(
+                clock,
+                clock_urgency,
+                Duration::from_millis(500),
+                Color::red(),
+            ),
+        ));
+    }
+
+    if config.shown("heartbeat") {
+        bar.add_widget(tracked(&config, "heartbeat", heartbeat_widget(&config)));
+    }
+
+    let result = bar.run();
+    state::bar().shutdown();
+    result?;
     Ok(())
 }
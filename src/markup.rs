@@ -0,0 +1,47 @@
+//! Helpers for Pango markup.
+
+/// Escapes `text` so that it is shown literally inside markup, e.g. a song
+/// title containing `&`
+#[must_use]
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    escape_into(&mut escaped, text);
+    escaped
+}
+
+/// Appends `text` to `output`, escaped as by [`escape`]
+pub fn escape_into(output: &mut String, text: &str) {
+    let mut rest = text;
+    while let Some(index) = rest.find(['&', '<', '>']) {
+        output.push_str(&rest[..index]);
+        output.push_str(match rest.as_bytes()[index] {
+            b'&' => "&amp;",
+            b'<' => "&lt;",
+            _ => "&gt;",
+        });
+        rest = &rest[index + 1..];
+    }
+    output.push_str(rest);
+}
+
+/// Removes the tags from `markup` and decodes its entities, leaving the text
+/// as it would be shown
+#[must_use]
+pub fn strip(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,194 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::Text;
+use cnx::widgets::{Widget, WidgetStream};
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::StreamExt;
+
+use crate::markup;
+
+/// Padding inserted between the end of the text and its start as it wraps
+/// around
+const GAP: &str = "   ";
+
+enum Event {
+    Update(Result<Vec<Text>>),
+    Scroll,
+}
+
+/// Wraps a widget so that text longer than a maximum width scrolls
+/// horizontally instead of overflowing. Text using Pango markup can't be cut
+/// safely, so it scrolls as plain text, without its styling, while too long
+pub struct Marquee {
+    inner: Box<dyn Widget>,
+    max_width: usize,
+    step: usize,
+    rate: Duration,
+}
+
+impl Marquee {
+    /// Creates a new [`Marquee`] widget
+    ///
+    /// Arguments
+    ///
+    /// `widget`: [`Widget`] - The widget whose text should scroll
+    ///
+    /// `max_width`: [`usize`] - Maximum number of characters to show
+    ///
+    /// `step`: [`usize`] - Number of characters to advance each tick
+    ///
+    /// `rate`: [`Duration`] - Time between each scroll step
+    pub fn new(
+        widget: impl Widget + 'static,
+        max_width: usize,
+        step: usize,
+        rate: Duration,
+    ) -> Marquee {
+        Marquee {
+            inner: Box::new(widget),
+            max_width,
+            step,
+            rate,
+        }
+    }
+}
+
+impl Widget for Marquee {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Marquee {
+            inner,
+            max_width,
+            step,
+            rate,
+        } = *self;
+
+        let updates = inner.into_stream()?.map(Event::Update);
+        let scrolls = IntervalStream::new(time::interval(rate)).map(|_| Event::Scroll);
+
+        let mut latest: Vec<Text> = vec![];
+        let mut offset = 0;
+
+        let stream = updates.merge(scrolls).filter_map(move |event| {
+            let overlong = |texts: &[Text]| {
+                texts
+                    .iter()
+                    .any(|text| scrolled(text, 0, max_width).is_some())
+            };
+
+            match event {
+                Event::Update(Ok(texts)) => {
+                    let unchanged = texts.len() == latest.len()
+                        && texts.iter().zip(&latest).all(|(a, b)| a.text == b.text);
+                    if !unchanged {
+                        offset = 0;
+                    }
+                    latest = texts;
+                }
+                Event::Update(Err(e)) => return Some(Err(e)),
+                Event::Scroll if overlong(&latest) => offset += step,
+                Event::Scroll => return None,
+            }
+
+            let texts = latest
+                .iter()
+                .cloned()
+                .map(|mut text| {
+                    if let Some(scrolled) = scrolled(&text, offset, max_width) {
+                        text.text = scrolled;
+                    }
+                    text
+                })
+                .collect();
+
+            Some(Ok(texts))
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// What `text` shows once scrolled to `offset`, `None` if it fits in `width`
+/// characters. Markup is stripped, and the window escaped again
+fn scrolled(text: &Text, offset: usize, width: usize) -> Option<String> {
+    let visible = if text.markup {
+        Cow::Owned(markup::strip(&text.text))
+    } else {
+        Cow::Borrowed(text.text.as_str())
+    };
+    if visible.chars().count() <= width {
+        return None;
+    }
+
+    let window = window(&visible, offset, width);
+    Some(if text.markup {
+        markup::escape(&window)
+    } else {
+        window
+    })
+}
+
+/// `width` characters of `text` starting at `offset`, wrapping around to the
+/// start
+fn window(text: &str, offset: usize, width: usize) -> String {
+    let chars: Vec<char> = text.chars().chain(GAP.chars()).collect();
+
+    chars
+        .iter()
+        .cycle()
+        .skip(offset % chars.len())
+        .take(width)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use cnx::text::{Attributes, Color, Font, Padding};
+
+    use super::*;
+
+    /// Widget drawing `texts` once
+    struct Fixed(Vec<Text>);
+
+    impl Widget for Fixed {
+        fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+            let once = tokio_stream::iter([Ok(self.0)]);
+            Ok(Box::pin(once.chain(tokio_stream::pending())))
+        }
+    }
+
+    fn text(text: &str, markup: bool) -> Text {
+        Text {
+            attr: Attributes {
+                font: Font::new("monospace 10"),
+                fg_color: Color::white(),
+                bg_color: None,
+                padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+            },
+            text: text.to_string(),
+            stretch: false,
+            markup,
+        }
+    }
+
+    #[test]
+    fn short_markup_is_untouched() {
+        assert_eq!(scrolled(&text("<b>Tom</b>", true), 0, 5), None);
+        assert_eq!(scrolled(&text("Tom", false), 0, 5), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn markup_scrolls_escaped() {
+        let widget = Fixed(vec![text("<b>Tom &amp; Jerry</b>", true)]);
+        let mut stream = Box::new(Marquee::new(widget, 5, 1, Duration::from_secs(1)))
+            .into_stream()
+            .unwrap();
+
+        for expected in ["Tom &amp;", "om &amp; J", "m &amp; Je"] {
+            let texts = stream.next().await.unwrap().unwrap();
+            assert_eq!(texts[0].text, expected);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Now playing information from MPRIS media players on the session bus.
+//!
+//! cnx doesn't deliver pointer events to widgets, so the player is controlled
+//! through the `media` IPC command, e.g. from key bindings:
+//! `media previous`, `media play-pause`, `media next` and `media seek <secs>`
+//! with a negative number of seconds to seek backwards.
+//!
+//! Players are read again when one of them emits `PropertiesChanged` or a
+//! player starts or exits, rather than on an interval.
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::{Attributes, Color};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio::sync::mpsc as tokio_mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::dbus;
+use crate::event::EventWidget;
+use crate::render::{Render, RenderContext};
+use crate::{ipc, markup, state};
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Players change several properties at once on a new track
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Abstracted type to represent the render closure
+type MediaRender = Box<dyn Render<MediaInfo>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+pub struct MediaInfo {
+    /// Bus name suffix of the player, e.g. `spotify`
+    pub player: String,
+    pub status: PlaybackStatus,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+}
+
+/// cnx widget that shows the track of the active MPRIS player, preferring
+/// one which is playing. Hidden while no player is running
+pub struct Media {
+    attrs: Attributes,
+    render: Option<MediaRender>,
+}
+
+impl Media {
+    /// Creates a new [`Media`] widget and registers the `media` IPC command
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<MediaRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    #[must_use]
+    pub fn new(attrs: Attributes, render: Option<MediaRender>) -> Media {
+        ipc::register("media", |args| {
+            let connection = Connection::session()?;
+            let (name, _) =
+                active_player(&connection)?.ok_or_else(|| anyhow!("no media player is running"))?;
+            let player = player_proxy(&connection, &name)?;
+
+            match args {
+                ["previous"] => player.call_method("Previous", &())?,
+                ["play-pause"] => player.call_method("PlayPause", &())?,
+                ["next"] => player.call_method("Next", &())?,
+                ["seek", seconds] => {
+                    let seconds: f64 = seconds.parse()?;
+                    // MPRIS offsets are in microseconds
+                    player.call_method("Seek", &((seconds * 1_000_000.0) as i64))?
+                }
+                _ => bail!("usage: media previous|play-pause|next|seek <seconds>"),
+            };
+
+            state::bar().request_refresh();
+            Ok(String::new())
+        });
+
+        Media { attrs, render }
+    }
+}
+
+fn default_render(media_info: &MediaInfo) -> String {
+    let symbol = match media_info.status {
+        PlaybackStatus::Playing => "▶",
+        PlaybackStatus::Paused => "⏸",
+        PlaybackStatus::Stopped => "⏹",
+    };
+    let track = match (media_info.artists.is_empty(), &media_info.title) {
+        (false, Some(title)) => format!("{} - {title}", media_info.artists.join(", ")),
+        (true, Some(title)) => title.clone(),
+        (_, None) => media_info.player.clone(),
+    };
+
+    format!(
+        "<span foreground=\"{}\">{symbol}</span> {}",
+        Color::from_rgb(128, 128, 128).to_hex(),
+        markup::escape(&track)
+    )
+}
+
+impl Widget for Media {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let connection = Connection::session()?;
+
+        // Property changes and players coming and going all end up here
+        let (changes, changed) = mpsc::channel();
+        dbus::send_property_changes(&connection, MPRIS_PATH, changes.clone())?;
+        let owners = DBusProxy::new(&connection)?.receive_name_owner_changed()?;
+        changes.send(())?;
+        thread::spawn(move || {
+            for signal in owners {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                if args.name().as_str().starts_with(MPRIS_PREFIX) && changes.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (sender, receiver) = tokio_mpsc::unbounded_channel();
+        thread::spawn(move || {
+            while changed.recv().is_ok() {
+                // A burst of changes only needs one read
+                while changed.try_recv().is_ok() {}
+                if sender.send(media_info(&connection).ok().flatten()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let render = self.render;
+        let widget = EventWidget::new(
+            self.attrs,
+            UnboundedReceiverStream::new(receiver),
+            move |media_info: Option<MediaInfo>| match (media_info, &render) {
+                (None, _) => String::new(),
+                (Some(media_info), Some(render)) => {
+                    render.render(media_info, &RenderContext::current())
+                }
+                (Some(media_info), None) => default_render(&media_info),
+            },
+        )
+        .with_debounce(DEBOUNCE);
+
+        Box::new(widget).into_stream()
+    }
+}
+
+/// The track of the player to show, `None` while no player is running
+fn media_info(connection: &Connection) -> Result<Option<MediaInfo>> {
+    let Some((name, status)) = active_player(connection)? else {
+        return Ok(None);
+    };
+    let metadata: HashMap<String, OwnedValue> =
+        player_proxy(connection, &name)?.get_property("Metadata")?;
+
+    let title = metadata.get("xesam:title").and_then(|title| string(title));
+    let artists = match metadata.get("xesam:artist").map(|artists| &**artists) {
+        Some(Value::Array(artists)) => artists.iter().filter_map(string).collect(),
+        _ => vec![],
+    };
+
+    Ok(Some(MediaInfo {
+        player: name.trim_start_matches(MPRIS_PREFIX).to_string(),
+        status,
+        title,
+        artists,
+    }))
+}
+
+fn player_proxy<'a>(connection: &Connection, name: &'a str) -> Result<Proxy<'a>> {
+    Ok(Proxy::new(connection, name, MPRIS_PATH, PLAYER_INTERFACE)?)
+}
+
+/// The bus name and status of the player to show, preferring a playing one
+/// over a paused one over a stopped one
+fn active_player(connection: &Connection) -> Result<Option<(String, PlaybackStatus)>> {
+    let mut players = vec![];
+
+    for name in DBusProxy::new(connection)?.list_names()? {
+        let name = name.as_str();
+        if !name.starts_with(MPRIS_PREFIX) {
+            continue;
+        }
+
+        let status = match player_proxy(connection, name)?
+            .get_property::<String>("PlaybackStatus")
+            .as_deref()
+        {
+            Ok("Playing") => PlaybackStatus::Playing,
+            Ok("Paused") => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        };
+        players.push((name.to_string(), status));
+    }
+
+    Ok(players.into_iter().min_by_key(|(_, status)| *status as u8))
+}
+
+fn string(value: &Value) -> Option<String> {
+    match value {
+        Value::Str(string) => Some(string.to_string()),
+        _ => None,
+    }
+}
@@ -1,22 +1,94 @@
 use anyhow::Result;
 use byte_unit::{Byte, UnitType};
-use cnx::text::{Attributes, Text};
+use cnx::text::Attributes;
 use cnx::widgets::{Widget, WidgetStream};
 use std::time::Duration;
-use sysinfo::{MemoryRefreshKind, System};
-use tokio::time;
-use tokio_stream::wrappers::IntervalStream;
-use tokio_stream::StreamExt;
+
+use crate::actions::Action;
+use crate::collectors::memory::MemoryCollector;
+pub use crate::collectors::memory::MemoryInfo;
+use crate::collectors::Collector;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{color, icons};
+
+/// `some avg10` memory pressure above which the default render warns
+const PRESSURE_WARNING: f64 = 10.0;
 
 // Abstracted type to represent the render closure
-type MemoryRender = Box<dyn Fn((Byte, Byte), (Byte, Byte)) -> String>;
+type MemoryRender = Box<dyn Render<MemoryInfo>>;
+
+impl Fields for MemoryInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        let size = |bytes: Byte| {
+            Value::Text(format!(
+                "{:.1}",
+                bytes.get_appropriate_unit(UnitType::Binary)
+            ))
+        };
+
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.memory))),
+            "swap_icon" => Some(Value::Markup(context.icon(context.icons.swap))),
+            "used" => Some(size(self.used_memory)),
+            "total" => Some(size(self.total_memory)),
+            "percentage" => Some(Value::Number(color::percentage(
+                self.used_memory.as_u64(),
+                self.total_memory.as_u64(),
+            ))),
+            "used_swap" => Some(size(self.used_swap)),
+            "total_swap" => Some(size(self.total_swap)),
+            "swap_percentage" => Some(Value::Number(color::percentage(
+                self.used_swap.as_u64(),
+                self.total_swap.as_u64(),
+            ))),
+            "pressure" => self
+                .pressure
+                .as_ref()
+                .map(|pressure| Value::Number(pressure.some_avg10)),
+            _ => None,
+        }
+    }
+}
 
 /// cnx widget that shows current system memory usage
 pub struct MemoryUsage {
     attrs: Attributes,
     render: Option<MemoryRender>,
-    memory_handle: System,
     update_interval: Duration,
+    alerts: Vec<Alert>,
+}
+
+/// An [`Action`] run once available memory has stayed below `threshold` for
+/// `ticks` readings in a row
+struct Alert {
+    threshold: Byte,
+    ticks: u32,
+    action: Action,
+    /// Consecutive readings below `threshold` so far
+    low: u32,
+}
+
+impl Alert {
+    fn check(&mut self, memory_info: &MemoryInfo) {
+        let available = memory_info
+            .total_memory
+            .as_u64()
+            .saturating_sub(memory_info.used_memory.as_u64());
+
+        if available >= self.threshold.as_u64() {
+            self.low = 0;
+            return;
+        }
+
+        // Runs on the reading that reaches `ticks` and not again until
+        // memory has recovered
+        self.low = self.low.saturating_add(1);
+        if self.low == self.ticks.max(1) {
+            self.action.spawn();
+        }
+    }
 }
 
 impl MemoryUsage {
@@ -29,52 +101,90 @@ impl MemoryUsage {
     ///
     /// `render`: [`Option<MemoryRender>`] - Optional
     /// parameter to customise the way the widget is rendered. Takes a
-    /// closure that returns a String
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
     #[must_use]
     pub fn new(attrs: Attributes, render: Option<MemoryRender>) -> MemoryUsage {
-        let memory_handle = System::new();
         MemoryUsage {
             attrs,
             render,
-            memory_handle,
             update_interval: Duration::new(1, 0),
+            alerts: Vec::new(),
         }
     }
 
-    fn tick(&mut self) -> Vec<Text> {
-        self.memory_handle
-            .refresh_memory_specifics(MemoryRefreshKind::everything());
-        let used_bytes = Byte::from_u64(self.memory_handle.free_memory());
-        let total_bytes = Byte::from_u64(self.memory_handle.total_memory());
-        let used_swap = Byte::from_u64(self.memory_handle.used_swap());
-        let total_swap = Byte::from_u64(self.memory_handle.total_swap());
-
-        let text = if let Some(render_f) = &self.render {
-            render_f.as_ref()((used_bytes, total_bytes), (used_swap, total_swap))
-        } else {
-            format!(
-                "({used_mem}/{total_mem}) ({used_swap}/{total_swap})",
-                used_mem = used_bytes.get_appropriate_unit(UnitType::Binary),
-                total_mem = total_bytes.get_appropriate_unit(UnitType::Binary),
-                used_swap = used_swap.get_appropriate_unit(UnitType::Binary),
-                total_swap = total_swap.get_appropriate_unit(UnitType::Binary),
-            )
-        };
+    /// Sets how often memory usage is read, once a second by default
+    #[must_use]
+    pub fn with_update_interval(mut self, update_interval: Duration) -> Self {
+        self.update_interval = update_interval;
+        self
+    }
 
-        vec![Text {
-            attr: self.attrs.clone(),
-            text,
-            stretch: false,
-            markup: self.render.is_some(),
-        }]
+    /// Runs `action` once available memory has stayed below `threshold` for
+    /// `ticks` updates in a row, e.g. to warn about a leak before the OOM
+    /// killer steps in. It runs again only after memory has recovered
+    #[must_use]
+    pub fn with_alert(mut self, threshold: Byte, ticks: u32, action: Action) -> Self {
+        self.alerts.push(Alert {
+            threshold,
+            ticks,
+            action,
+            low: 0,
+        });
+        self
     }
 }
 
+fn default_render(memory_info: &MemoryInfo) -> String {
+    let pressure = match memory_info.pressure {
+        Some(pressure) if pressure.some_avg10 >= PRESSURE_WARNING => format!(
+            " <span foreground=\"{}\">PSI {:.0}%</span>",
+            color::gradient(pressure.some_avg10 * 2.0).to_hex(),
+            pressure.some_avg10
+        ),
+        _ => String::new(),
+    };
+
+    format!(
+        "{mem_icon} <span foreground=\"{mem_colour}\">{used_mem}</span>/{total_mem} {swap_icon} <span foreground=\"{swap_colour}\">{used_swap}</span>/{total_swap}{pressure}",
+        mem_icon = icons::icon_markup(icons::icons().memory),
+        swap_icon = icons::icon_markup(icons::icons().swap),
+        mem_colour = color::gradient(color::percentage(memory_info.used_memory.as_u64(), memory_info.total_memory.as_u64())).to_hex(),
+        swap_colour = color::gradient(color::percentage(memory_info.used_swap.as_u64(), memory_info.total_swap.as_u64())).to_hex(),
+        used_mem = memory_info.used_memory.get_appropriate_unit(UnitType::Binary),
+        total_mem = memory_info.total_memory.get_appropriate_unit(UnitType::Binary),
+        used_swap = memory_info.used_swap.get_appropriate_unit(UnitType::Binary),
+        total_swap = memory_info.total_swap.get_appropriate_unit(UnitType::Binary),
+    )
+}
+
 impl Widget for MemoryUsage {
-    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
-        let interval = time::interval(self.update_interval);
-        let stream = IntervalStream::new(interval).map(move |_| Ok(self.tick()));
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let MemoryUsage {
+            attrs,
+            render,
+            update_interval,
+            mut alerts,
+        } = *self;
+
+        let alerts_empty = alerts.is_empty();
+        let mut collector = MemoryCollector::new();
+        let collect = move || -> Result<MemoryInfo> {
+            let memory_info = collector.collect()?;
+            for alert in &mut alerts {
+                alert.check(&memory_info);
+            }
+            Ok(memory_info)
+        };
+
+        let show = move |memory_info: MemoryInfo| match &render {
+            Some(render) => render.render(memory_info, &RenderContext::current()),
+            None => default_render(&memory_info),
+        };
 
-        Ok(Box::pin(stream))
+        let widget = PollingWidget::new(attrs, update_interval, collect, show)
+            .with_export("memory")
+            .with_pausing(alerts_empty);
+        Box::new(widget).into_stream()
     }
 }
@@ -0,0 +1,123 @@
+//! Shared policy for widgets making network requests, so that together they
+//! never hammer an API, e.g. when every widget refreshes at once after resume
+//! from suspend.
+//!
+//! Requests made through [`request`] are delayed by a random jitter, limited
+//! to [`MAX_CONCURRENT`] at a time across the whole bar, and skipped while
+//! their endpoint is backing off after failures, or while the machine is
+//! [offline](crate::offline).
+//!
+//! Latency probes such as [`crate::http_check`] start their timer inside the
+//! request, so the jitter doesn't distort what they measure.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Condvar, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{bail, Result};
+
+use crate::offline;
+
+/// Maximum number of requests in flight at once
+pub const MAX_CONCURRENT: usize = 4;
+
+/// Upper bound of the random delay before each request
+const MAX_JITTER: Duration = Duration::from_millis(500);
+
+/// Backoff after the first failure, doubled for each further failure
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+struct Backoff {
+    failures: u32,
+    until: Instant,
+}
+
+static IN_FLIGHT: Mutex<usize> = Mutex::new(0);
+static SLOT_FREED: Condvar = Condvar::new();
+static BACKOFFS: LazyLock<Mutex<HashMap<String, Backoff>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Releases a concurrency slot when dropped, even if the request panics
+struct Permit;
+
+impl Permit {
+    fn acquire() -> Permit {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        while *in_flight >= MAX_CONCURRENT {
+            in_flight = SLOT_FREED.wait(in_flight).unwrap();
+        }
+        *in_flight += 1;
+        Permit
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *IN_FLIGHT.lock().unwrap() -= 1;
+        SLOT_FREED.notify_one();
+    }
+}
+
+/// Runs `request` against the endpoint identified by `key`, typically its
+/// base URL. Blocks the calling thread, so should be called from a
+/// background thread such as a [`crate::cache::Cache`] refresh. Fails
+/// without making the request while the endpoint is backing off or the
+/// machine is offline
+pub fn request<T>(key: &str, request: impl FnOnce() -> Result<T>) -> Result<T> {
+    // Not counted as a failure of the endpoint
+    if offline::is_offline() {
+        bail!("offline, not requesting {key}");
+    }
+    if let Some(backoff) = BACKOFFS.lock().unwrap().get(key) {
+        if backoff.until > Instant::now() {
+            bail!("{key} is backing off after {} failures", backoff.failures);
+        }
+    }
+
+    thread::sleep(jitter());
+    let result = {
+        let _permit = Permit::acquire();
+        request()
+    };
+
+    let mut backoffs = BACKOFFS.lock().unwrap();
+    if result.is_ok() {
+        backoffs.remove(key);
+    } else {
+        let failures = backoffs.get(key).map_or(0, |backoff| backoff.failures) + 1;
+        let delay = INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(failures - 1))
+            .min(MAX_BACKOFF);
+        backoffs.insert(
+            key.to_string(),
+            Backoff {
+                failures,
+                until: Instant::now() + delay,
+            },
+        );
+    }
+
+    result
+}
+
+/// Forgets all failures, so every endpoint is tried again on the next
+/// request. Useful once the network is known to have changed
+pub fn reset_backoff() {
+    BACKOFFS.lock().unwrap().clear();
+}
+
+fn jitter() -> Duration {
+    // A randomly keyed hasher is a good enough source of randomness here
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    let fraction = (hasher.finish() % 1000) as u32;
+
+    MAX_JITTER * fraction / 1000
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use byte_unit::{Byte, UnitType};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+
+use crate::render::{Render, RenderContext};
+use crate::{paths, state};
+
+/// State file holding daily usage totals
+const USAGE_FILE: &str = "network_usage.json";
+
+/// How often accumulated usage is written to disk
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Days of usage kept in the ledger, about a quarter of billing periods
+const KEEP_DAYS: usize = 92;
+
+// Abstracted type to represent the render closure
+type NetworkRender = Box<dyn Render<NetworkInfo>>;
+
+/// Bytes transferred per day, then per network
+type UsageLedger = BTreeMap<String, BTreeMap<String, u64>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NetworkInfo {
+    pub interface: String,
+    /// SSID of the connected wireless network, `None` on wired interfaces
+    pub ssid: Option<String>,
+    pub up: bool,
+    /// Receive rate in bytes per second
+    pub rx_rate: u64,
+    /// Transmit rate in bytes per second
+    pub tx_rate: u64,
+    /// Bytes transferred since connecting to the current network
+    pub session_bytes: u64,
+    /// Bytes transferred on the current network today
+    pub today_bytes: u64,
+}
+
+/// cnx widget that shows network throughput and keeps a daily tally of data
+/// transferred on each network, for keeping an eye on metered connections
+pub struct Network {
+    attrs: Attributes,
+    render: Option<NetworkRender>,
+    update_interval: Duration,
+    interface: String,
+    previous: Option<(u64, u64, Instant)>,
+    network: Option<String>,
+    /// `operstate` and `carrier_changes` when the SSID was last read, which
+    /// stay the same for as long as the interface stays on one network
+    link: Option<(String, String)>,
+    ssid: Option<String>,
+    session_bytes: u64,
+    ledger: Arc<Mutex<UsageLedger>>,
+    last_saved: Instant,
+    unsaved: bool,
+}
+
+impl Network {
+    /// Creates a new [`Network`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<NetworkRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often counters are sampled
+    ///
+    /// `interface`: [`String`] - Network interface to watch, e.g. `wlan0`
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<NetworkRender>,
+        update_interval: Duration,
+        interface: String,
+    ) -> Network {
+        let mut ledger: UsageLedger = paths::read_state(USAGE_FILE)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        prune(&mut ledger);
+        let ledger = Arc::new(Mutex::new(ledger));
+
+        // Don't lose the usage since the last save when the bar exits
+        let pending = Arc::clone(&ledger);
+        state::bar().on_shutdown(move || {
+            if let Err(e) = save_ledger(&pending.lock().unwrap()) {
+                eprintln!("Could not save network usage: {e}");
+            }
+        });
+
+        Network {
+            attrs,
+            render,
+            update_interval,
+            interface,
+            previous: None,
+            network: None,
+            link: None,
+            ssid: None,
+            session_bytes: 0,
+            ledger,
+            last_saved: Instant::now(),
+            unsaved: false,
+        }
+    }
+
+    fn read(&self, name: &str) -> String {
+        fs::read_to_string(format!("/sys/class/net/{}/{name}", self.interface))
+            .map(|contents| contents.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    /// SSID of the current network, only asking `iw` again once the link has
+    /// gone down or changed since it last did
+    fn ssid(&mut self) -> Option<String> {
+        let link = (self.read("operstate"), self.read("carrier_changes"));
+        if self.link.as_ref() != Some(&link) {
+            self.ssid = current_ssid(&self.interface);
+            self.link = Some(link);
+        }
+        self.ssid.clone()
+    }
+
+    fn read_counter(&self, name: &str) -> u64 {
+        fs::read_to_string(format!(
+            "/sys/class/net/{}/statistics/{name}",
+            self.interface
+        ))
+        .ok()
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0)
+    }
+
+    /// Writes the ledger to disk if it has changed
+    pub fn save(&mut self) -> Result<()> {
+        if self.unsaved {
+            save_ledger(&self.ledger.lock().unwrap())?;
+            self.unsaved = false;
+        }
+        self.last_saved = Instant::now();
+        Ok(())
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        let now = Instant::now();
+        let rx = self.read_counter("rx_bytes");
+        let tx = self.read_counter("tx_bytes");
+        let up = self.read("operstate") == "up";
+        let ssid = self.ssid();
+
+        // Counters restart from zero when the interface is re-created
+        let delta = |current: u64, previous: u64| {
+            if current >= previous {
+                current - previous
+            } else {
+                current
+            }
+        };
+
+        let (rx_delta, tx_delta, elapsed) = match self.previous {
+            Some((previous_rx, previous_tx, at)) => (
+                delta(rx, previous_rx),
+                delta(tx, previous_tx),
+                now.duration_since(at).as_secs_f64(),
+            ),
+            None => (0, 0, 0.0),
+        };
+        self.previous = Some((rx, tx, now));
+
+        let network = ssid.clone().unwrap_or_else(|| self.interface.clone());
+        if self.network.as_ref() != Some(&network) {
+            self.network = Some(network.clone());
+            self.session_bytes = 0;
+        }
+
+        let transferred = rx_delta + tx_delta;
+        self.session_bytes += transferred;
+
+        let today = chrono::Local::now().date_naive().to_string();
+        let mut ledger = self.ledger.lock().unwrap();
+        if !ledger.contains_key(&today) {
+            ledger.insert(today.clone(), BTreeMap::new());
+            prune(&mut ledger);
+        }
+        let today_bytes = ledger.entry(today).or_default().entry(network).or_default();
+        *today_bytes += transferred;
+        let today_bytes = *today_bytes;
+        drop(ledger);
+
+        if transferred > 0 {
+            self.unsaved = true;
+        }
+        if now.duration_since(self.last_saved) >= SAVE_INTERVAL {
+            let _ = self.save();
+        }
+
+        let rate = |bytes: u64| {
+            if elapsed > 0.0 {
+                (bytes as f64 / elapsed) as u64
+            } else {
+                0
+            }
+        };
+
+        let network_info = NetworkInfo {
+            interface: self.interface.clone(),
+            ssid,
+            up,
+            rx_rate: rate(rx_delta),
+            tx_rate: rate(tx_delta),
+            session_bytes: self.session_bytes,
+            today_bytes,
+        };
+        state::bar().set_value(&format!("network_{}", self.interface), &network_info);
+
+        let text = if let Some(render) = &self.render {
+            render.render(network_info, &RenderContext::current())
+        } else if !network_info.up {
+            format!("{} down", network_info.interface)
+        } else {
+            let format_bytes = |bytes: u64| {
+                format!(
+                    "{:.1}",
+                    Byte::from_u64(bytes).get_appropriate_unit(UnitType::Binary)
+                )
+            };
+
+            format!(
+                "{} ↓{}/s ↑{}/s ({} today)",
+                network_info
+                    .ssid
+                    .as_deref()
+                    .unwrap_or(&network_info.interface),
+                format_bytes(network_info.rx_rate),
+                format_bytes(network_info.tx_rate),
+                format_bytes(network_info.today_bytes)
+            )
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: self.render.is_some(),
+        }]
+    }
+}
+
+impl Widget for Network {
+    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Drops all but the last [`KEEP_DAYS`] days. Days are ISO dates, so they
+/// sort oldest first
+fn prune(ledger: &mut UsageLedger) {
+    while ledger.len() > KEEP_DAYS {
+        ledger.pop_first();
+    }
+}
+
+fn save_ledger(ledger: &UsageLedger) -> Result<()> {
+    paths::write_state(USAGE_FILE, &serde_json::to_string(ledger)?)?;
+    Ok(())
+}
+
+/// SSID of the wireless network `interface` is connected to, from `iw`
+fn current_ssid(interface: &str) -> Option<String> {
+    let output = Command::new("iw")
+        .args(["dev", interface, "link"])
+        .output()
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("SSID: "))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, NaiveDate};
+
+    use super::*;
+
+    #[test]
+    fn prune_keeps_the_latest_days() {
+        let start = NaiveDate::from_ymd_opt(2025, 12, 1).unwrap();
+        let mut ledger: UsageLedger = (0..100)
+            .map(|day| {
+                let date = start + Days::new(day);
+                (
+                    date.to_string(),
+                    BTreeMap::from([("home".to_string(), day)]),
+                )
+            })
+            .collect();
+
+        prune(&mut ledger);
+        assert_eq!(ledger.len(), KEEP_DAYS);
+        // Across the turn of the year, the oldest go first
+        assert_eq!(ledger.keys().next().unwrap(), "2025-12-09");
+        assert_eq!(ledger.keys().last().unwrap(), "2026-03-10");
+    }
+}
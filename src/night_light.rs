@@ -0,0 +1,198 @@
+//! Status of a night light tool, gammastep or redshift, which can be toggled
+//! through the `night_light` IPC command.
+
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::render::{Render, RenderContext};
+use crate::{ipc, state};
+
+// Abstracted type to represent the render closure
+type NightLightRender = Box<dyn Render<NightLightInfo>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NightLightTool {
+    Gammastep,
+    Redshift,
+}
+
+impl NightLightTool {
+    fn command(self) -> &'static str {
+        match self {
+            NightLightTool::Gammastep => "gammastep",
+            NightLightTool::Redshift => "redshift",
+        }
+    }
+
+    fn is_running(self) -> bool {
+        Command::new("pgrep")
+            .args(["-x", self.command()])
+            .stdout(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+pub struct NightLightInfo {
+    pub running: bool,
+    /// Whether the colour adjustment is applied. Both tools toggle it on
+    /// `SIGUSR1` without reporting the change, so this is tracked by the bar
+    /// and assumes the tool started enabled
+    pub enabled: bool,
+    /// Colour temperature for the current time of day, in Kelvin
+    pub temperature: Option<u32>,
+    /// `Daytime`, `Night` or the transition between them
+    pub period: Option<String>,
+}
+
+/// cnx widget that shows whether night light is active and its colour
+/// temperature. Hidden while the tool isn't running
+pub struct NightLight {
+    attrs: Attributes,
+    render: Option<NightLightRender>,
+    update_interval: Duration,
+    tool: NightLightTool,
+    enabled: Arc<AtomicBool>,
+}
+
+impl NightLight {
+    /// Creates a new [`NightLight`] widget and registers the `night_light`
+    /// IPC command, which takes `toggle`, `on` or `off`. Turning it on starts
+    /// the tool if it isn't running
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<NightLightRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the status is updated
+    ///
+    /// `tool`: [`NightLightTool`] - The tool controlling the screen colour
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<NightLightRender>,
+        update_interval: Duration,
+        tool: NightLightTool,
+    ) -> NightLight {
+        let enabled = Arc::new(AtomicBool::new(true));
+
+        let toggled = Arc::clone(&enabled);
+        ipc::register("night_light", move |args| {
+            let running = tool.is_running();
+            let enable = match args {
+                ["toggle"] | [] => !(running && toggled.load(Ordering::Relaxed)),
+                ["on"] => true,
+                ["off"] => false,
+                _ => bail!("usage: night_light [toggle|on|off]"),
+            };
+
+            if !running {
+                if enable {
+                    Command::new(tool.command())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn()?;
+                    toggled.store(true, Ordering::Relaxed);
+                }
+            } else if enable != toggled.load(Ordering::Relaxed) {
+                Command::new("pkill")
+                    .args(["-USR1", "-x", tool.command()])
+                    .status()?;
+                toggled.store(enable, Ordering::Relaxed);
+            }
+
+            state::bar().request_refresh();
+            Ok(String::new())
+        });
+
+        NightLight {
+            attrs,
+            render,
+            update_interval,
+            tool,
+            enabled,
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        if !self.tool.is_running() {
+            // A restarted tool comes back enabled
+            self.enabled.store(true, Ordering::Relaxed);
+            return vec![];
+        }
+
+        let (temperature, period) = self.current_setting();
+        let night_light_info = NightLightInfo {
+            running: true,
+            enabled: self.enabled.load(Ordering::Relaxed),
+            temperature,
+            period,
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(night_light_info, &RenderContext::current())
+        } else if !night_light_info.enabled {
+            format!(
+                "<span foreground=\"{}\">night off</span>",
+                Color::from_rgb(128, 128, 128).to_hex()
+            )
+        } else {
+            match night_light_info.temperature {
+                Some(temperature) => format!("night {temperature}K"),
+                None => "night".to_string(),
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+
+    /// Asks the tool for the colour temperature it would set right now
+    fn current_setting(&self) -> (Option<u32>, Option<String>) {
+        let Ok(output) = Command::new(self.tool.command()).arg("-p").output() else {
+            return (None, None);
+        };
+
+        // Both tools print their status on stdout, older redshift versions on
+        // stderr
+        let mut temperature = None;
+        let mut period = None;
+        for line in String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .chain(String::from_utf8_lossy(&output.stderr).lines())
+        {
+            if let Some(value) = line.strip_prefix("Color temperature:") {
+                temperature = value.trim().trim_end_matches('K').parse().ok();
+            } else if let Some(value) = line.strip_prefix("Period:") {
+                period = Some(value.trim().to_string());
+            }
+        }
+
+        (temperature, period)
+    }
+}
+
+impl Widget for NightLight {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
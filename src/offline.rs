@@ -0,0 +1,134 @@
+//! Watches whether the machine is online at all, so that widgets fetching
+//! from the web pause together rather than each timing out on its own.
+//!
+//! The machine counts as offline when no network device has a carrier, i.e.
+//! no cable plugged in and no wireless link, or when its only links are
+//! wireless while rfkill blocks wireless, as in flight mode. Routes vary too
+//! much between setups, e.g. with VPNs or policy routing, so going without a
+//! default route only counts with `offline_requires_route` in the config.
+//! Where sysfs doesn't list interfaces, as on the BSDs, the machine always
+//! counts as online. While offline, [`net::request`] fails
+//! straight away and widgets keep showing their cached data. Once back
+//! online, backoffs and cached DNS answers are forgotten and every widget
+//! refreshes.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::{dns, net, state};
+
+/// How often carriers, rfkill and routes are checked
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the machine was offline when last checked. Always `false` unless
+/// [`watch`] is running
+#[must_use]
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Network devices other than loopback with a carrier, `None` where sysfs
+/// doesn't list interfaces. Virtual interfaces such as bridges and tunnels
+/// have no `device` and are left out, as their carrier says nothing about
+/// the network
+fn carrier_interfaces() -> Option<Vec<String>> {
+    let interfaces = fs::read_dir("/sys/class/net").ok()?;
+
+    Some(
+        interfaces
+            .flatten()
+            .filter(|interface| interface.path().join("device").exists())
+            .filter(|interface| {
+                // Reading the carrier of a device that is down fails
+                fs::read_to_string(interface.path().join("carrier"))
+                    .is_ok_and(|carrier| carrier.trim() == "1")
+            })
+            .map(|interface| interface.file_name().to_string_lossy().into_owned())
+            .collect(),
+    )
+}
+
+/// Interfaces with an IPv4 or IPv6 default route
+fn default_route_interfaces() -> Vec<String> {
+    let mut interfaces = vec![];
+
+    // `Iface  Destination  Gateway ...` with addresses in hex
+    if let Ok(routes) = fs::read_to_string("/proc/net/route") {
+        for line in routes.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let [interface, "00000000", ..] = fields.as_slice() {
+                interfaces.push(interface.to_string());
+            }
+        }
+    }
+
+    // `destination prefix_length source prefix_length next_hop metric refs
+    // use flags interface`, skipping the loopback's unreachable route
+    if let Ok(routes) = fs::read_to_string("/proc/net/ipv6_route") {
+        for line in routes.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if let [destination, "00", .., interface] = fields.as_slice() {
+                if destination.bytes().all(|byte| byte == b'0') && *interface != "lo" {
+                    interfaces.push(interface.to_string());
+                }
+            }
+        }
+    }
+
+    interfaces
+}
+
+/// Whether rfkill blocks wireless LAN, in software or with a switch
+fn wireless_blocked() -> bool {
+    let Ok(devices) = fs::read_dir("/sys/class/rfkill") else {
+        return false;
+    };
+
+    devices.flatten().any(|device| {
+        let read = |name: &str| fs::read_to_string(device.path().join(name)).unwrap_or_default();
+        read("type").trim() == "wlan" && (read("soft").trim() == "1" || read("hard").trim() == "1")
+    })
+}
+
+fn offline(requires_route: bool) -> bool {
+    if requires_route && default_route_interfaces().is_empty() {
+        return true;
+    }
+    let Some(interfaces) = carrier_interfaces() else {
+        return false;
+    };
+
+    let blocked = wireless_blocked();
+    interfaces.iter().all(|interface| {
+        let wireless = Path::new("/sys/class/net")
+            .join(interface)
+            .join("wireless")
+            .exists();
+        wireless && blocked
+    })
+}
+
+/// Starts checking connectivity on a background thread. With
+/// `requires_route`, the machine also counts as offline without a default
+/// route
+pub fn watch(requires_route: bool) {
+    OFFLINE.store(offline(requires_route), Ordering::Relaxed);
+
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let offline = offline(requires_route);
+        let was_offline = OFFLINE.swap(offline, Ordering::Relaxed);
+        if was_offline && !offline {
+            // Failures while offline say nothing about the endpoints
+            net::reset_backoff();
+            dns::flush();
+            state::bar().request_refresh();
+        }
+    });
+}
@@ -0,0 +1,47 @@
+//! Locations of the files the bar reads and writes, following the XDG base
+//! directory specification.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+fn xdg_dir(variable: &str, fallback: &str) -> PathBuf {
+    env::var_os(variable)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(fallback))
+}
+
+fn home_dir() -> PathBuf {
+    env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+/// Directory holding `config.toml`
+#[must_use]
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("status_bar")
+}
+
+/// Directory for state persisted across restarts, such as usage counters
+#[must_use]
+pub fn state_dir() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state").join("status_bar")
+}
+
+/// Reads the state file called `name`
+pub fn read_state(name: &str) -> io::Result<String> {
+    fs::read_to_string(state_dir().join(name))
+}
+
+/// Replaces the state file called `name`. The file is written under a
+/// temporary name and renamed into place, so a crash mid-write can't leave it
+/// truncated
+pub fn write_state(name: &str, contents: &str) -> io::Result<()> {
+    let dir = state_dir();
+    fs::create_dir_all(&dir)?;
+
+    let temporary = dir.join(format!(".{name}.tmp"));
+    fs::write(&temporary, contents)?;
+    fs::rename(temporary, dir.join(name))
+}
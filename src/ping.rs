@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::cache::Cache;
+use crate::render::{Render, RenderContext};
+use crate::{dns, markup, state};
+
+/// Number of probes per host used to compute packet loss
+const HISTORY_LEN: usize = 10;
+
+// Abstracted type to represent the render closure
+type PingRender = Box<dyn Render<PingInfo>>;
+
+#[derive(Clone)]
+pub struct HostLatency {
+    pub host: String,
+    /// Round-trip time of the latest probe, `None` if it timed out
+    pub latency: Option<Duration>,
+    /// Percentage of the recent probes that failed
+    pub loss: f64,
+}
+
+pub struct PingInfo {
+    pub hosts: Vec<HostLatency>,
+}
+
+/// cnx widget that measures round-trip latency to a set of hosts using TCP
+/// connects, which unlike ICMP needs no special privileges
+pub struct Ping {
+    attrs: Attributes,
+    render: Option<PingRender>,
+    update_interval: Duration,
+    timeout: Duration,
+    hosts: Vec<String>,
+    history: Arc<Mutex<Vec<VecDeque<Option<Duration>>>>>,
+    cache: Cache<Vec<HostLatency>>,
+}
+
+impl Ping {
+    /// Creates a new [`Ping`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<PingRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often each host is probed
+    ///
+    /// `timeout`: [`Duration`] - How long to wait before counting a probe as
+    /// lost
+    ///
+    /// `hosts`: [`Vec<String>`] - Hosts to probe, as `host:port`
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<PingRender>,
+        update_interval: Duration,
+        timeout: Duration,
+        hosts: Vec<String>,
+    ) -> Ping {
+        let history = hosts
+            .iter()
+            .map(|_| VecDeque::with_capacity(HISTORY_LEN))
+            .collect();
+
+        Ping {
+            attrs,
+            render,
+            update_interval,
+            timeout,
+            hosts,
+            history: Arc::new(Mutex::new(history)),
+            // Probes never fail, a lost one is a result
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let (hosts, history, timeout) =
+            (self.hosts.clone(), Arc::clone(&self.history), self.timeout);
+        let cached = self
+            .cache
+            .get(move || Ok(probe_all(&hosts, &history, timeout)));
+        let Some(cached) = cached else {
+            return vec![];
+        };
+
+        let ping_info = PingInfo {
+            hosts: cached.value,
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(ping_info, &RenderContext::current())
+        } else {
+            ping_info
+                .hosts
+                .iter()
+                .map(|host| {
+                    let colour = match host.loss {
+                        loss if loss == 0.0 => Color::green(),
+                        loss if loss < 20.0 => Color::yellow(),
+                        _ => Color::red(),
+                    }
+                    .to_hex();
+                    let latency = host
+                        .latency
+                        .map(|latency| format!("{}ms", latency.as_millis()))
+                        .unwrap_or_else(|| "timeout".to_string());
+
+                    format!(
+                        "{} <span foreground=\"{colour}\">{latency}</span>",
+                        markup::escape(&host.host)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Ping {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Times a TCP connect to `host`. A refused connection still means the host
+/// answered, so it counts as a successful probe
+fn probe(host: &str, timeout: Duration) -> Option<Duration> {
+    let address = dns::resolve(host).ok()?.into_iter().next()?;
+
+    let start = Instant::now();
+    match TcpStream::connect_timeout(&address, timeout) {
+        Ok(_) => Some(start.elapsed()),
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => Some(start.elapsed()),
+        Err(_) => None,
+    }
+}
+
+/// Probes every host at once, so an unreachable host doesn't delay the
+/// others, and records the results in each host's `history`
+fn probe_all(
+    hosts: &[String],
+    history: &Mutex<Vec<VecDeque<Option<Duration>>>>,
+    timeout: Duration,
+) -> Vec<HostLatency> {
+    let latencies: Vec<_> = thread::scope(|scope| {
+        let probes: Vec<_> = hosts
+            .iter()
+            .map(|host| scope.spawn(move || probe(host, timeout)))
+            .collect();
+        probes
+            .into_iter()
+            .map(|probe| probe.join().expect("probe panicked"))
+            .collect()
+    });
+
+    let mut history = history.lock().unwrap();
+    hosts
+        .iter()
+        .zip(latencies)
+        .zip(history.iter_mut())
+        .map(|((host, latency), history)| {
+            if history.len() == HISTORY_LEN {
+                history.pop_front();
+            }
+            history.push_back(latency);
+
+            let lost = history.iter().filter(|sample| sample.is_none()).count();
+            HostLatency {
+                host: host.clone(),
+                latency,
+                loss: 100.0 * lost as f64 / history.len() as f64,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use anyhow::Result;
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use tokio::io::{self, AsyncBufReadExt, BufReader};
+use tokio::net::unix::pipe;
+use tokio_stream::wrappers::LinesStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::event::EventWidget;
+use crate::markup;
+use crate::render::{Render, RenderContext};
+
+// Abstracted type to represent the render closure
+type PipeRender = Box<dyn Render<String>>;
+
+/// Where a [`Pipe`] widget reads its lines from
+pub enum PipeSource {
+    Stdin,
+    /// Path to a named pipe, created beforehand with `mkfifo`
+    Fifo(PathBuf),
+}
+
+/// cnx widget that shows the latest line written to stdin or a named pipe,
+/// re-rendering as soon as a new line arrives
+pub struct Pipe {
+    attrs: Attributes,
+    render: Option<PipeRender>,
+    source: PipeSource,
+}
+
+impl Pipe {
+    /// Creates a new [`Pipe`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<PipeRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `source`: [`PipeSource`] - Where lines are read from
+    #[must_use]
+    pub fn new(attrs: Attributes, render: Option<PipeRender>, source: PipeSource) -> Pipe {
+        Pipe {
+            attrs,
+            render,
+            source,
+        }
+    }
+}
+
+impl Widget for Pipe {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let lines: Pin<Box<dyn Stream<Item = io::Result<String>>>> = match &self.source {
+            PipeSource::Stdin => Box::pin(LinesStream::new(BufReader::new(io::stdin()).lines())),
+            PipeSource::Fifo(path) => {
+                // Opening the pipe for writing as well means there is always a
+                // writer attached, so it doesn't hit EOF when a script exits
+                let receiver = pipe::OpenOptions::new()
+                    .read_write(true)
+                    .open_receiver(path)?;
+                Box::pin(LinesStream::new(BufReader::new(receiver).lines()))
+            }
+        };
+
+        let render = self.render;
+        let widget =
+            EventWidget::new(
+                self.attrs,
+                lines.map_while(io::Result::ok),
+                move |line| match &render {
+                    Some(render) => render.render(line, &RenderContext::current()),
+                    None => markup::escape(&line),
+                },
+            );
+
+        Box::new(widget).into_stream()
+    }
+}
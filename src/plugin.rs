@@ -0,0 +1,182 @@
+//! Widgets implemented as WebAssembly modules loaded at runtime.
+//!
+//! A plugin is a WASI (preview 1) module which exports `memory` and a
+//! `tick` function taking no arguments. Reactor modules exporting
+//! `_initialize` have it called once after instantiation. Alongside WASI,
+//! plugins can import the following from the `status_bar` module:
+//!
+//! - `emit(ptr: i32, len: i32)` - sets the Pango markup shown by the widget
+//!   to the UTF-8 string at `ptr`. The last call during a `tick` wins.
+//! - `config_len() -> i32` - length in bytes of the plugin's config string
+//! - `config_read(ptr: i32)` - copies the config string to `ptr`
+//!
+//! Each call into the plugin is given [`FUEL_PER_CALL`] units of fuel, about
+//! one per instruction, so a plugin stuck in a loop fails its tick rather
+//! than hanging the bar.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Memory, Module, Store, Trap, TypedFunc};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::WasiCtxBuilder;
+
+use crate::state;
+
+/// Name of the import module holding the host API
+const HOST_MODULE: &str = "status_bar";
+
+/// Fuel a plugin may use per call of `_initialize` or `tick`
+pub const FUEL_PER_CALL: u64 = 100_000_000;
+
+struct PluginState {
+    wasi: WasiP1Ctx,
+    config: String,
+    output: Option<String>,
+}
+
+/// cnx widget whose content is produced by a WASM plugin
+pub struct Plugin {
+    attrs: Attributes,
+    update_interval: Duration,
+    store: Store<PluginState>,
+    tick: TypedFunc<(), ()>,
+}
+
+impl Plugin {
+    /// Loads a plugin and creates a [`Plugin`] widget from it
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `update_interval`: [`Duration`] - How often the plugin's `tick` is
+    /// called
+    ///
+    /// `path`: [`Path`] - Path to the `.wasm` module
+    ///
+    /// `config`: [`String`] - Plugin-defined configuration, readable by the
+    /// plugin through `config_read`
+    pub fn new(
+        attrs: Attributes,
+        update_interval: Duration,
+        path: &Path,
+        config: String,
+    ) -> Result<Plugin> {
+        let mut engine_config = Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config)?;
+        let module = Module::from_file(&engine, path)?;
+
+        let mut linker: Linker<PluginState> = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |state: &mut PluginState| &mut state.wasi)?;
+
+        linker.func_wrap(
+            HOST_MODULE,
+            "emit",
+            |mut caller: Caller<'_, PluginState>, ptr: i32, len: i32| -> Result<()> {
+                let memory = guest_memory(&mut caller)?;
+                // Checked before allocating, as the guest controls `len`
+                let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+                if ptr
+                    .checked_add(len)
+                    .is_none_or(|end| end > memory.data_size(&caller))
+                {
+                    bail!("emit({ptr}, {len}) is outside the plugin's memory");
+                }
+                let buffer = memory.data(&caller)[ptr..ptr + len].to_vec();
+                caller.data_mut().output = Some(String::from_utf8_lossy(&buffer).into_owned());
+                Ok(())
+            },
+        )?;
+        linker.func_wrap(
+            HOST_MODULE,
+            "config_len",
+            |caller: Caller<'_, PluginState>| -> i32 { caller.data().config.len() as i32 },
+        )?;
+        linker.func_wrap(
+            HOST_MODULE,
+            "config_read",
+            |mut caller: Caller<'_, PluginState>, ptr: i32| -> Result<()> {
+                let memory = guest_memory(&mut caller)?;
+                let config = caller.data().config.clone();
+                memory.write(&mut caller, ptr as u32 as usize, config.as_bytes())?;
+                Ok(())
+            },
+        )?;
+
+        let state = PluginState {
+            wasi: WasiCtxBuilder::new().inherit_stderr().build_p1(),
+            config,
+            output: None,
+        };
+        let mut store = Store::new(&engine, state);
+
+        let instance = linker.instantiate(&mut store, &module)?;
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            store.set_fuel(FUEL_PER_CALL)?;
+            initialize.call(&mut store, ()).map_err(out_of_fuel)?;
+        }
+        let tick = instance.get_typed_func::<(), ()>(&mut store, "tick")?;
+
+        Ok(Plugin {
+            attrs,
+            update_interval,
+            store,
+            tick,
+        })
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        self.store.data_mut().output = None;
+
+        let result = self
+            .store
+            .set_fuel(FUEL_PER_CALL)
+            .and_then(|()| self.tick.call(&mut self.store, ()).map_err(out_of_fuel));
+        // Errors are shown verbatim, so they mustn't be parsed as markup
+        let (text, markup) = match result {
+            Ok(()) => (
+                self.store.data_mut().output.take().unwrap_or_default(),
+                true,
+            ),
+            Err(e) => (format!("plugin error: {e}"), false),
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup,
+        }]
+    }
+}
+
+impl Widget for Plugin {
+    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+fn guest_memory(caller: &mut Caller<'_, PluginState>) -> Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| anyhow!("Plugin does not export its memory"))
+}
+
+/// Replaces the trap of a plugin running out of fuel with a clearer error
+fn out_of_fuel(e: anyhow::Error) -> anyhow::Error {
+    if e.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) {
+        anyhow!("plugin used more than {FUEL_PER_CALL} fuel in one call")
+    } else {
+        e
+    }
+}
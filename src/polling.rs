@@ -0,0 +1,135 @@
+//! Scaffolding shared by widgets that sample something on an interval and
+//! render it, so that such a widget only has to supply the two functions.
+//!
+//! ```ignore
+//! let uptime = PollingWidget::new(
+//!     attrs,
+//!     Duration::from_secs(60),
+//!     || Ok(fs::read_to_string("/proc/uptime")?),
+//!     |uptime| format!("up {}", uptime.split('.').next().unwrap_or_default()),
+//! );
+//! ```
+
+use std::pin::Pin;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde::Serialize;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::state;
+
+/// cnx widget that calls `collect` on every tick of [`state::ticks`] and
+/// shows what `render` makes of the result.
+///
+/// The widget hides itself while `collect` fails or `render` returns an empty
+/// string
+pub struct PollingWidget<T> {
+    attrs: Attributes,
+    update_interval: Duration,
+    collect: Box<dyn FnMut() -> Result<T>>,
+    render: Box<dyn Fn(T) -> String>,
+    export: Option<Box<dyn Fn(&T)>>,
+    pausing: bool,
+}
+
+impl<T: 'static> PollingWidget<T> {
+    /// Creates a new [`PollingWidget`]
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `update_interval`: [`Duration`] - How often `collect` is called
+    ///
+    /// `collect`: Takes a reading
+    ///
+    /// `render`: Turns a reading into Pango markup
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        update_interval: Duration,
+        collect: impl FnMut() -> Result<T> + 'static,
+        render: impl Fn(T) -> String + 'static,
+    ) -> PollingWidget<T> {
+        PollingWidget {
+            attrs,
+            update_interval,
+            collect: Box::new(collect),
+            render: Box::new(render),
+            export: None,
+            pausing: true,
+        }
+    }
+
+    /// Whether the widget stops polling while the bar is paused, see
+    /// [`crate::dormant`]. Widgets whose readings trigger actions should keep
+    /// polling
+    #[must_use]
+    pub fn with_pausing(mut self, pausing: bool) -> Self {
+        self.pausing = pausing;
+        self
+    }
+
+    /// Takes a reading and exports it
+    fn collect(&mut self) -> Option<T> {
+        let value = (self.collect)().ok()?;
+        if let Some(export) = &self.export {
+            export(&value);
+        }
+        Some(value)
+    }
+
+    fn tick(&mut self) -> Vec<Text> {
+        let Some(value) = self.collect() else {
+            return vec![];
+        };
+
+        let text = (self.render)(value);
+        if text.is_empty() {
+            return vec![];
+        }
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl<T: Serialize + 'static> PollingWidget<T> {
+    /// Records each reading under `name` with [`state::BarState::set_value`],
+    /// making it available to [`crate::export`]
+    #[must_use]
+    pub fn with_export(mut self, name: &str) -> Self {
+        let name = name.to_string();
+        self.export = Some(Box::new(move |value| state::bar().set_value(&name, value)));
+        self
+    }
+}
+
+impl<T: 'static> Widget for PollingWidget<T> {
+    fn into_stream(mut self: Box<Self>) -> Result<WidgetStream> {
+        let stream = if self.pausing {
+            Box::pin(state::ticks(self.update_interval)) as Pin<Box<dyn Stream<Item = ()>>>
+        } else {
+            Box::pin(state::ticks_while_paused(self.update_interval))
+        };
+
+        let stream = stream.filter_map(move |_| {
+            // Still collecting for the sake of actions, but nothing is drawn
+            if state::bar().is_paused() {
+                self.collect();
+                return None;
+            }
+            Some(Ok(self.tick()))
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use crate::cache::{self, Cache};
+use crate::render::{Render, RenderContext};
+use crate::{markup, state};
+
+// Abstracted type to represent the render closure
+type PoolRender = Box<dyn Render<PoolInfo>>;
+
+/// Storage pool queried by the [`Pool`] widget
+#[derive(Clone)]
+pub enum PoolBackend {
+    /// ZFS pool, identified by its pool name. Queried with `zpool status -j`
+    Zfs(String),
+    /// Btrfs filesystem, identified by its mount point. Queried with
+    /// `btrfs device stats` and `btrfs scrub status`
+    Btrfs(String),
+}
+
+/// Pool health, following the ZFS vdev states
+#[derive(Clone, Debug, PartialEq)]
+pub enum PoolHealth {
+    Unknown,
+    Online,
+    Degraded,
+    Faulted,
+    Unavailable,
+}
+
+#[derive(Clone)]
+pub struct PoolInfo {
+    pub name: String,
+    pub health: PoolHealth,
+    /// Percentage of the running scrub that has completed, if a scrub is in
+    /// progress
+    pub scrub_progress: Option<f64>,
+    /// Whether the reading is older than the cache's TTL, because querying
+    /// the pool has been failing
+    pub stale: bool,
+}
+
+/// Btrfs error counters as first read, keyed by device and counter
+type Baseline = Arc<Mutex<HashMap<String, u64>>>;
+
+/// cnx widget that shows the health and scrub progress of a ZFS pool or
+/// Btrfs filesystem
+pub struct Pool {
+    attrs: Attributes,
+    render: Option<PoolRender>,
+    update_interval: Duration,
+    backend: PoolBackend,
+    baseline: Baseline,
+    cache: Cache<PoolInfo>,
+}
+
+impl Pool {
+    /// Creates a new [`Pool`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<PoolRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the pool is queried
+    ///
+    /// `backend`: [`PoolBackend`] - The pool to watch
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<PoolRender>,
+        update_interval: Duration,
+        backend: PoolBackend,
+    ) -> Pool {
+        Pool {
+            attrs,
+            render,
+            update_interval,
+            backend,
+            baseline: Arc::default(),
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let (backend, baseline) = (self.backend.clone(), Arc::clone(&self.baseline));
+        let cached = self.cache.get(move || match &backend {
+            PoolBackend::Zfs(pool) => zfs_info(pool),
+            PoolBackend::Btrfs(mount) => btrfs_info(mount, &baseline),
+        });
+        let Some(cached) = cached else {
+            return vec![];
+        };
+        let pool_info = PoolInfo {
+            stale: cached.stale,
+            ..cached.value
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(pool_info, &RenderContext::current())
+        } else {
+            let colour = match pool_info.health {
+                PoolHealth::Online => Color::white(),
+                PoolHealth::Unknown => Color::yellow(),
+                _ => Color::red(),
+            }
+            .to_hex();
+            let scrub = pool_info
+                .scrub_progress
+                .map(|progress| format!(" (scrub {progress:.0}%)"))
+                .unwrap_or_default();
+
+            let text = format!(
+                "{name}: <span foreground=\"{colour}\">{health:?}</span>{scrub}",
+                name = markup::escape(&pool_info.name),
+                health = pool_info.health,
+            );
+            if pool_info.stale {
+                cache::mark_stale(&text)
+            } else {
+                text
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Pool {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Reads a JSON number, accepting the string-encoded numbers `zpool` emits
+/// without `--json-int`
+fn json_number(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+fn zfs_info(pool: &str) -> Result<PoolInfo> {
+    let output = Command::new("zpool")
+        .args(["status", "-j", "--json-int", pool])
+        .output()?;
+    if !output.status.success() {
+        bail!("zpool status exited with {}", output.status);
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let status = &json["pools"][pool];
+
+    let health = match status["state"].as_str() {
+        Some("ONLINE") => PoolHealth::Online,
+        Some("DEGRADED") => PoolHealth::Degraded,
+        Some("FAULTED") | Some("SUSPENDED") => PoolHealth::Faulted,
+        Some("OFFLINE") | Some("REMOVED") | Some("UNAVAIL") => PoolHealth::Unavailable,
+        _ => PoolHealth::Unknown,
+    };
+
+    let scan = &status["scan_stats"];
+    let scrub_progress = if scan["function"] == "SCRUB" && scan["state"] == "SCANNING" {
+        let done = json_number(&scan["issued"]).or_else(|| json_number(&scan["examined"]));
+        let total = json_number(&scan["to_examine"]);
+        match (done, total) {
+            (Some(done), Some(total)) if total > 0.0 => Some(100.0 * done / total),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    Ok(PoolInfo {
+        name: pool.to_string(),
+        health,
+        scrub_progress,
+        stale: false,
+    })
+}
+
+/// Error counters from `btrfs device stats`, keyed by device and counter
+fn device_errors(stats: &str) -> HashMap<String, u64> {
+    // Each line looks like `[/dev/sda1].write_io_errs    0`
+    stats
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let counter = words.next()?;
+            let count = words.next()?.parse().ok()?;
+            Some((counter.to_string(), count))
+        })
+        .collect()
+}
+
+/// Whether any counter in `errors` has gone up since it was first seen. The
+/// counters last for the life of the filesystem, so errors from before the
+/// bar started, e.g. a cable since replaced, don't count
+fn new_errors(baseline: &mut HashMap<String, u64>, errors: HashMap<String, u64>) -> bool {
+    let mut increased = false;
+    for (counter, count) in errors {
+        increased |= count > *baseline.entry(counter).or_insert(count);
+    }
+    increased
+}
+
+fn btrfs_info(mount: &str, baseline: &Mutex<HashMap<String, u64>>) -> Result<PoolInfo> {
+    let stats = Command::new("btrfs")
+        .args(["device", "stats", mount])
+        .output()?;
+    if stats.stdout.is_empty() {
+        bail!("btrfs device stats produced no output");
+    }
+
+    let errors = device_errors(&String::from_utf8_lossy(&stats.stdout));
+    let health = if new_errors(&mut baseline.lock().unwrap(), errors) {
+        PoolHealth::Degraded
+    } else {
+        PoolHealth::Online
+    };
+
+    let scrub = Command::new("btrfs")
+        .args(["scrub", "status", mount])
+        .output()?;
+    let scrub = String::from_utf8_lossy(&scrub.stdout);
+
+    let running = scrub
+        .lines()
+        .any(|line| line.starts_with("Status:") && line.contains("running"));
+    let scrub_progress = if running {
+        // `Bytes scrubbed:   10.00GiB  (10.00%)`
+        scrub
+            .lines()
+            .find(|line| line.starts_with("Bytes scrubbed:"))
+            .and_then(|line| line.rsplit_once('('))
+            .and_then(|(_, percent)| percent.trim_end_matches([')', '%']).parse().ok())
+    } else {
+        None
+    };
+
+    Ok(PoolInfo {
+        name: mount.to_string(),
+        health,
+        scrub_progress,
+        stale: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATS: &str = "\
+[/dev/sda1].write_io_errs    0
+[/dev/sda1].read_io_errs     3
+[/dev/sda1].flush_io_errs    0
+[/dev/sda1].corruption_errs  0
+[/dev/sda1].generation_errs  0
+";
+
+    #[test]
+    fn parses_device_stats() {
+        let errors = device_errors(STATS);
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors["[/dev/sda1].read_io_errs"], 3);
+    }
+
+    #[test]
+    fn old_errors_are_not_degraded() {
+        let mut baseline = HashMap::new();
+        assert!(!new_errors(&mut baseline, device_errors(STATS)));
+        assert!(!new_errors(&mut baseline, device_errors(STATS)));
+
+        let more = STATS.replace("read_io_errs     3", "read_io_errs     4");
+        assert!(new_errors(&mut baseline, device_errors(&more)));
+        // Still above what was first seen
+        assert!(new_errors(&mut baseline, device_errors(&more)));
+    }
+}
@@ -0,0 +1,209 @@
+//! A single segment for everything power related: battery charge, mains
+//! power and the power profile, instead of a widget for each.
+//!
+//! The segment is read again as soon as UPower or power-profiles-daemon
+//! emits `PropertiesChanged`, and on an interval in between so that the
+//! battery estimate keeps its samples on systems without them.
+
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use tokio::sync::mpsc as tokio_mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use zbus::blocking::Connection;
+
+use crate::battery::ChargeStatus;
+use crate::collectors::power::PowerCollector;
+pub use crate::collectors::power::{PowerInfo, PowerProfile};
+use crate::collectors::Collector;
+use crate::event::EventWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{color, dbus, icons, state};
+
+/// Objects whose properties make up the segment: UPower itself for
+/// `OnBattery`, its combined battery, and power-profiles-daemon
+const WATCHED_PATHS: [&str; 3] = [
+    "/org/freedesktop/UPower",
+    "/org/freedesktop/UPower/devices/DisplayDevice",
+    "/net/hadess/PowerProfiles",
+];
+
+/// UPower updates several properties of a device at once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+// Abstracted type to represent the render closure
+type PowerRender = Box<dyn Render<PowerInfo>>;
+
+impl Fields for PowerInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(power_icon(self)))),
+            "profile_icon" => self
+                .profile
+                .and_then(profile_icon)
+                .map(|icon| Value::Markup(context.icon(icon))),
+            "profile" => self.profile.map(|profile| {
+                Value::Text(
+                    match profile {
+                        PowerProfile::PowerSaver => "power-saver",
+                        PowerProfile::Balanced => "balanced",
+                        PowerProfile::Performance => "performance",
+                    }
+                    .to_string(),
+                )
+            }),
+            "on_ac" => Some(Value::Number(f64::from(u8::from(self.on_ac)))),
+            // Everything else comes from the battery
+            _ => self.battery.as_ref()?.field(name, context),
+        }
+    }
+}
+
+/// cnx widget that shows battery charge, whether mains power is connected
+/// and the active power profile in one segment
+pub struct PowerWidget {
+    attrs: Attributes,
+    render: Option<PowerRender>,
+    update_interval: Duration,
+    battery_path: Option<String>,
+}
+
+impl PowerWidget {
+    /// Creates a new [`PowerWidget`]
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<PowerRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the power state is read
+    /// when no signal has arrived
+    ///
+    /// `battery_path`: [`Option<String>`] - Battery to show, e.g.
+    /// `/sys/class/power_supply/BAT0`, `None` on systems without one
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<PowerRender>,
+        update_interval: Duration,
+        battery_path: Option<String>,
+    ) -> PowerWidget {
+        PowerWidget {
+            attrs,
+            render,
+            update_interval,
+            battery_path,
+        }
+    }
+}
+
+/// The plug while on mains power, otherwise the battery
+fn power_icon(power_info: &PowerInfo) -> &'static str {
+    let charging = power_info
+        .battery
+        .as_ref()
+        .is_some_and(|battery| matches!(battery.status, ChargeStatus::Charging));
+
+    if charging || power_info.on_ac {
+        icons::icons().charging
+    } else {
+        icons::icons().battery
+    }
+}
+
+/// Only profiles other than the default are worth an icon
+fn profile_icon(profile: PowerProfile) -> Option<&'static str> {
+    match profile {
+        PowerProfile::PowerSaver => Some(icons::icons().power_saver),
+        PowerProfile::Balanced => None,
+        PowerProfile::Performance => Some(icons::icons().performance),
+    }
+}
+
+fn default_render(power_info: &PowerInfo) -> String {
+    let mut text = icons::icon_markup(power_icon(power_info));
+
+    if let Some(battery) = &power_info.battery {
+        text += &format!(
+            " <span foreground=\"{}\">{}%</span>",
+            color::gradient(100.0 - battery.capacity as f64).to_hex(),
+            battery.capacity
+        );
+
+        if !battery.time_till_empty.is_zero() {
+            let minutes = battery.time_till_empty.as_secs() / 60;
+            text += &format!(" {}:{:02}", minutes / 60, minutes % 60);
+        }
+    }
+
+    if let Some(icon) = power_info.profile.and_then(profile_icon) {
+        text += &format!(" {}", icons::icon_markup(icon));
+    }
+
+    text
+}
+
+impl Widget for PowerWidget {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let PowerWidget {
+            attrs,
+            render,
+            update_interval,
+            battery_path,
+        } = *self;
+
+        // Signals are best effort, the interval still covers a system bus
+        // without UPower or power-profiles-daemon
+        let (changes, changed) = mpsc::channel();
+        if let Ok(connection) = Connection::system() {
+            for path in WATCHED_PATHS {
+                let _ = dbus::send_property_changes(&connection, path, changes.clone());
+            }
+        }
+        drop(changes);
+
+        let (sender, receiver) = tokio_mpsc::unbounded_channel();
+        let battery_path = battery_path.map(PathBuf::from);
+        thread::spawn(move || {
+            let mut collector = PowerCollector::new(battery_path.as_deref());
+            loop {
+                if let Ok(power_info) = collector.collect() {
+                    state::bar().set_value("power", &power_info);
+                    if sender.send(power_info).is_err() {
+                        break;
+                    }
+                }
+
+                match changed.recv_timeout(update_interval) {
+                    // A burst of changes only needs one read
+                    Ok(()) => while changed.try_recv().is_ok() {},
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(update_interval),
+                }
+            }
+        });
+
+        let widget = EventWidget::new(
+            attrs,
+            UnboundedReceiverStream::new(receiver),
+            move |power_info| match &render {
+                Some(render) => render.render(power_info, &RenderContext::current()),
+                None => default_render(&power_info),
+            },
+        )
+        .with_debounce(DEBOUNCE);
+
+        Box::new(widget).into_stream()
+    }
+}
@@ -0,0 +1,215 @@
+//! Any device under `/sys/class/power_supply`, such as a UPS or the battery
+//! of a wireless mouse, rather than only the laptop battery.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Attributes, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::battery::ChargeStatus;
+use crate::render::{Render, RenderContext};
+use crate::{color, icons, state};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+// Abstracted type to represent the render closure
+type PowerSupplyRender = Box<dyn Render<PowerSupplyInfo>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SupplyKind {
+    /// A battery powering the system itself
+    Battery,
+    Ups,
+    Mouse,
+    Keyboard,
+    Headphones,
+    /// Any other device with its own battery
+    Peripheral,
+}
+
+impl SupplyKind {
+    /// Icon for this kind of device from the current icon set
+    #[must_use]
+    pub fn icon(self) -> &'static str {
+        let icons = icons::icons();
+        match self {
+            SupplyKind::Battery => icons.battery,
+            SupplyKind::Ups => icons.ups,
+            SupplyKind::Mouse => icons.mouse,
+            SupplyKind::Keyboard => icons.keyboard,
+            SupplyKind::Headphones => icons.headphones,
+            SupplyKind::Peripheral => icons.peripheral,
+        }
+    }
+}
+
+pub struct PowerSupplyInfo {
+    /// Name of the device's directory, e.g. `hidpp_battery_0`
+    pub name: String,
+    pub model: Option<String>,
+    pub kind: SupplyKind,
+    pub status: ChargeStatus,
+    /// Charge percentage. Devices which only report a coarse level, as many
+    /// HID devices do, are mapped onto a representative percentage
+    pub capacity: Option<u64>,
+}
+
+/// Reads the device called `name`, `None` if it isn't present, e.g. because a
+/// wireless mouse is switched off
+#[must_use]
+pub fn read(name: &str) -> Option<PowerSupplyInfo> {
+    let path = Path::new(POWER_SUPPLY_DIR).join(name);
+    let read = |file: &str| {
+        fs::read_to_string(path.join(file))
+            .ok()
+            .map(|contents| contents.trim().to_string())
+    };
+
+    let supply_type = read("type")?;
+    if supply_type == "Mains" {
+        return None;
+    }
+
+    let model = read("model_name").filter(|model| !model.is_empty());
+    let lower_model = model.as_deref().unwrap_or_default().to_lowercase();
+    let kind = if supply_type == "UPS" {
+        SupplyKind::Ups
+    } else if read("scope").as_deref() != Some("Device") {
+        SupplyKind::Battery
+    } else if lower_model.contains("mouse") {
+        SupplyKind::Mouse
+    } else if lower_model.contains("keyboard") {
+        SupplyKind::Keyboard
+    } else if lower_model.contains("headset") || lower_model.contains("headphone") {
+        SupplyKind::Headphones
+    } else {
+        SupplyKind::Peripheral
+    };
+
+    let status = match read("status").as_deref() {
+        Some("Charging") => ChargeStatus::Charging,
+        Some("Discharging") => ChargeStatus::Discharging,
+        Some("Full") => ChargeStatus::Full,
+        Some("Not charging") => ChargeStatus::NotCharging,
+        _ => ChargeStatus::Unknown,
+    };
+
+    let capacity = read("capacity")
+        .and_then(|capacity| capacity.parse().ok())
+        .or_else(|| match read("capacity_level").as_deref() {
+            Some("Full") => Some(100),
+            Some("High") => Some(80),
+            Some("Normal") => Some(50),
+            Some("Low") => Some(20),
+            Some("Critical") => Some(5),
+            _ => None,
+        });
+
+    Some(PowerSupplyInfo {
+        name: name.to_string(),
+        model,
+        kind,
+        status,
+        capacity,
+    })
+}
+
+/// Names of the devices currently present, excluding mains adapters
+#[must_use]
+pub fn list() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(POWER_SUPPLY_DIR) else {
+        return vec![];
+    };
+
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| read(name).is_some())
+        .collect();
+    names.sort();
+    names
+}
+
+/// cnx widget that shows the charge of one power supply device, selected by
+/// name. Hidden while the device is absent
+pub struct PowerSupply {
+    attrs: Attributes,
+    render: Option<PowerSupplyRender>,
+    update_interval: Duration,
+    name: String,
+}
+
+impl PowerSupply {
+    /// Creates a new [`PowerSupply`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<PowerSupplyRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the device is read
+    ///
+    /// `name`: [`&str`] - Name of the device under `/sys/class/power_supply`,
+    /// as returned by [`list`]
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<PowerSupplyRender>,
+        update_interval: Duration,
+        name: &str,
+    ) -> PowerSupply {
+        PowerSupply {
+            attrs,
+            render,
+            update_interval,
+            name: name.to_string(),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let Some(supply_info) = read(&self.name) else {
+            return vec![];
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(supply_info, &RenderContext::current())
+        } else {
+            let icon = icons::icon_markup(match supply_info.status {
+                ChargeStatus::Charging => icons::icons().charging,
+                _ => supply_info.kind.icon(),
+            });
+
+            match supply_info.capacity {
+                Some(capacity) => format!(
+                    "{icon} <span foreground=\"{}\">{capacity}%</span>",
+                    color::gradient(100.0 - capacity as f64).to_hex()
+                ),
+                None => format!("{icon} ?"),
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for PowerSupply {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use cnx::text::{Attributes, Color, Padding, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::{StreamExt, StreamMap};
+
+/// Glyph drawn between widgets with different background colours. Both need
+/// a font with the Powerline symbols, such as a Nerd Font
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separator {
+    Arrow,
+    Slant,
+    Custom(char),
+}
+
+impl Separator {
+    fn glyph(self) -> char {
+        match self {
+            Separator::Arrow => '\u{e0b0}',
+            Separator::Slant => '\u{e0bc}',
+            Separator::Custom(glyph) => glyph,
+        }
+    }
+}
+
+/// Lays out a group of widgets side by side, inserting a separator wherever
+/// the background colour changes from one widget to the next. The separator
+/// is drawn in the colour of the background it leaves on the background it
+/// enters, so widgets appear to flow into each other
+pub struct Powerline {
+    widgets: Vec<Box<dyn Widget>>,
+    separator: Separator,
+    bar_background: Color,
+}
+
+impl Powerline {
+    /// Creates a new [`Powerline`] widget
+    ///
+    /// Arguments
+    ///
+    /// `separator`: [`Separator`] - Glyph drawn between widgets
+    ///
+    /// `bar_background`: [`Color`] - Background of the bar itself, used for
+    /// widgets which don't set their own background
+    #[must_use]
+    pub fn new(separator: Separator, bar_background: Color) -> Powerline {
+        Powerline {
+            widgets: vec![],
+            separator,
+            bar_background,
+        }
+    }
+
+    /// Appends `widget` to the right of the group
+    #[must_use]
+    pub fn with_widget(mut self, widget: impl Widget + 'static) -> Self {
+        self.widgets.push(Box::new(widget));
+        self
+    }
+}
+
+impl Widget for Powerline {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Powerline {
+            widgets,
+            separator,
+            bar_background,
+        } = *self;
+
+        let mut streams = StreamMap::new();
+        for (index, widget) in widgets.into_iter().enumerate() {
+            streams.insert(index, widget.into_stream()?);
+        }
+
+        let mut latest: Vec<Vec<Text>> = vec![vec![]; streams.len()];
+
+        // A widget which fails keeps showing its last text rather than
+        // ending the whole group
+        let stream = streams.map(move |(index, texts)| {
+            match texts {
+                Ok(texts) => latest[index] = texts,
+                Err(e) => eprintln!("Widget {index} of a powerline group failed: {e}"),
+            }
+            Ok(join(&latest, separator, &bar_background))
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Concatenates the text of each widget, with a separator wherever the
+/// background changes. Hidden widgets don't affect the separators
+fn join(groups: &[Vec<Text>], separator: Separator, bar_background: &Color) -> Vec<Text> {
+    let background = |text: &Text| {
+        text.attr
+            .bg_color
+            .clone()
+            .unwrap_or_else(|| bar_background.clone())
+    };
+
+    let mut joined: Vec<Text> = vec![];
+    for group in groups.iter().filter(|group| !group.is_empty()) {
+        if let (Some(previous), Some(next)) = (joined.last(), group.first()) {
+            let (from, to) = (background(previous), background(next));
+            if from.to_hex() != to.to_hex() {
+                joined.push(Text {
+                    attr: Attributes {
+                        font: next.attr.font.clone(),
+                        fg_color: from,
+                        bg_color: Some(to),
+                        padding: Padding::new(0.0, 0.0, 0.0, 0.0),
+                    },
+                    text: separator.glyph().to_string(),
+                    stretch: false,
+                    markup: false,
+                });
+            }
+        }
+        joined.extend(group.iter().cloned());
+    }
+
+    joined
+}
@@ -0,0 +1,143 @@
+//! Ready-made renders for the battery, memory and CPU widgets, picked by name
+//! with `preset` in a widget's config:
+//!
+//! ```toml
+//! [widgets.battery]
+//! preset = "nerd-font"
+//! ```
+//!
+//! `minimal` shows the bare figure, `verbose` spells everything out in text,
+//! and `nerd-font` and `emoji` show the figure after an icon from that set,
+//! whichever icon set is selected for the rest of the bar.
+
+use byte_unit::{Byte, UnitType};
+use serde::Deserialize;
+
+use crate::battery::{BatteryInfo, ChargeStatus};
+use crate::color;
+use crate::cpu::CpuInfo;
+use crate::icons::{self, IconSet, Icons};
+use crate::memory::MemoryInfo;
+use crate::render::{Render, RenderContext};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    Minimal,
+    Verbose,
+    NerdFont,
+    Emoji,
+}
+
+impl Preset {
+    /// The icons of the preset, `None` for presets without icons
+    fn icons(self) -> Option<&'static Icons> {
+        match self {
+            Preset::NerdFont => Some(icons::icons_of(IconSet::NerdFont)),
+            Preset::Emoji => Some(icons::icons_of(IconSet::Emoji)),
+            Preset::Minimal | Preset::Verbose => None,
+        }
+    }
+}
+
+/// `text` in the colour [`color::gradient`] gives `value`
+fn coloured(text: &str, value: f64) -> String {
+    format!(
+        "<span foreground=\"{}\">{text}</span>",
+        color::gradient(value).to_hex()
+    )
+}
+
+fn size(bytes: Byte) -> String {
+    format!("{:.1}", bytes.get_appropriate_unit(UnitType::Binary))
+}
+
+/// A render of [`BatteryInfo`] in the style of `preset`
+#[must_use]
+pub fn battery(preset: Preset) -> Box<dyn Render<BatteryInfo>> {
+    Box::new(move |battery_info: BatteryInfo, context: &RenderContext| {
+        let charge = coloured(
+            &format!("{}%", battery_info.capacity),
+            100.0 - battery_info.capacity as f64,
+        );
+
+        if let Some(icons) = preset.icons() {
+            let icon = match battery_info.status {
+                ChargeStatus::Charging => icons.charging,
+                _ => icons.battery,
+            };
+            return format!("{} {charge}", context.icon(icon));
+        }
+
+        match preset {
+            Preset::Verbose => {
+                let mut text = format!("Battery {charge} {:?}", battery_info.status);
+                if let Some(charger) = &battery_info.charger {
+                    text.push_str(&format!(" @ {:.0}W", charger.watts));
+                }
+                if !battery_info.time_till_empty.is_zero() {
+                    let minutes = battery_info.time_till_empty.as_secs() / 60;
+                    text.push_str(&format!(", {}h{:02}m left", minutes / 60, minutes % 60));
+                }
+                text
+            }
+            _ => charge,
+        }
+    })
+}
+
+/// A render of [`MemoryInfo`] in the style of `preset`
+#[must_use]
+pub fn memory(preset: Preset) -> Box<dyn Render<MemoryInfo>> {
+    Box::new(move |memory_info: MemoryInfo, context: &RenderContext| {
+        let usage = color::percentage(
+            memory_info.used_memory.as_u64(),
+            memory_info.total_memory.as_u64(),
+        );
+
+        if let Some(icons) = preset.icons() {
+            return format!(
+                "{} {}",
+                context.icon(icons.memory),
+                coloured(&size(memory_info.used_memory), usage)
+            );
+        }
+
+        match preset {
+            Preset::Verbose => {
+                let swap_usage = color::percentage(
+                    memory_info.used_swap.as_u64(),
+                    memory_info.total_swap.as_u64(),
+                );
+                format!(
+                    "Memory {}/{} Swap {}/{}",
+                    coloured(&size(memory_info.used_memory), usage),
+                    size(memory_info.total_memory),
+                    coloured(&size(memory_info.used_swap), swap_usage),
+                    size(memory_info.total_swap)
+                )
+            }
+            _ => coloured(&format!("{usage:.0}%"), usage),
+        }
+    })
+}
+
+/// A render of [`CpuInfo`] in the style of `preset`
+#[must_use]
+pub fn cpu(preset: Preset) -> Box<dyn Render<CpuInfo>> {
+    Box::new(move |cpu_info: CpuInfo, context: &RenderContext| {
+        let usage = coloured(&format!("{:.0}%", cpu_info.usage), cpu_info.usage);
+
+        if let Some(icons) = preset.icons() {
+            return format!("{} {usage}", context.icon(icons.cpu));
+        }
+
+        match preset {
+            Preset::Verbose => format!(
+                "CPU {usage} iowait {:.0}% steal {:.0}%",
+                cpu_info.iowait, cpu_info.steal
+            ),
+            _ => usage,
+        }
+    })
+}
@@ -0,0 +1,193 @@
+//! Writing the sysfs knobs only root can write, the charge threshold and CPU
+//! frequency scaling, through `pkexec`, which asks polkit to authorise it.
+//!
+//! The bar never writes them as root itself. It runs the `status_bar-sysfs`
+//! helper, which only knows the knobs in [`Knob`], works out their paths by
+//! itself and checks the value before writing. The polkit action in
+//! `dist/polkit/org.status_bar.sysfs.policy` names the helper, so that
+//! authorising it can't be used to write anything else.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::collectors::battery::CHARGE_LIMIT;
+use crate::governor::{governor_paths, BOOST, CPU_DIR, NO_TURBO};
+
+/// Where packages install the helper, which must match the path in the
+/// polkit action
+pub const HELPER: &str = "/usr/libexec/status_bar-sysfs";
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// A setting the helper can change
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Knob {
+    /// Scaling governor of every CPU, one the driver offers
+    Governor(String),
+    /// Turbo boost, through whichever switch the cpufreq driver has
+    Turbo(bool),
+    /// Percentage a battery stops charging at
+    ChargeLimit { battery: String, percent: u64 },
+}
+
+impl Knob {
+    /// Parses the helper's command line: `governor <name>`,
+    /// `turbo on|off` or `charge-limit <battery> <percent>`
+    pub fn parse(args: &[&str]) -> Result<Knob> {
+        match args {
+            ["governor", name] if is_name(name) => Ok(Knob::Governor(name.to_string())),
+            ["turbo", "on"] => Ok(Knob::Turbo(true)),
+            ["turbo", "off"] => Ok(Knob::Turbo(false)),
+            ["charge-limit", battery, percent] if is_name(battery) => {
+                let percent = percent
+                    .parse()
+                    .ok()
+                    .filter(|percent| (1..=100).contains(percent))
+                    .ok_or_else(|| anyhow!("charge limits are 1 to 100, not {percent}"))?;
+                Ok(Knob::ChargeLimit {
+                    battery: battery.to_string(),
+                    percent,
+                })
+            }
+            _ => bail!(
+                "usage: status_bar-sysfs governor <name> | turbo on|off | \
+                 charge-limit <battery> <percent>"
+            ),
+        }
+    }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Knob::Governor(name) => vec!["governor".to_string(), name.clone()],
+            Knob::Turbo(on) => vec![
+                "turbo".to_string(),
+                if *on { "on" } else { "off" }.to_string(),
+            ],
+            Knob::ChargeLimit { battery, percent } => vec![
+                "charge-limit".to_string(),
+                battery.clone(),
+                percent.to_string(),
+            ],
+        }
+    }
+
+    /// Writes the knob, which takes root. Only the helper calls this
+    pub fn apply(&self) -> Result<()> {
+        match self {
+            Knob::Governor(name) => {
+                let available = fs::read_to_string(
+                    Path::new(CPU_DIR).join("cpu0/cpufreq/scaling_available_governors"),
+                )?;
+                if !available
+                    .split_whitespace()
+                    .any(|governor| governor == name)
+                {
+                    bail!("the cpufreq driver has no governor {name}");
+                }
+                for path in governor_paths()? {
+                    write(&path, name)?;
+                }
+                Ok(())
+            }
+            Knob::Turbo(on) => {
+                if Path::new(NO_TURBO).exists() {
+                    write(Path::new(NO_TURBO), if *on { "0" } else { "1" })
+                } else if Path::new(BOOST).exists() {
+                    write(Path::new(BOOST), if *on { "1" } else { "0" })
+                } else {
+                    bail!("the cpufreq driver has no turbo switch")
+                }
+            }
+            Knob::ChargeLimit { battery, percent } => {
+                let path: PathBuf = [POWER_SUPPLY_DIR, battery, CHARGE_LIMIT].iter().collect();
+                if !path.exists() {
+                    bail!("{battery} has no charge threshold");
+                }
+                write(&path, &percent.to_string())
+            }
+        }
+    }
+}
+
+/// Whether `name` is a plain file name, so that it can't reach outside the
+/// directory it is joined to
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn write(path: &Path, value: &str) -> Result<()> {
+    fs::write(path, format!("{value}\n")).with_context(|| format!("Could not write {path:?}"))
+}
+
+/// Sets `knob` as root through the helper, asking polkit to authorise it
+pub fn set(knob: &Knob) -> Result<()> {
+    let status = Command::new("pkexec")
+        .arg(HELPER)
+        .args(knob.args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    if !status.success() {
+        bail!("could not set {knob:?} as root");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_knobs() {
+        assert_eq!(
+            Knob::parse(&["governor", "powersave"]).unwrap(),
+            Knob::Governor("powersave".to_string())
+        );
+        assert_eq!(Knob::parse(&["turbo", "off"]).unwrap(), Knob::Turbo(false));
+        assert_eq!(
+            Knob::parse(&["charge-limit", "BAT0", "80"]).unwrap(),
+            Knob::ChargeLimit {
+                battery: "BAT0".to_string(),
+                percent: 80
+            }
+        );
+    }
+
+    #[test]
+    fn round_trips_through_args() {
+        for knob in [
+            Knob::Governor("performance".to_string()),
+            Knob::Turbo(true),
+            Knob::ChargeLimit {
+                battery: "BAT1".to_string(),
+                percent: 60,
+            },
+        ] {
+            let args = knob.args();
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            assert_eq!(Knob::parse(&args).unwrap(), knob);
+        }
+    }
+
+    #[test]
+    fn rejects_paths_and_bad_values() {
+        for args in [
+            &["governor", "../../../etc/passwd"][..],
+            &["governor", ""],
+            &["charge-limit", "../BAT0", "80"],
+            &["charge-limit", "BAT0", "0"],
+            &["charge-limit", "BAT0", "101"],
+            &["charge-limit", "BAT0", "eighty"],
+            &["turbo", "maybe"],
+            &["tee", "/etc/shadow"],
+        ] {
+            assert!(Knob::parse(args).is_err(), "{args:?}");
+        }
+    }
+}
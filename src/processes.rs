@@ -0,0 +1,157 @@
+//! Process and thread counts from `/proc`, flagging zombie processes, which
+//! point to a parent that isn't reaping its children.
+
+use std::fs;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, markup, render};
+
+// Abstracted type to represent the render closure
+type ProcessRender = Box<dyn Render<ProcessInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub processes: u32,
+    pub threads: u32,
+    /// Names of the zombie processes
+    pub zombies: Vec<String>,
+}
+
+impl Fields for ProcessInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(context.icons.processes))),
+            "processes" => Some(Value::Number(f64::from(self.processes))),
+            "threads" => Some(Value::Number(f64::from(self.threads))),
+            "zombies" => Some(Value::Number(self.zombies.len() as f64)),
+            _ => None,
+        }
+    }
+}
+
+/// The name, state and thread count from a `/proc/<pid>/stat`, which looks
+/// like `1234 (name) S 1 ...` with the thread count as the 20th field. The
+/// name may itself contain spaces and parentheses
+pub(crate) fn parse_stat(stat: &str) -> Option<(&str, char, u32)> {
+    let (name, rest) = stat.split_once(" (")?.1.rsplit_once(") ")?;
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let threads = fields.nth(16)?.parse().ok()?;
+    Some((name, state, threads))
+}
+
+fn read() -> Result<ProcessInfo> {
+    let mut process_info = ProcessInfo {
+        processes: 0,
+        threads: 0,
+        zombies: vec![],
+    };
+
+    for entry in fs::read_dir("/proc")?.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|byte| byte.is_ascii_digit())
+        {
+            continue;
+        }
+        // Processes exit between listing and reading
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        let Some((name, state, threads)) = parse_stat(&stat) else {
+            continue;
+        };
+
+        process_info.processes += 1;
+        process_info.threads += threads;
+        if state == 'Z' {
+            process_info.zombies.push(name.to_string());
+        }
+    }
+
+    Ok(process_info)
+}
+
+/// cnx widget that counts processes and threads, showing any zombies in the
+/// theme's critical colour
+pub struct Processes {
+    attrs: Attributes,
+    render: Option<ProcessRender>,
+    update_interval: Duration,
+}
+
+impl Processes {
+    /// Creates a new [`Processes`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<ProcessRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often `/proc` is read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<ProcessRender>,
+        update_interval: Duration,
+    ) -> Processes {
+        Processes {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+}
+
+fn default_render(process_info: &ProcessInfo) -> String {
+    let mut text = format!(
+        "{} {}/{}",
+        icons::icon_markup(icons::icons().processes),
+        process_info.processes,
+        process_info.threads
+    );
+
+    if let Some(first) = process_info.zombies.first() {
+        text.push_str(&format!(
+            " <span foreground=\"{}\">{} zombie ({})</span>",
+            render::theme().critical.to_hex(),
+            process_info.zombies.len(),
+            markup::escape(first)
+        ));
+    }
+    text
+}
+
+impl Widget for Processes {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Processes {
+            attrs,
+            render,
+            update_interval,
+        } = *self;
+
+        let show = move |process_info: ProcessInfo| match &render {
+            Some(render) => render.render(process_info, &RenderContext::current()),
+            None => default_render(&process_info),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, read, show).with_export("processes");
+        Box::new(widget).into_stream()
+    }
+}
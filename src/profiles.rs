@@ -0,0 +1,86 @@
+//! Named sets of overrides in one config file, such as a `presentation`
+//! profile which hides chat widgets and enlarges the clock:
+//!
+//! ```toml
+//! profile = "work"
+//!
+//! [profiles.presentation]
+//! hide = ["custom", "cpu", "memory"]
+//!
+//! [profiles.presentation.widgets.clock.font]
+//! size = 16
+//! ```
+//!
+//! The `profile` IPC command, or `status_bar profile` from a shell, lists the
+//! profiles, and `profile <name>` switches to one by restarting the bar in
+//! place. The choice is kept across restarts until `profile reset` goes back
+//! to the one in the config.
+
+use anyhow::{bail, Result};
+
+use crate::config::Config;
+use crate::{ipc, paths, signals};
+
+/// State file holding the profile picked over IPC
+const STATE: &str = "profile";
+
+/// Command name on the CLI and over IPC
+pub const COMMAND: &str = "profile";
+
+/// The profile picked over IPC, or else the one set in `config`
+#[must_use]
+pub fn active(config: &Config) -> Option<String> {
+    match paths::read_state(STATE) {
+        Ok(profile) if !profile.trim().is_empty() => Some(profile.trim().to_string()),
+        _ => config.profile.clone(),
+    }
+}
+
+/// Registers the `profile` IPC command, switching between the profiles in
+/// `config`
+pub fn register_command(config: &Config) {
+    let names: Vec<String> = config.profiles.keys().cloned().collect();
+    let active = config.active_profile().map(str::to_string);
+
+    ipc::register(COMMAND, move |args| match args {
+        [] => Ok(names
+            .iter()
+            .map(|name| {
+                let marker = if active.as_ref() == Some(name) {
+                    '*'
+                } else {
+                    ' '
+                };
+                format!("{marker} {name}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ["reset"] => {
+            paths::write_state(STATE, "")?;
+            signals::reload()
+        }
+        [name] => {
+            if !names.iter().any(|profile| profile == name) {
+                bail!("no profile called {name}");
+            }
+            paths::write_state(STATE, name)?;
+            signals::reload()
+        }
+        _ => bail!("usage: profile [<name>|reset]"),
+    });
+}
+
+/// Sends `args` to the running bar as a `profile` command and prints the reply
+pub fn run(args: &[String]) -> Result<()> {
+    let mut command = COMMAND.to_string();
+    for arg in args {
+        command.push(' ');
+        command.push_str(arg);
+    }
+
+    let reply = ipc::send(&command)?;
+    if !reply.is_empty() {
+        println!("{reply}");
+    }
+    Ok(())
+}
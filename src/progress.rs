@@ -0,0 +1,148 @@
+//! Textual progress bars and sparklines for use in renders, e.g. battery
+//! charge or volume.
+
+use cnx::text::Color;
+
+/// Eighth-block characters used to draw a partially filled cell
+const EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+pub struct ProgressBar {
+    /// Width of the bar in characters
+    pub width: usize,
+    pub fill: char,
+    pub empty: char,
+    /// Whether the boundary cell is drawn with eighth blocks for finer
+    /// resolution. Only looks right when `fill` is a full block
+    pub partial_cells: bool,
+}
+
+impl ProgressBar {
+    /// A bar drawn with Unicode block characters, e.g. `███▌░░░░`
+    #[must_use]
+    pub const fn blocks(width: usize) -> ProgressBar {
+        ProgressBar {
+            width,
+            fill: '█',
+            empty: '░',
+            partial_cells: true,
+        }
+    }
+
+    /// A bar which renders with any font, e.g. `####----`
+    #[must_use]
+    pub const fn ascii(width: usize) -> ProgressBar {
+        ProgressBar {
+            width,
+            fill: '#',
+            empty: '-',
+            partial_cells: false,
+        }
+    }
+
+    /// Splits the bar into its filled and empty parts
+    fn parts(&self, percentage: f64) -> (String, String) {
+        let percentage = if percentage.is_nan() {
+            0.0
+        } else {
+            percentage.clamp(0.0, 100.0)
+        };
+
+        let cells = percentage / 100.0 * self.width as f64;
+        let full = cells.floor() as usize;
+
+        let mut filled: String = std::iter::repeat_n(self.fill, full).collect();
+
+        if self.partial_cells && full < self.width {
+            let eighths = ((cells - full as f64) * 8.0) as usize;
+            if eighths > 0 {
+                filled.push(EIGHTHS[eighths - 1]);
+            }
+        }
+
+        let remaining = self.width - filled.chars().count();
+        let empty = std::iter::repeat_n(self.empty, remaining).collect();
+
+        (filled, empty)
+    }
+
+    /// Renders the bar as plain text
+    #[must_use]
+    pub fn render(&self, percentage: f64) -> String {
+        let (filled, empty) = self.parts(percentage);
+        filled + &empty
+    }
+
+    /// Renders the bar as Pango markup, colouring the filled and empty parts
+    #[must_use]
+    pub fn render_markup(
+        &self,
+        percentage: f64,
+        fill_colour: &Color,
+        empty_colour: &Color,
+    ) -> String {
+        let (filled, empty) = self.parts(percentage);
+
+        format!(
+            "<span foreground=\"{}\">{filled}</span><span foreground=\"{}\">{empty}</span>",
+            fill_colour.to_hex(),
+            empty_colour.to_hex()
+        )
+    }
+}
+
+/// Block characters of increasing height used by [`sparkline`]
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Renders `values` as a sparkline, one character per value, scaled so that
+/// `min` is the lowest level and `max` the highest
+#[must_use]
+pub fn sparkline(values: &[f64], min: f64, max: f64) -> String {
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|value| {
+            let level = if range > 0.0 {
+                ((value - min) / range * (LEVELS.len() - 1) as f64).round()
+            } else {
+                0.0
+            };
+            LEVELS[(level.max(0.0) as usize).min(LEVELS.len() - 1)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_keep_their_width() {
+        let bar = ProgressBar::blocks(8);
+        for percentage in [0.0, 12.5, 37.0, 99.9, 100.0] {
+            assert_eq!(bar.render(percentage).chars().count(), 8, "{percentage}");
+        }
+        assert_eq!(bar.render(0.0), "░░░░░░░░");
+        assert_eq!(bar.render(100.0), "████████");
+    }
+
+    #[test]
+    fn boundary_cell_is_drawn_in_eighths() {
+        assert_eq!(ProgressBar::blocks(8).render(43.75), "███▌░░░░");
+        assert_eq!(ProgressBar::ascii(8).render(43.75), "###-----");
+    }
+
+    #[test]
+    fn percentages_out_of_range_are_clamped() {
+        let bar = ProgressBar::ascii(4);
+        assert_eq!(bar.render(-20.0), "----");
+        assert_eq!(bar.render(150.0), "####");
+        assert_eq!(bar.render(f64::NAN), "----");
+    }
+
+    #[test]
+    fn sparkline_scales_between_bounds() {
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0, 200.0], 0.0, 100.0), "▁▅██");
+        assert_eq!(sparkline(&[3.0, 3.0], 3.0, 3.0), "▁▁");
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use serde_json::Value;
+use tokio_stream::StreamExt;
+
+use crate::cache::{self, Cache};
+use crate::render::{Render, RenderContext};
+use crate::{http, markup, net, state};
+
+// Abstracted type to represent the render closure
+type PrometheusRender = Box<dyn Render<PrometheusInfo>>;
+
+pub struct PrometheusInfo {
+    pub label: String,
+    /// Result of the query, `None` if it failed or returned no samples
+    pub value: Option<f64>,
+    /// Whether `value` is out of date because the server can't be reached
+    pub stale: bool,
+}
+
+/// Values at which the default render changes colour. If `critical` is lower
+/// than `warning`, lower values are treated as worse
+pub struct Thresholds {
+    pub warning: f64,
+    pub critical: f64,
+}
+
+impl Thresholds {
+    #[must_use]
+    pub fn colour(&self, value: f64) -> Color {
+        let (value, warning, critical) = if self.critical >= self.warning {
+            (value, self.warning, self.critical)
+        } else {
+            (-value, -self.warning, -self.critical)
+        };
+
+        if value >= critical {
+            Color::red()
+        } else if value >= warning {
+            Color::yellow()
+        } else {
+            Color::green()
+        }
+    }
+}
+
+/// cnx widget that evaluates a PromQL instant query and shows the result
+pub struct Prometheus {
+    attrs: Attributes,
+    render: Option<PrometheusRender>,
+    update_interval: Duration,
+    server: String,
+    label: String,
+    query: String,
+    thresholds: Option<Thresholds>,
+    agent: ureq::Agent,
+    cache: Cache<f64>,
+}
+
+impl Prometheus {
+    /// Creates a new [`Prometheus`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<PrometheusRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the query is evaluated
+    ///
+    /// `server`: [`String`] - Base URL of the Prometheus server, e.g.
+    /// `http://localhost:9090`
+    ///
+    /// `label`: [`String`] - Name shown alongside the value
+    ///
+    /// `query`: [`String`] - PromQL query, which should evaluate to a scalar
+    /// or a single-element vector
+    ///
+    /// `thresholds`: [`Option<Thresholds>`] - Optional colouring for the
+    /// default render
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<PrometheusRender>,
+        update_interval: Duration,
+        server: String,
+        label: String,
+        query: String,
+        thresholds: Option<Thresholds>,
+    ) -> Prometheus {
+        Prometheus {
+            attrs,
+            render,
+            update_interval,
+            server: server.trim_end_matches('/').to_string(),
+            label,
+            query,
+            thresholds,
+            agent: http::agent(Duration::from_secs(10)),
+            cache: Cache::new(update_interval, update_interval * 3),
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let (agent, server, query) = (self.agent.clone(), self.server.clone(), self.query.clone());
+        let cached = self
+            .cache
+            .get(move || net::request(&server, || evaluate(&agent, &server, &query)));
+
+        let prometheus_info = PrometheusInfo {
+            label: self.label.clone(),
+            value: cached.as_ref().map(|cached| cached.value),
+            stale: cached.is_some_and(|cached| cached.stale),
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(prometheus_info, &RenderContext::current())
+        } else {
+            let label = markup::escape(&prometheus_info.label);
+            let text = match (prometheus_info.value, &self.thresholds) {
+                (Some(value), Some(thresholds)) => format!(
+                    "{label} <span foreground=\"{}\">{value:.2}</span>",
+                    thresholds.colour(value).to_hex()
+                ),
+                (Some(value), None) => format!("{label} {value:.2}"),
+                (None, _) => format!("{label} ?"),
+            };
+            if prometheus_info.stale {
+                cache::mark_stale(&text)
+            } else {
+                text
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Prometheus {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.cache.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+fn evaluate(agent: &ureq::Agent, server: &str, query: &str) -> Result<f64> {
+    let body = agent
+        .get(&format!("{server}/api/v1/query"))
+        .query("query", query)
+        .call()?
+        .into_string()?;
+
+    let json: Value = serde_json::from_str(&body)?;
+    let result = &json["data"]["result"];
+
+    // Samples are `[timestamp, "value"]` pairs
+    let sample = match json["data"]["resultType"].as_str() {
+        Some("scalar") => &result[1],
+        Some("vector") => &result[0]["value"][1],
+        other => bail!("Unsupported result type {other:?}"),
+    };
+
+    sample
+        .as_str()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| anyhow!("Query returned no samples"))
+}
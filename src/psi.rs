@@ -0,0 +1,58 @@
+//! Pressure stall information (PSI), read from `/proc/pressure`.
+//!
+//! PSI reports the share of wall time in which tasks were stalled waiting on
+//! a resource, which exposes contention that utilisation figures hide.
+
+use std::fs;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Stall percentages averaged over the last 10 and 60 seconds. `some` counts
+/// time where at least one task was stalled, `full` time where all non-idle
+/// tasks were
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pressure {
+    pub some_avg10: f64,
+    pub some_avg60: f64,
+    pub full_avg10: f64,
+    pub full_avg60: f64,
+}
+
+/// Reads the pressure of `resource`, one of `cpu`, `memory` or `io`. Fails on
+/// kernels built without PSI support
+pub fn read(resource: &str) -> Result<Pressure> {
+    let contents = fs::read_to_string(format!("/proc/pressure/{resource}"))?;
+    Ok(parse(&contents))
+}
+
+/// Parses the contents of a `/proc/pressure` file, which look like
+/// `some avg10=0.00 avg60=0.00 avg300=0.00 total=0`
+#[must_use]
+pub fn parse(contents: &str) -> Pressure {
+    let mut pressure = Pressure::default();
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+
+        for field in fields {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            let Ok(value) = value.parse() else {
+                continue;
+            };
+
+            match (kind, key) {
+                (Some("some"), "avg10") => pressure.some_avg10 = value,
+                (Some("some"), "avg60") => pressure.some_avg60 = value,
+                (Some("full"), "avg10") => pressure.full_avg10 = value,
+                (Some("full"), "avg60") => pressure.full_avg60 = value,
+                _ => {}
+            }
+        }
+    }
+
+    pressure
+}
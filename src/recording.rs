@@ -0,0 +1,207 @@
+//! Screen recorder managed by the bar, started and stopped through the
+//! `recording` IPC command.
+
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::render::{Render, RenderContext};
+use crate::{ipc, state};
+
+// Abstracted type to represent the render closure
+type RecordingRender = Box<dyn Render<RecordingInfo>>;
+
+pub struct RecordingInfo {
+    /// Time since the recording started
+    pub elapsed: Duration,
+    pub output: PathBuf,
+}
+
+struct Recorder {
+    child: Child,
+    started: Instant,
+    output: PathBuf,
+}
+
+impl Recorder {
+    /// Asks the recorder to finish writing and waits for it to exit
+    fn stop(mut self) -> Result<()> {
+        // Both ffmpeg and wf-recorder finalise the file on SIGINT. The child
+        // hasn't been reaped, so its pid can't have been reused
+        // SAFETY: kill only sends a signal
+        if unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGINT) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+/// cnx widget that shows the elapsed time of a screen recording run by the
+/// bar. Hidden while not recording
+pub struct Recording {
+    attrs: Attributes,
+    render: Option<RecordingRender>,
+    recorder: Arc<Mutex<Option<Recorder>>>,
+}
+
+impl Recording {
+    /// Creates a new [`Recording`] widget and registers the `recording` IPC
+    /// command, which takes `toggle`, `start` or `stop`
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<RecordingRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `command`: [`Vec<String>`] - Recorder command line, e.g.
+    /// `ffmpeg -f x11grab -i :0`. The output file is appended to it
+    ///
+    /// `directory`: [`PathBuf`] - Directory recordings are saved in
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<RecordingRender>,
+        command: Vec<String>,
+        directory: PathBuf,
+    ) -> Recording {
+        let recorder: Arc<Mutex<Option<Recorder>>> = Arc::new(Mutex::new(None));
+
+        let command_recorder = Arc::clone(&recorder);
+        ipc::register("recording", move |args| {
+            let mut recorder = command_recorder.lock().unwrap();
+
+            // Forget a recorder which exited by itself
+            if let Some(running) = recorder.as_mut() {
+                if running.child.try_wait()?.is_some() {
+                    *recorder = None;
+                }
+            }
+
+            let start = match args {
+                ["toggle"] | [] => recorder.is_none(),
+                ["start"] => true,
+                ["stop"] => false,
+                _ => bail!("usage: recording [toggle|start|stop]"),
+            };
+
+            if !start {
+                // Stopping waits for the recorder to finish its file, so it
+                // happens without the lock to not hold up the widget
+                let stopping = recorder.take();
+                drop(recorder);
+                let reply = match stopping {
+                    Some(running) => {
+                        let output = running.output.display().to_string();
+                        running.stop()?;
+                        output
+                    }
+                    None => String::new(),
+                };
+                state::bar().request_refresh();
+                return Ok(reply);
+            }
+
+            let reply = if recorder.is_none() {
+                let Some((program, args)) = command.split_first() else {
+                    bail!("no recorder command configured");
+                };
+                let output = directory.join(format!(
+                    "recording-{}.mp4",
+                    chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+                ));
+
+                let child = Command::new(program)
+                    .args(args)
+                    .arg(&output)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                *recorder = Some(Recorder {
+                    child,
+                    started: Instant::now(),
+                    output: output.clone(),
+                });
+                output.display().to_string()
+            } else {
+                String::new()
+            };
+
+            state::bar().request_refresh();
+            Ok(reply)
+        });
+
+        // Don't leave a recorder running, or its file unfinished, after exit
+        let shutdown_recorder = Arc::clone(&recorder);
+        state::bar().on_shutdown(move || {
+            let stopping = shutdown_recorder.lock().unwrap().take();
+            if let Some(running) = stopping {
+                let _ = running.stop();
+            }
+        });
+
+        Recording {
+            attrs,
+            render,
+            recorder,
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let mut recorder = self.recorder.lock().unwrap();
+        if let Some(running) = recorder.as_mut() {
+            if !matches!(running.child.try_wait(), Ok(None)) {
+                *recorder = None;
+            }
+        }
+        let Some(running) = recorder.as_ref() else {
+            return vec![];
+        };
+
+        let recording_info = RecordingInfo {
+            elapsed: running.started.elapsed(),
+            output: running.output.clone(),
+        };
+        drop(recorder);
+
+        let text = if let Some(render) = &self.render {
+            render.render(recording_info, &RenderContext::current())
+        } else {
+            let seconds = recording_info.elapsed.as_secs();
+            format!(
+                "<span foreground=\"{}\">●</span> {:02}:{:02}",
+                Color::red().to_hex(),
+                seconds / 60,
+                seconds % 60
+            )
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Recording {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(Duration::from_secs(1)).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
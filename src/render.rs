@@ -0,0 +1,96 @@
+//! Context handed to render closures alongside each widget's data, so custom
+//! renders can follow the bar's theme and icon set instead of hardcoding
+//! colours and glyphs.
+//!
+//! ```ignore
+//! let render = Box::new(|cpu_info: CpuInfo, context: &RenderContext| {
+//!     format!(
+//!         "{icon} <span foreground=\"{colour}\">{usage:.0}%</span>",
+//!         icon = context.icon(context.icons.cpu),
+//!         colour = context.theme.warning.to_hex(),
+//!         usage = cpu_info.usage,
+//!     )
+//! });
+//! ```
+
+use std::sync::{Arc, LazyLock, RwLock};
+
+use cnx::text::Color;
+
+use crate::icons::{self, Icons};
+
+static THEME: LazyLock<RwLock<Arc<Theme>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Theme::default())));
+
+/// Colours shared by the renders of every widget
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Theme {
+    pub foreground: Color,
+    /// Colour for decoration such as brackets and separators
+    pub muted: Color,
+    pub good: Color,
+    pub warning: Color,
+    pub critical: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            foreground: Color::white(),
+            muted: Color::from_rgb(128, 128, 128),
+            good: Color::green(),
+            warning: Color::yellow(),
+            critical: Color::red(),
+        }
+    }
+}
+
+/// Replaces the theme used by renders from their next tick
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap() = Arc::new(theme);
+}
+
+#[must_use]
+pub fn theme() -> Arc<Theme> {
+    Arc::clone(&THEME.read().unwrap())
+}
+
+/// What a render gets to know about the bar besides the widget's data
+pub struct RenderContext {
+    pub theme: Arc<Theme>,
+    /// Icons of the selected [`icons::IconSet`]
+    pub icons: &'static Icons,
+}
+
+impl RenderContext {
+    /// Context with the theme and icon set currently in use
+    #[must_use]
+    pub fn current() -> RenderContext {
+        RenderContext {
+            theme: theme(),
+            icons: icons::icons(),
+        }
+    }
+
+    /// Pango markup showing `icon` in the configured icon font
+    #[must_use]
+    pub fn icon(&self, icon: &str) -> String {
+        icons::icon_markup(icon)
+    }
+}
+
+/// Turns a widget's data into the Pango markup it displays. Implemented for
+/// any `Fn(T, &RenderContext) -> String` closure
+pub trait Render<T> {
+    fn render(&self, data: T, context: &RenderContext) -> String;
+}
+
+impl<T, F> Render<T> for F
+where
+    F: Fn(T, &RenderContext) -> String,
+{
+    fn render(&self, data: T, context: &RenderContext) -> String {
+        self(data, context)
+    }
+}
@@ -0,0 +1,66 @@
+//! The pieces needed to write widgets in another crate, gathered in one
+//! place with a stability promise the rest of the library doesn't make.
+//!
+//! Everything re-exported here follows semver: while the crate is at 0.x,
+//! only a minor version bump may change or remove it, and patch releases only
+//! add to it. Import it through `sdk`, as the modules it comes from may be
+//! renamed or split up in any release. [`Icons`] and [`Theme`] gain fields
+//! as widgets need them, so read them rather than building them.
+//!
+//! A widget is usually a [`PollingWidget`], reading something on an interval
+//! and rendering it with the bar's [`Theme`] and [`Icons`]:
+//!
+//! ```ignore
+//! use status_bar::sdk::{self, Attributes, PollingWidget, RenderContext};
+//!
+//! let uptime = PollingWidget::new(
+//!     attrs,
+//!     Duration::from_secs(60),
+//!     || Ok(fs::read_to_string("/proc/uptime")?),
+//!     |uptime| {
+//!         let context = RenderContext::current();
+//!         format!(
+//!             "<span foreground=\"{}\">up</span> {}",
+//!             context.theme.muted.to_hex(),
+//!             sdk::escape(uptime.split('.').next().unwrap_or_default())
+//!         )
+//!     },
+//! )
+//! .with_export("uptime");
+//! ```
+//!
+//! cnx doesn't deliver clicks, so widgets take commands over IPC instead,
+//! registered with [`register_command`] and sent with `status_bar` or
+//! `socat`. Handlers run on their own thread, so state they share with the
+//! widget goes behind an `Arc<Mutex<_>>`, and they call [`refresh`] to have
+//! the change shown straight away.
+
+use anyhow::Result;
+
+pub use cnx::text::{Attributes, Color, Font, Padding, Text};
+pub use cnx::widgets::{Widget, WidgetStream};
+
+pub use crate::event::EventWidget;
+pub use crate::icons::{icon_markup, icons, IconSet, Icons};
+pub use crate::markup::escape;
+pub use crate::polling::PollingWidget;
+pub use crate::render::{theme, Render, RenderContext, Theme};
+pub use crate::state::{ticks, Tracked};
+pub use crate::template::{Fields, Template, Value};
+pub use crate::urgent::{Blink, Urgency};
+
+/// Makes `handler` answer the IPC command `name`, see [`crate::ipc`]. It is
+/// called with the words following the name, and its output is sent back to
+/// the client
+pub fn register_command(
+    name: &str,
+    handler: impl Fn(&[&str]) -> Result<String> + Send + Sync + 'static,
+) {
+    crate::ipc::register(name, handler);
+}
+
+/// Makes every widget driven by [`ticks`], including every [`PollingWidget`],
+/// update immediately
+pub fn refresh() {
+    crate::state::bar().request_refresh();
+}
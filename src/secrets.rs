@@ -0,0 +1,132 @@
+//! Tokens and API keys for networked widgets, fetched from somewhere other
+//! than the config file so they never sit in it as plain text:
+//!
+//! ```toml
+//! token = { command = "pass show home-assistant" }
+//! # token = { env = "HASS_TOKEN" }
+//! # token = { keyring = { service = "home-assistant", account = "me" } }
+//! ```
+//!
+//! `command` takes the first line a shell command prints, as `pass show`
+//! prints the password first. `keyring` looks the secret up by its
+//! attributes in the Secret Service, through libsecret's `secret-tool`.
+//!
+//! A secret is fetched the first time it is needed and kept from then on.
+//! Widgets configured in `config.toml` take theirs from
+//! [`Config::token`](crate::config::Config::token).
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+/// Where a [`Secret`] comes from
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+enum Source {
+    /// Shell command printing the secret
+    Command(String),
+    /// Environment variable holding the secret
+    Env(String),
+    /// Attributes of the secret in the Secret Service
+    Keyring(BTreeMap<String, String>),
+    /// The secret itself, given in code
+    #[serde(skip)]
+    Value(String),
+}
+
+impl Source {
+    fn fetch(&self) -> Result<String> {
+        let secret = match self {
+            Source::Command(command) => {
+                let output = Command::new("sh")
+                    .args(["-c", command])
+                    .output()
+                    .with_context(|| format!("Could not run `{command}`"))?;
+                if !output.status.success() {
+                    bail!("`{command}` exited with {}", output.status);
+                }
+                String::from_utf8(output.stdout)?
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            }
+            Source::Env(name) => env::var(name).map_err(|e| anyhow!("${name}: {e}"))?,
+            Source::Keyring(attributes) => {
+                let output = Command::new("secret-tool")
+                    .arg("lookup")
+                    .args(attributes.iter().flat_map(|(key, value)| [key, value]))
+                    .output()
+                    .context("Could not run secret-tool")?;
+                if !output.status.success() {
+                    bail!("no secret in the keyring matching {attributes:?}");
+                }
+                String::from_utf8(output.stdout)?
+            }
+            Source::Value(value) => value.clone(),
+        };
+
+        if secret.is_empty() {
+            bail!("the secret from {self:?} is empty");
+        }
+        Ok(secret)
+    }
+}
+
+/// A token or key, see the [module documentation](self). Clones share the
+/// fetched value
+#[derive(Clone, Deserialize)]
+#[serde(from = "Source")]
+pub struct Secret {
+    source: Source,
+    value: Arc<OnceLock<String>>,
+}
+
+impl Secret {
+    /// The secret, fetching it if this is the first time it is needed. This
+    /// may block on a command, such as `pass` asking for a passphrase
+    pub fn resolve(&self) -> Result<String> {
+        if let Some(value) = self.value.get() {
+            return Ok(value.clone());
+        }
+        let value = self.source.fetch()?;
+        Ok(self.value.get_or_init(|| value).clone())
+    }
+}
+
+impl From<Source> for Secret {
+    fn from(source: Source) -> Secret {
+        Secret {
+            source,
+            value: Arc::new(OnceLock::new()),
+        }
+    }
+}
+
+/// A secret given in code rather than fetched
+impl From<String> for Secret {
+    fn from(value: String) -> Secret {
+        Secret::from(Source::Value(value))
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Secret {
+        Secret::from(value.to_string())
+    }
+}
+
+/// Shows where the secret comes from, never the secret itself
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Source::Value(_) => f.write_str("Secret(..)"),
+            source => write!(f, "Secret({source:?})"),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Unix signal handling.
+//!
+//! `SIGTERM` and `SIGINT` run the shutdown hooks before exiting, `SIGUSR1`
+//! refreshes every widget and `SIGUSR2` restarts the bar in place to reload
+//! its configuration.
+
+use std::env;
+use std::os::unix::process::CommandExt;
+use std::process::{self, Command};
+use std::thread;
+
+use anyhow::Result;
+use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+
+use crate::state;
+
+/// Starts handling signals on a background thread
+pub fn listen() -> Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGUSR1, SIGUSR2])?;
+
+    thread::spawn(move || {
+        for signal in &mut signals {
+            match signal {
+                SIGUSR1 => state::bar().request_refresh(),
+                SIGUSR2 => reload(),
+                _ => {
+                    state::bar().shutdown();
+                    process::exit(0);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Replaces the running bar with a fresh instance of itself, which reads the
+/// configuration again
+pub fn reload() -> ! {
+    state::bar().shutdown();
+
+    let error = match env::current_exe() {
+        Ok(executable) => Command::new(executable).args(env::args_os().skip(1)).exec(),
+        Err(e) => e,
+    };
+
+    // The shutdown hooks have already run, so carrying on would leave state
+    // unsaved from here on
+    eprintln!("Could not restart the bar: {error}");
+    process::exit(1);
+}
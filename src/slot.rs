@@ -0,0 +1,40 @@
+use anyhow::Result;
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::wrappers::WatchStream;
+
+use crate::event::EventWidget;
+use crate::state;
+
+/// cnx widget whose Pango markup is set from outside the bar, through
+/// [`state::BarState::set_slot`]. Hidden while its text is empty
+pub struct Slot {
+    attrs: Attributes,
+    name: String,
+}
+
+impl Slot {
+    /// Creates a new [`Slot`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `name`: [`&str`] - Name used to address the slot
+    #[must_use]
+    pub fn new(attrs: Attributes, name: &str) -> Slot {
+        Slot {
+            attrs,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Widget for Slot {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let updates = WatchStream::new(state::bar().subscribe_slot(&self.name));
+
+        Box::new(EventWidget::new(self.attrs, updates, |text| text)).into_stream()
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::render::{Render, RenderContext};
+use crate::{icons, state};
+
+/// Message numbers from the ssh-agent protocol (draft-miller-ssh-agent)
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+
+// Abstracted type to represent the render closure
+type SshAgentRender = Box<dyn Render<SshAgentInfo>>;
+
+pub struct SshAgentInfo {
+    /// Number of identities loaded in the agent, or `None` if no agent could
+    /// be reached through `SSH_AUTH_SOCK`
+    pub identities: Option<u32>,
+}
+
+/// cnx widget that shows how many identities are loaded in ssh-agent
+pub struct SshAgent {
+    attrs: Attributes,
+    render: Option<SshAgentRender>,
+    update_interval: Duration,
+}
+
+impl SshAgent {
+    /// Creates a new [`SshAgent`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<SshAgentRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the agent is queried
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<SshAgentRender>,
+        update_interval: Duration,
+    ) -> SshAgent {
+        SshAgent {
+            attrs,
+            render,
+            update_interval,
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let agent_info = SshAgentInfo {
+            identities: std::env::var("SSH_AUTH_SOCK")
+                .ok()
+                .and_then(|socket| count_identities(&socket).ok()),
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(agent_info, &RenderContext::current())
+        } else {
+            let icon = icons::icon_markup(icons::icons().ssh_key);
+            match agent_info.identities {
+                Some(0) => format!(
+                    "{icon} <span foreground=\"{}\">0</span>",
+                    Color::red().to_hex()
+                ),
+                Some(count) => format!("{icon} {count}"),
+                None => format!(
+                    "{icon} <span foreground=\"{}\">no agent</span>",
+                    Color::red().to_hex()
+                ),
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for SshAgent {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval).map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Asks the agent listening on `socket` how many identities it holds
+fn count_identities(socket: &str) -> Result<u32> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    // Messages are a big-endian u32 length followed by the message type
+    stream.write_all(&[0, 0, 0, 1, SSH_AGENTC_REQUEST_IDENTITIES])?;
+
+    // Only the length, type and key count are needed, the key blobs that
+    // follow are ignored
+    let mut header = [0u8; 9];
+    stream.read_exact(&mut header)?;
+
+    if header[4] != SSH_AGENT_IDENTITIES_ANSWER {
+        bail!("Unexpected ssh-agent response type {}", header[4]);
+    }
+
+    Ok(u32::from_be_bytes([
+        header[5], header[6], header[7], header[8],
+    ]))
+}
@@ -0,0 +1,342 @@
+//! State shared between widgets and the services that drive the bar from the
+//! outside, such as the D-Bus interface.
+
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use cnx::text::Text;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::time;
+use tokio_stream::wrappers::{IntervalStream, WatchStream};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::markup;
+
+static STATE: OnceLock<BarState> = OnceLock::new();
+
+/// Intervals a [`Tracked`] widget may go without drawing before it counts as
+/// stale, unless configured otherwise
+pub const STALE_INTERVALS: u32 = 3;
+
+/// Opacity of the text of stale widgets
+const STALE_ALPHA: &str = "50%";
+
+pub struct BarState {
+    contents: Mutex<BTreeMap<String, String>>,
+    updates: Mutex<BTreeMap<String, Updates>>,
+    values: Mutex<BTreeMap<String, serde_json::Value>>,
+    slots: Mutex<HashMap<String, watch::Sender<String>>>,
+    refresh: watch::Sender<u64>,
+    paused: AtomicBool,
+    shutdown_hooks: Mutex<Vec<ShutdownHook>>,
+}
+
+type ShutdownHook = Box<dyn FnOnce() + Send>;
+
+/// When a [`Tracked`] widget last drew, and how long it may go without
+struct Updates {
+    /// Time of the last update, or of tracking starting before the first
+    last: Instant,
+    stale_after: Option<Duration>,
+}
+
+/// A widget which hasn't drawn for longer than expected, see
+/// [`BarState::stale`]
+#[derive(Clone, Debug)]
+pub struct Stale {
+    pub name: String,
+    /// Time since the widget last drew
+    pub age: Duration,
+}
+
+/// Returns the state of the running bar
+pub fn bar() -> &'static BarState {
+    STATE.get_or_init(|| BarState {
+        contents: Mutex::new(BTreeMap::new()),
+        updates: Mutex::new(BTreeMap::new()),
+        values: Mutex::new(BTreeMap::new()),
+        slots: Mutex::new(HashMap::new()),
+        refresh: watch::channel(0).0,
+        paused: AtomicBool::new(false),
+        shutdown_hooks: Mutex::new(Vec::new()),
+    })
+}
+
+impl BarState {
+    /// Makes every widget driven by [`ticks`] update immediately
+    pub fn request_refresh(&self) {
+        self.refresh.send_modify(|requests| *requests += 1);
+    }
+
+    pub fn refresh_requests(&self) -> watch::Receiver<u64> {
+        self.refresh.subscribe()
+    }
+
+    /// Stops or restarts [`ticks`], e.g. while nobody can see the bar.
+    /// Widgets refresh straight away once unpaused
+    pub fn set_paused(&self, paused: bool) {
+        let was_paused = self.paused.swap(paused, Ordering::Relaxed);
+        if was_paused && !paused {
+            // Time spent paused doesn't make widgets stale
+            let now = Instant::now();
+            for updates in self.updates.lock().unwrap().values_mut() {
+                updates.last = now;
+            }
+            self.request_refresh();
+        }
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Records the text last rendered by the widget called `name`
+    pub fn set_content(&self, name: &str, content: String) {
+        self.contents
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), content);
+        self.updates
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert(Updates {
+                last: Instant::now(),
+                stale_after: None,
+            })
+            .last = Instant::now();
+    }
+
+    /// Records that the widget called `name` counts as stale once it hasn't
+    /// drawn for `stale_after`, see [`BarState::stale`]
+    pub fn expect_updates(&self, name: &str, stale_after: Duration) {
+        self.updates
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert(Updates {
+                last: Instant::now(),
+                stale_after: None,
+            })
+            .stale_after = Some(stale_after);
+    }
+
+    /// Widgets which haven't drawn for longer than they may, the longest
+    /// overdue first. Always empty while the bar is paused, as nothing draws
+    /// then
+    pub fn stale(&self) -> Vec<Stale> {
+        if self.is_paused() {
+            return vec![];
+        }
+
+        let mut stale: Vec<Stale> = self
+            .updates
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(name, updates)| {
+                let age = updates.last.elapsed();
+                (age > updates.stale_after?).then(|| Stale {
+                    name: name.clone(),
+                    age,
+                })
+            })
+            .collect();
+        stale.sort_by(|a, b| b.age.cmp(&a.age));
+        stale
+    }
+
+    /// Whether the widget called `name` is among [`BarState::stale`]
+    #[must_use]
+    pub fn is_stale(&self, name: &str) -> bool {
+        !self.is_paused()
+            && self
+                .updates
+                .lock()
+                .unwrap()
+                .get(name)
+                .is_some_and(|updates| {
+                    updates
+                        .stale_after
+                        .is_some_and(|stale_after| updates.last.elapsed() > stale_after)
+                })
+    }
+
+    /// The text last rendered by each [`Tracked`] widget, keyed by name
+    pub fn contents(&self) -> BTreeMap<String, String> {
+        self.contents.lock().unwrap().clone()
+    }
+
+    /// Records the data last collected by the widget called `name`, for
+    /// consumers other than the bar such as [`crate::export`]
+    pub fn set_value(&self, name: &str, value: &impl Serialize) {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.values.lock().unwrap().insert(name.to_string(), value);
+        }
+    }
+
+    /// The data last collected by each widget, keyed by name
+    pub fn values(&self) -> BTreeMap<String, serde_json::Value> {
+        self.values.lock().unwrap().clone()
+    }
+
+    /// Sets the text shown by the slot called `name`
+    pub fn set_slot(&self, name: &str, text: String) {
+        self.slot(name).send_replace(text);
+    }
+
+    pub fn subscribe_slot(&self, name: &str) -> watch::Receiver<String> {
+        self.slot(name).subscribe()
+    }
+
+    /// Registers `hook` to run before the bar exits, e.g. to flush state to
+    /// disk
+    pub fn on_shutdown(&self, hook: impl FnOnce() + Send + 'static) {
+        self.shutdown_hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    /// Runs the hooks registered with [`BarState::on_shutdown`]. Each hook
+    /// runs at most once
+    pub fn shutdown(&self) {
+        let hooks = std::mem::take(&mut *self.shutdown_hooks.lock().unwrap());
+        for hook in hooks {
+            hook();
+        }
+    }
+
+    fn slot(&self, name: &str) -> watch::Sender<String> {
+        self.slots
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| watch::channel(String::new()).0)
+            .clone()
+    }
+}
+
+/// A stream that yields every `period`, and whenever a refresh of all widgets
+/// is requested, except while the bar is paused
+pub fn ticks(period: Duration) -> impl Stream<Item = ()> {
+    ticks_while_paused(period).filter(|_| !bar().is_paused())
+}
+
+/// [`ticks`] which carry on while the bar is paused, for widgets whose
+/// readings trigger actions, such as battery alarms
+pub fn ticks_while_paused(period: Duration) -> impl Stream<Item = ()> {
+    let interval = IntervalStream::new(time::interval(period)).map(|_| ());
+    let refreshes = WatchStream::from_changes(bar().refresh_requests()).map(|_| ());
+
+    interval.merge(refreshes)
+}
+
+/// Wraps a widget so that the text it renders, and when, is recorded under
+/// `name` in the [`BarState`].
+///
+/// Given its interval, the widget is dimmed once it misses several, until it
+/// draws again. A collector that hangs or a stream that has ended would
+/// otherwise leave old data looking current
+pub struct Tracked {
+    name: String,
+    inner: Box<dyn Widget>,
+    interval: Option<Duration>,
+    stale_intervals: u32,
+}
+
+enum Event {
+    Update(Result<Vec<Text>>),
+    Check,
+}
+
+impl Tracked {
+    pub fn new(name: &str, widget: impl Widget + 'static) -> Tracked {
+        Tracked {
+            name: name.to_string(),
+            inner: Box::new(widget),
+            interval: None,
+            stale_intervals: STALE_INTERVALS,
+        }
+    }
+
+    /// Sets how often the widget is meant to draw, so that it counts as
+    /// stale once it stops, see [`BarState::stale`]
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Tracked {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Sets how many intervals the widget may miss before it counts as
+    /// stale, [`STALE_INTERVALS`] by default. `0` never counts it as stale
+    #[must_use]
+    pub fn with_stale_intervals(mut self, intervals: u32) -> Tracked {
+        self.stale_intervals = intervals;
+        self
+    }
+}
+
+/// `text` faded out, keeping its own markup
+fn dim(mut text: Text) -> Text {
+    let inner = if text.markup {
+        text.text
+    } else {
+        markup::escape(&text.text)
+    };
+    text.text = format!("<span alpha=\"{STALE_ALPHA}\">{inner}</span>");
+    text.markup = true;
+    text
+}
+
+impl Widget for Tracked {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Tracked {
+            name,
+            inner,
+            interval,
+            stale_intervals,
+        } = *self;
+
+        let updates = inner.into_stream()?.map(Event::Update);
+        let interval = interval.filter(|_| stale_intervals > 0);
+        let checks: Pin<Box<dyn Stream<Item = Event>>> = match interval {
+            Some(interval) => {
+                bar().expect_updates(&name, interval * stale_intervals);
+                Box::pin(IntervalStream::new(time::interval(interval)).map(|_| Event::Check))
+            }
+            None => Box::pin(tokio_stream::empty()),
+        };
+
+        // What was last drawn, to show it dimmed once stale
+        let mut latest = vec![];
+        let mut dimmed = false;
+
+        let stream = updates.merge(checks).filter_map(move |event| match event {
+            Event::Update(texts) => {
+                if let Ok(texts) = &texts {
+                    let content = texts.iter().map(|text| text.text.as_str()).collect();
+                    bar().set_content(&name, content);
+                    if interval.is_some() {
+                        latest.clone_from(texts);
+                    }
+                }
+                dimmed = false;
+                Some(texts)
+            }
+            Event::Check => {
+                if dimmed || latest.is_empty() || !bar().is_stale(&name) {
+                    return None;
+                }
+                dimmed = true;
+                Some(Ok(latest.iter().cloned().map(dim).collect()))
+            }
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
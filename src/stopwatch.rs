@@ -0,0 +1,249 @@
+//! Stopwatch driven through the `stopwatch` IPC command, as cnx can't take
+//! clicks: `stopwatch start`, `stop` and `toggle` run it, `stopwatch lap`
+//! records and prints a lap, and `stopwatch reset` clears it.
+//!
+//! The elapsed time is shown to the centisecond, which takes a redraw every
+//! centisecond, so the widget only redraws on a timer while it is running.
+
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::time::{self, Interval, MissedTickBehavior};
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::event::EventWidget;
+use crate::render::{Render, RenderContext};
+use crate::{icons, ipc, render, state};
+
+/// How often the widget redraws while running
+const RUNNING_INTERVAL: Duration = Duration::from_millis(10);
+
+// Abstracted type to represent the render closure
+type StopwatchRender = Box<dyn Render<StopwatchInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StopwatchInfo {
+    /// Seconds on the stopwatch
+    pub elapsed: f64,
+    pub running: bool,
+    /// Length of each lap in seconds, from the first
+    pub laps: Vec<f64>,
+}
+
+#[derive(Default)]
+struct Timer {
+    /// When the stopwatch was last started, while it is running
+    started: Option<Instant>,
+    /// Time on the stopwatch when it was last stopped
+    banked: Duration,
+    /// Time on the stopwatch at the end of each lap
+    splits: Vec<Duration>,
+}
+
+impl Timer {
+    fn elapsed(&self) -> Duration {
+        self.banked
+            + self
+                .started
+                .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    fn running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    fn start(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    fn stop(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.banked += started.elapsed();
+        }
+    }
+
+    /// Ends the current lap, returning its length
+    fn lap(&mut self) -> Duration {
+        let elapsed = self.elapsed();
+        let previous = self.splits.last().copied().unwrap_or_default();
+        self.splits.push(elapsed);
+        elapsed - previous
+    }
+
+    fn info(&self) -> StopwatchInfo {
+        let mut previous = Duration::ZERO;
+        let laps = self
+            .splits
+            .iter()
+            .map(|&split| {
+                let lap = split - previous;
+                previous = split;
+                lap.as_secs_f64()
+            })
+            .collect();
+
+        StopwatchInfo {
+            elapsed: self.elapsed().as_secs_f64(),
+            running: self.running(),
+            laps,
+        }
+    }
+}
+
+/// `duration` as minutes, seconds and centiseconds, with hours once there
+/// are any
+fn format_duration(duration: Duration) -> String {
+    let centis = duration.as_millis() / 10;
+    let (hours, minutes, seconds) = (centis / 360_000, centis / 6000 % 60, centis / 100 % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}.{:02}", centis % 100)
+    } else {
+        format!("{minutes:02}:{seconds:02}.{:02}", centis % 100)
+    }
+}
+
+/// Yields once for every change of the running state, given by `changes`,
+/// and every [`RUNNING_INTERVAL`] while it is running
+struct Ticks {
+    changes: WatchStream<bool>,
+    interval: Option<Interval>,
+}
+
+impl Stream for Ticks {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<()>> {
+        match Pin::new(&mut self.changes).poll_next(cx) {
+            Poll::Ready(Some(running)) => {
+                self.interval = running.then(|| {
+                    let mut interval = time::interval(RUNNING_INTERVAL);
+                    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                    interval
+                });
+                return Poll::Ready(Some(()));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        match &mut self.interval {
+            Some(interval) if interval.poll_tick(cx).is_ready() => Poll::Ready(Some(())),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+/// cnx widget that shows a stopwatch, hidden until it is first started, and
+/// registers the `stopwatch` IPC command
+pub struct Stopwatch {
+    attrs: Attributes,
+    render: Option<StopwatchRender>,
+}
+
+impl Stopwatch {
+    /// Creates a new [`Stopwatch`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<StopwatchRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    #[must_use]
+    pub fn new(attrs: Attributes, render: Option<StopwatchRender>) -> Stopwatch {
+        Stopwatch { attrs, render }
+    }
+}
+
+fn default_render(stopwatch_info: &StopwatchInfo) -> String {
+    if !stopwatch_info.running && stopwatch_info.elapsed == 0.0 {
+        return String::new();
+    }
+
+    let text = format!(
+        "{} {}",
+        icons::icon_markup(icons::icons().stopwatch),
+        format_duration(Duration::from_secs_f64(stopwatch_info.elapsed))
+    );
+    if stopwatch_info.running {
+        text
+    } else {
+        format!(
+            "<span foreground=\"{}\">{text}</span>",
+            render::theme().muted.to_hex()
+        )
+    }
+}
+
+impl Widget for Stopwatch {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Stopwatch { attrs, render } = *self;
+
+        let timer = Arc::new(Mutex::new(Timer::default()));
+        let (running, changes) = watch::channel(false);
+
+        let command_timer = Arc::clone(&timer);
+        ipc::register("stopwatch", move |args| {
+            let mut timer = command_timer.lock().unwrap();
+            let output = match args {
+                ["start"] => {
+                    timer.start();
+                    String::new()
+                }
+                ["stop"] => {
+                    timer.stop();
+                    format_duration(timer.elapsed())
+                }
+                ["toggle"] if timer.running() => {
+                    timer.stop();
+                    format_duration(timer.elapsed())
+                }
+                ["toggle"] => {
+                    timer.start();
+                    String::new()
+                }
+                ["lap"] => {
+                    let lap = timer.lap();
+                    format!("lap {}: {}", timer.splits.len(), format_duration(lap))
+                }
+                ["reset"] => {
+                    *timer = Timer::default();
+                    String::new()
+                }
+                _ => bail!("usage: stopwatch start|stop|toggle|lap|reset"),
+            };
+
+            running.send_replace(timer.running());
+            Ok(output)
+        });
+
+        let ticks = Ticks {
+            changes: WatchStream::new(changes),
+            interval: None,
+        };
+        let refreshes = WatchStream::from_changes(state::bar().refresh_requests()).map(|_| ());
+        let updates = ticks.merge(refreshes).map(move |()| {
+            let stopwatch_info = timer.lock().unwrap().info();
+            state::bar().set_value("stopwatch", &stopwatch_info);
+            stopwatch_info
+        });
+
+        let show = move |stopwatch_info: StopwatchInfo| match &render {
+            Some(render) => render.render(stopwatch_info, &RenderContext::current()),
+            None => default_render(&stopwatch_info),
+        };
+
+        Box::new(EventWidget::new(attrs, updates, show)).into_stream()
+    }
+}
@@ -0,0 +1,152 @@
+//! Supervisor mode, in which a parent process restarts the bar when it
+//! crashes so that a bug in one widget doesn't leave the desktop without a
+//! bar.
+//!
+//! `SIGTERM` and `SIGINT` are passed on to the bar, so it runs its shutdown
+//! hooks, and the supervisor exits once the bar has.
+
+use std::collections::VecDeque;
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::paths;
+
+/// Flag that starts the bar under supervision
+pub const FLAG: &str = "--supervise";
+
+/// State file the output leading up to the last crash is written to
+const CRASH_LOG: &str = "crash.log";
+
+/// Number of lines of output kept for the crash log
+const CRASH_LOG_LINES: usize = 200;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A bar which stays up this long is considered healthy, and the backoff is
+/// reset when it next crashes
+const HEALTHY_RUNTIME: Duration = Duration::from_secs(60);
+
+/// Pid of the running bar, 0 while it is being restarted
+static CHILD: AtomicU32 = AtomicU32::new(0);
+
+/// Set once the supervisor has been asked to stop
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+fn kill(pid: u32, signal: i32) {
+    // SAFETY: kill has no memory safety requirements
+    unsafe {
+        libc::kill(pid as libc::pid_t, signal);
+    }
+}
+
+/// Passes `SIGTERM` and `SIGINT` on to the bar from a background thread. The
+/// returned receiver gets a message for each, to cut a restart's backoff
+/// short
+fn forward_signals() -> Result<mpsc::Receiver<()>> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    let (stop, stopped) = mpsc::channel();
+
+    thread::spawn(move || {
+        for signal in &mut signals {
+            STOPPING.store(true, Ordering::SeqCst);
+            match CHILD.load(Ordering::SeqCst) {
+                0 => {}
+                pid => kill(pid, signal),
+            }
+            let _ = stop.send(());
+        }
+    });
+
+    Ok(stopped)
+}
+
+/// Runs the bar in a child process, restarting it with exponential backoff
+/// whenever it exits unsuccessfully. Returns once the bar exits cleanly, or
+/// exits after being passed on a `SIGTERM` or `SIGINT`
+pub fn run() -> Result<()> {
+    let executable = env::current_exe()?;
+    let args: Vec<String> = env::args().skip(1).filter(|arg| arg != FLAG).collect();
+    let mut backoff = INITIAL_BACKOFF;
+    let stopped = forward_signals()?;
+
+    while !STOPPING.load(Ordering::SeqCst) {
+        let started = Instant::now();
+        let mut child = Command::new(&executable)
+            .args(&args)
+            .env("RUST_BACKTRACE", "1")
+            .stderr(Stdio::piped())
+            .spawn()?;
+        CHILD.store(child.id(), Ordering::SeqCst);
+        // A signal which came before the pid was stored wasn't passed on
+        if STOPPING.load(Ordering::SeqCst) {
+            kill(child.id(), SIGTERM);
+        }
+
+        // Pass the bar's stderr through, remembering the tail of it so the
+        // panic message and backtrace can be saved if it crashes
+        let output = Arc::new(Mutex::new(VecDeque::with_capacity(CRASH_LOG_LINES)));
+        let stderr = child.stderr.take().map(|stderr| {
+            let output = Arc::clone(&output);
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(io::Result::ok) {
+                    let _ = writeln!(io::stderr(), "{line}");
+                    let mut output = output.lock().unwrap();
+                    if output.len() == CRASH_LOG_LINES {
+                        output.pop_front();
+                    }
+                    output.push_back(line);
+                }
+            })
+        });
+
+        let status = child.wait()?;
+        CHILD.store(0, Ordering::SeqCst);
+        if let Some(stderr) = stderr {
+            let _ = stderr.join();
+        }
+
+        if status.success() || STOPPING.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let output = output.lock().unwrap();
+        if let Err(e) = write_crash_log(status, output.iter()) {
+            eprintln!("Could not write crash log: {e}");
+        }
+
+        if started.elapsed() >= HEALTHY_RUNTIME {
+            backoff = INITIAL_BACKOFF;
+        }
+        eprintln!("Bar exited with {status}, restarting in {backoff:?}");
+        if stopped.recv_timeout(backoff).is_ok() {
+            return Ok(());
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+
+    Ok(())
+}
+
+fn write_crash_log<'a>(status: ExitStatus, output: impl Iterator<Item = &'a String>) -> Result<()> {
+    let mut log = format!(
+        "{} bar exited with {status}\n",
+        chrono::Local::now().to_rfc3339()
+    );
+    for line in output {
+        log += line;
+        log.push('\n');
+    }
+
+    paths::write_state(CRASH_LOG, &log)?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Refreshes the bar on resume from suspend, which logind announces with
+//! `PrepareForSleep(false)` on the system bus. Otherwise widgets would show
+//! data from before the suspend until their next interval.
+
+use std::thread;
+
+use anyhow::Result;
+use zbus::blocking::{Connection, Proxy};
+
+use crate::{dns, net, state};
+
+/// Starts listening for resume on a background thread
+pub fn watch_resume() -> Result<()> {
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    let signals = manager.receive_signal("PrepareForSleep")?;
+
+    thread::spawn(move || {
+        // Keep the connection alive for as long as signals are received
+        let _connection = connection;
+
+        for signal in signals {
+            let Ok(going_to_sleep) = signal.body().deserialize::<bool>() else {
+                continue;
+            };
+            if !going_to_sleep {
+                // Failures from before the suspend say nothing about the
+                // network the machine woke up on
+                net::reset_backoff();
+                dns::flush();
+                state::bar().request_refresh();
+            }
+        }
+    });
+
+    Ok(())
+}
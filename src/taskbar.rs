@@ -0,0 +1,192 @@
+//! Windows on the current workspace, read through EWMH.
+//!
+//! cnx doesn't deliver clicks to widgets, so windows are focused with the
+//! `taskbar` IPC command: `taskbar list` prints the windows shown, numbered
+//! from 1, and `taskbar focus <number>` focuses and raises one of them.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+
+use crate::ewmh::Ewmh;
+use crate::polling::PollingWidget;
+use crate::render::{Render, RenderContext};
+use crate::{icons, ipc, markup, render, state};
+
+/// Titles are cut down to this many characters by the default render
+const MAX_TITLE: usize = 24;
+
+// Abstracted type to represent the render closure
+type TaskbarRender = Box<dyn Render<TaskbarInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskbarWindow {
+    /// X window id
+    pub id: u32,
+    pub title: String,
+    /// Class from `WM_CLASS`, e.g. `firefox`
+    pub class: String,
+    pub focused: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskbarInfo {
+    /// Windows on the current workspace, from oldest to newest
+    pub windows: Vec<TaskbarWindow>,
+}
+
+/// Windows on the current desktop
+fn current_windows(ewmh: &Ewmh) -> Result<Vec<TaskbarWindow>> {
+    let desktop = ewmh.current_desktop()?;
+    let active = ewmh.active_window()?;
+
+    let mut windows = vec![];
+    for id in ewmh.windows()? {
+        // Errors here are windows closed since the list was read
+        let Ok(true) = ewmh.on_desktop(id, desktop) else {
+            continue;
+        };
+        windows.push(TaskbarWindow {
+            id,
+            title: ewmh.title(id).unwrap_or_default(),
+            class: ewmh.class(id).unwrap_or_default(),
+            focused: active == Some(id),
+        });
+    }
+
+    Ok(windows)
+}
+
+/// cnx widget that lists the windows on the current workspace, highlighting
+/// the focused one, and registers the `taskbar` IPC command
+pub struct Taskbar {
+    attrs: Attributes,
+    render: Option<TaskbarRender>,
+    update_interval: Duration,
+    class_icons: HashMap<String, String>,
+}
+
+impl Taskbar {
+    /// Creates a new [`Taskbar`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<TaskbarRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the windows are read
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<TaskbarRender>,
+        update_interval: Duration,
+    ) -> Taskbar {
+        Taskbar {
+            attrs,
+            render,
+            update_interval,
+            class_icons: HashMap::new(),
+        }
+    }
+
+    /// Shows `icon` before the titles of windows whose class is `class`,
+    /// ignoring case. Windows without an icon show their title only
+    #[must_use]
+    pub fn with_icon(mut self, class: &str, icon: &str) -> Self {
+        self.class_icons
+            .insert(class.to_lowercase(), icon.to_string());
+        self
+    }
+}
+
+fn default_render(taskbar_info: &TaskbarInfo, class_icons: &HashMap<String, String>) -> String {
+    let muted = render::theme().muted.to_hex();
+
+    taskbar_info
+        .windows
+        .iter()
+        .map(|window| {
+            let mut title: String = window.title.chars().take(MAX_TITLE).collect();
+            if title.len() < window.title.len() {
+                title.push('…');
+            }
+
+            let text = match class_icons.get(&window.class.to_lowercase()) {
+                Some(icon) => format!("{} {}", icons::icon_markup(icon), markup::escape(&title)),
+                None => markup::escape(&title),
+            };
+
+            if window.focused {
+                format!("<b>{text}</b>")
+            } else {
+                format!("<span foreground=\"{muted}\">{text}</span>")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+impl Widget for Taskbar {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Taskbar {
+            attrs,
+            render,
+            update_interval,
+            class_icons,
+        } = *self;
+
+        ipc::register("taskbar", |args| {
+            let ewmh = Ewmh::connect()?;
+            let windows = current_windows(&ewmh)?;
+            match args {
+                ["list"] => Ok(windows
+                    .iter()
+                    .enumerate()
+                    .map(|(index, window)| format!("{}: {}", index + 1, window.title))
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                ["focus", number] => {
+                    let window = number
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|number| windows.get(number.checked_sub(1)?))
+                        .ok_or_else(|| anyhow!("no window number {number}"))?;
+                    ewmh.activate(window.id)?;
+                    state::bar().request_refresh();
+                    Ok(String::new())
+                }
+                _ => bail!("usage: taskbar list|focus <number>"),
+            }
+        });
+
+        // Connects on first use, and again after an error
+        let mut ewmh = None;
+        let collect = move || -> Result<TaskbarInfo> {
+            let connection = match ewmh.take() {
+                Some(connection) => connection,
+                None => Ewmh::connect()?,
+            };
+            let windows = current_windows(&connection)?;
+            ewmh = Some(connection);
+            Ok(TaskbarInfo { windows })
+        };
+
+        let show = move |taskbar_info: TaskbarInfo| match &render {
+            Some(render) => render.render(taskbar_info, &RenderContext::current()),
+            None => default_render(&taskbar_info, &class_icons),
+        };
+
+        let widget =
+            PollingWidget::new(attrs, update_interval, collect, show).with_export("taskbar");
+        Box::new(widget).into_stream()
+    }
+}
@@ -0,0 +1,353 @@
+//! Render templates, an alternative to render closures which can be written
+//! in the config file.
+//!
+//! Text is copied as is, including any Pango markup, and `{name}` is replaced
+//! by the widget's field called `name`. Characters between the `{` and the
+//! name are only shown when the field has a value, so `{, time_left}` shows
+//! nothing while the battery isn't discharging. Filters follow the name,
+//! separated by `|`:
+//!
+//! - `color` colours a percentage from green at `0` to red at `100`
+//! - `color(>N)` or `color(<N)` colours the value with the theme's critical
+//!   colour while it is above or below `N`
+//! - `fixed(N)` shows a number with `N` decimal places
+//! - `upper` and `lower` change the case of text
+//!
+//! `{{` and `}}` stand for literal braces.
+//!
+//! ```toml
+//! [widgets.battery]
+//! template = "[{icon} {capacity|color(<20)}%{, time_left}]"
+//! ```
+
+use std::fmt::Write as _;
+
+use anyhow::{anyhow, bail, Result};
+use cnx::text::Color;
+use serde::Deserialize;
+
+use crate::render::{Render, RenderContext};
+use crate::{color, markup};
+
+/// A value substituted into a template
+pub enum Value {
+    Text(String),
+    Number(f64),
+    /// Pango markup shown as is, such as an icon in the icon font
+    Markup(String),
+}
+
+/// Data whose fields can be shown by a [`Template`]
+pub trait Fields {
+    /// The field called `name`, `None` if it doesn't exist or currently has
+    /// no value
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value>;
+}
+
+#[derive(Clone, Debug)]
+enum Filter {
+    Gradient,
+    /// Critical colour while the value is above (`true`) or below the limit
+    Threshold {
+        above: bool,
+        limit: f64,
+    },
+    Fixed(usize),
+    Upper,
+    Lower,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Field {
+        prefix: String,
+        name: String,
+        filters: Vec<Filter>,
+    },
+}
+
+/// A parsed render template, see the [module documentation](self)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Template> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => bail!("unclosed `{{` in template {template:?}"),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_field(&placeholder)?);
+                }
+                '}' => bail!("unmatched `}}` in template {template:?}, write `}}}}` for a brace"),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Template { parts })
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = anyhow::Error;
+
+    fn try_from(template: String) -> Result<Template> {
+        Template::parse(&template)
+    }
+}
+
+fn parse_field(placeholder: &str) -> Result<Part> {
+    let mut sections = placeholder.split('|');
+    let field = sections.next().unwrap_or_default();
+
+    let start = field
+        .find(|c: char| c.is_alphanumeric() || c == '_')
+        .ok_or_else(|| anyhow!("no field name in `{{{placeholder}}}`"))?;
+    let (prefix, name) = field.split_at(start);
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        bail!("invalid field name `{name}`");
+    }
+
+    let filters = sections
+        .map(|filter| parse_filter(filter.trim()))
+        .collect::<Result<_>>()?;
+
+    Ok(Part::Field {
+        prefix: prefix.to_string(),
+        name: name.to_string(),
+        filters,
+    })
+}
+
+fn parse_filter(filter: &str) -> Result<Filter> {
+    let (name, argument) = match filter.split_once('(') {
+        Some((name, rest)) => {
+            let argument = rest
+                .strip_suffix(')')
+                .ok_or_else(|| anyhow!("unclosed `(` in filter `{filter}`"))?;
+            (name, Some(argument.trim()))
+        }
+        None => (filter, None),
+    };
+
+    Ok(match (name, argument) {
+        ("color", None) => Filter::Gradient,
+        ("color", Some(argument)) => {
+            let (above, limit) = match argument.strip_prefix('<') {
+                Some(limit) => (false, limit),
+                None => (true, argument.strip_prefix('>').unwrap_or(argument)),
+            };
+            Filter::Threshold {
+                above,
+                limit: limit.trim().parse()?,
+            }
+        }
+        ("fixed", Some(places)) => Filter::Fixed(places.parse()?),
+        ("upper", None) => Filter::Upper,
+        ("lower", None) => Filter::Lower,
+        _ => bail!("unknown filter `{filter}`"),
+    })
+}
+
+/// Applies `filters` to `value`, appending the markup to show to `output`
+fn apply(output: &mut String, value: Value, filters: &[Filter], context: &RenderContext) {
+    let (mut text, number) = match value {
+        Value::Text(text) => (text, None),
+        Value::Number(number) => (String::new(), Some(number)),
+        Value::Markup(value) => {
+            output.push_str(&value);
+            return;
+        }
+    };
+    let mut places = None;
+    let mut colour: Option<Color> = None;
+
+    for filter in filters {
+        match (filter, number) {
+            (Filter::Gradient, Some(number)) => colour = Some(color::gradient(number)),
+            (Filter::Threshold { above, limit }, Some(number)) => {
+                if (*above && number > *limit) || (!*above && number < *limit) {
+                    colour = Some(context.theme.critical.clone());
+                }
+            }
+            (Filter::Fixed(fixed), Some(_)) => places = Some(*fixed),
+            (Filter::Upper, None) => text = text.to_uppercase(),
+            (Filter::Lower, None) => text = text.to_lowercase(),
+            // Number filters leave text alone and vice versa
+            _ => {}
+        }
+    }
+
+    if let Some(colour) = &colour {
+        let _ = write!(output, "<span foreground=\"{}\">", colour.to_hex());
+    }
+    match (number, places) {
+        (Some(number), Some(places)) => {
+            let _ = write!(output, "{number:.places$}");
+        }
+        (Some(number), None) => {
+            let _ = write!(output, "{number}");
+        }
+        (None, _) => markup::escape_into(output, &text),
+    }
+    if colour.is_some() {
+        output.push_str("</span>");
+    }
+}
+
+impl<T: Fields> Render<T> for Template {
+    fn render(&self, data: T, context: &RenderContext) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => output.push_str(literal),
+                Part::Field {
+                    prefix,
+                    name,
+                    filters,
+                } => match data.field(name, context) {
+                    Some(Value::Text(text)) if text.is_empty() => {}
+                    Some(value) => {
+                        output.push_str(prefix);
+                        apply(&mut output, value, filters, context);
+                    }
+                    None => {}
+                },
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::icons::{self, IconSet};
+    use crate::render::Theme;
+
+    struct Battery {
+        capacity: f64,
+        status: &'static str,
+        time_left: Option<&'static str>,
+    }
+
+    impl Fields for Battery {
+        fn field(&self, name: &str, _context: &RenderContext) -> Option<Value> {
+            match name {
+                "capacity" => Some(Value::Number(self.capacity)),
+                "status" => Some(Value::Text(self.status.to_string())),
+                "time_left" => Some(Value::Text(self.time_left?.to_string())),
+                "icon" => Some(Value::Markup("<b>B</b>".to_string())),
+                _ => None,
+            }
+        }
+    }
+
+    const BATTERY: Battery = Battery {
+        capacity: 42.0,
+        status: "Discharging",
+        time_left: None,
+    };
+
+    fn render(template: &str, battery: Battery) -> String {
+        let context = RenderContext {
+            theme: Arc::new(Theme::default()),
+            icons: icons::icons_of(IconSet::Ascii),
+        };
+        Template::parse(template).unwrap().render(battery, &context)
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        assert_eq!(render("{status} {capacity}%", BATTERY), "Discharging 42%");
+    }
+
+    #[test]
+    fn prefix_is_only_shown_with_a_value() {
+        assert_eq!(render("{capacity}%{, time_left}", BATTERY), "42%");
+
+        let discharging = Battery {
+            time_left: Some("1h 5m"),
+            ..BATTERY
+        };
+        assert_eq!(
+            render("{capacity}%{, time_left}", discharging),
+            "42%, 1h 5m"
+        );
+    }
+
+    #[test]
+    fn filters_apply_to_matching_values() {
+        assert_eq!(render("{capacity|fixed(1)}", BATTERY), "42.0");
+        assert_eq!(render("{status|upper}", BATTERY), "DISCHARGING");
+        // Text filters leave numbers alone
+        assert_eq!(render("{capacity|upper}", BATTERY), "42");
+
+        let critical = Theme::default().critical.to_hex();
+        assert_eq!(
+            render("{capacity|color(<50)}", BATTERY),
+            format!("<span foreground=\"{critical}\">42</span>")
+        );
+        assert_eq!(render("{capacity|color(>50)}", BATTERY), "42");
+    }
+
+    #[test]
+    fn text_is_escaped_and_markup_is_not() {
+        let odd = Battery {
+            status: "<Full & idle>",
+            ..BATTERY
+        };
+        assert_eq!(render("{status}", odd), "&lt;Full &amp; idle&gt;");
+        assert_eq!(render("{icon}", BATTERY), "<b>B</b>");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{capacity}} }}", BATTERY), "{capacity} }");
+        assert_eq!(render("{{{capacity}}}", BATTERY), "{42}");
+    }
+
+    #[test]
+    fn unknown_fields_render_nothing() {
+        assert_eq!(render("[{nothing}{, nor_this}]", BATTERY), "[]");
+    }
+
+    #[test]
+    fn malformed_templates_are_errors() {
+        assert!(Template::parse("{capacity").is_err());
+        assert!(Template::parse("capacity}").is_err());
+        assert!(Template::parse("{}").is_err());
+        assert!(Template::parse("{capacity|bold}").is_err());
+        assert!(Template::parse("{capacity|fixed(1}").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Urgency flags and the [`Blink`] wrapper which animates urgent widgets.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::Result;
+use cnx::text::{Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio::time;
+use tokio_stream::wrappers::IntervalStream;
+use tokio_stream::StreamExt;
+
+use crate::state;
+
+/// Shared flag marking a widget's output as urgent. Clones share the same
+/// flag, so a clone can be moved into a render closure and set from there
+#[derive(Clone, Default)]
+pub struct Urgency(Rc<Cell<bool>>);
+
+impl Urgency {
+    #[must_use]
+    pub fn new() -> Urgency {
+        Urgency::default()
+    }
+
+    pub fn set(&self, urgent: bool) {
+        self.0.set(urgent);
+    }
+
+    #[must_use]
+    pub fn is_urgent(&self) -> bool {
+        self.0.get()
+    }
+}
+
+enum Event {
+    Update(Result<Vec<Text>>),
+    Blink,
+}
+
+/// Wraps a widget so that its background blinks while its [`Urgency`] is set
+pub struct Blink {
+    inner: Box<dyn Widget>,
+    urgency: Urgency,
+    rate: Duration,
+    colour: Color,
+}
+
+impl Blink {
+    /// Creates a new [`Blink`] widget
+    ///
+    /// Arguments
+    ///
+    /// `widget`: [`Widget`] - The widget to animate
+    ///
+    /// `urgency`: [`Urgency`] - Flag which turns blinking on and off
+    ///
+    /// `rate`: [`Duration`] - How long the background stays on or off
+    ///
+    /// `colour`: [`Color`] - Background colour while lit
+    pub fn new(
+        widget: impl Widget + 'static,
+        urgency: Urgency,
+        rate: Duration,
+        colour: Color,
+    ) -> Blink {
+        Blink {
+            inner: Box::new(widget),
+            urgency,
+            rate,
+            colour,
+        }
+    }
+}
+
+impl Widget for Blink {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Blink {
+            inner,
+            urgency,
+            rate,
+            colour,
+        } = *self;
+
+        let updates = inner.into_stream()?.map(Event::Update);
+        // Extra ticks between data updates drive the animation
+        let blinks = IntervalStream::new(time::interval(rate)).map(|_| Event::Blink);
+
+        let mut latest = vec![];
+        let mut lit = false;
+
+        let stream = updates.merge(blinks).filter_map(move |event| {
+            match event {
+                Event::Update(Ok(texts)) => latest = texts,
+                Event::Update(Err(e)) => return Some(Err(e)),
+                // Blink ticks only cause a redraw while urgent, or to turn
+                // the background back off once no longer urgent
+                // Nothing is drawn while the bar is paused
+                Event::Blink if state::bar().is_paused() => return None,
+                Event::Blink if urgency.is_urgent() => lit = !lit,
+                Event::Blink if lit => lit = false,
+                Event::Blink => return None,
+            }
+
+            if !urgency.is_urgent() {
+                lit = false;
+            }
+
+            let texts = latest
+                .iter()
+                .cloned()
+                .map(|mut text| {
+                    if lit {
+                        text.attr.bg_color = Some(colour.clone());
+                    }
+                    text
+                })
+                .collect();
+
+            Some(Ok(texts))
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,273 @@
+//! Volume of the default audio output through `pactl`, which works with both
+//! PulseAudio and PipeWire.
+//!
+//! The bar can't take clicks or scrolling itself, so the widget registers the
+//! `volume` IPC command for the window manager's mouse bindings to call:
+//! `volume up` and `volume down` change the volume by the widget's step, and
+//! `volume mute` toggles mute.
+//!
+//! The volume is read again when `pactl subscribe` reports a change to a sink
+//! or the server, so neither reading nor waiting happens on the bar's thread.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use cnx::text::Attributes;
+use cnx::widgets::{Widget, WidgetStream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc as tokio_mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::audio_output::pactl;
+use crate::event::EventWidget;
+use crate::progress::ProgressBar;
+use crate::render::{Render, RenderContext};
+use crate::template::{Fields, Value};
+use crate::{icons, ipc, state};
+
+/// Sink `pactl` resolves to the current default output
+const DEFAULT_SINK: &str = "@DEFAULT_SINK@";
+
+/// Percentage `volume up` and `volume down` change the volume by
+const DEFAULT_STEP: u32 = 5;
+
+/// Volume isn't raised beyond this percentage, where it would distort
+const MAX_VOLUME: u32 = 100;
+
+/// How long to wait before running `pactl subscribe` again after it exits,
+/// e.g. while the sound server restarts
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Dragging a volume slider reports a change for every step
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Drawn by the `bar` template field
+const BAR: ProgressBar = ProgressBar::blocks(8);
+
+// Abstracted type to represent the render closure
+type VolumeRender = Box<dyn Render<VolumeInfo>>;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VolumeInfo {
+    /// Percentage of the default output's first channel
+    pub volume: u32,
+    pub muted: bool,
+}
+
+impl Fields for VolumeInfo {
+    fn field(&self, name: &str, context: &RenderContext) -> Option<Value> {
+        match name {
+            "icon" => Some(Value::Markup(context.icon(volume_icon(self)))),
+            "volume" => Some(Value::Number(f64::from(self.volume))),
+            "muted" => Some(Value::Number(f64::from(u8::from(self.muted)))),
+            "bar" => Some(Value::Text(BAR.render(if self.muted {
+                0.0
+            } else {
+                f64::from(self.volume)
+            }))),
+            _ => None,
+        }
+    }
+}
+
+/// cnx widget that shows the volume of the default audio output and
+/// registers the `volume` IPC command for changing it
+pub struct Volume {
+    attrs: Attributes,
+    render: Option<VolumeRender>,
+    update_interval: Duration,
+    step: u32,
+}
+
+impl Volume {
+    /// Creates a new [`Volume`] widget
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<VolumeRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the volume is read when
+    /// `pactl subscribe` reports no change
+    #[must_use]
+    pub fn new(
+        attrs: Attributes,
+        render: Option<VolumeRender>,
+        update_interval: Duration,
+    ) -> Volume {
+        Volume {
+            attrs,
+            render,
+            update_interval,
+            step: DEFAULT_STEP,
+        }
+    }
+
+    /// Sets the percentage `volume up` and `volume down` change the volume by
+    #[must_use]
+    pub fn with_step(mut self, step: u32) -> Self {
+        self.step = step;
+        self
+    }
+}
+
+/// Reads the volume and mute state of the default output
+pub fn read() -> Result<VolumeInfo> {
+    // e.g. "Volume: front-left: 39321 /  60% / -13.31 dB,   front-right: ..."
+    let volume = pactl(&["get-sink-volume", DEFAULT_SINK])?;
+    let volume = volume
+        .split('/')
+        .nth(1)
+        .and_then(|percentage| percentage.trim().trim_end_matches('%').parse().ok())
+        .ok_or_else(|| anyhow!("unexpected pactl output: {volume}"))?;
+
+    let muted = pactl(&["get-sink-mute", DEFAULT_SINK])?.trim() == "Mute: yes";
+
+    Ok(VolumeInfo { volume, muted })
+}
+
+/// Sends to `changes` for every event on a sink or the server, which covers
+/// volume, mute and a new default output, until `pactl subscribe` exits.
+/// Returns `false` once nobody is listening to `changes` any more
+fn subscribe(changes: &mpsc::Sender<()>) -> Result<bool> {
+    let mut child = Command::new("pactl")
+        .arg("subscribe")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child.stdout.take().context("pactl has no stdout")?;
+
+    let mut listening = true;
+    // e.g. "Event 'change' on sink #54"
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        if !(line.contains(" on sink ") || line.contains(" on server")) {
+            continue;
+        }
+        if changes.send(()).is_err() {
+            listening = false;
+            let _ = child.kill();
+            break;
+        }
+    }
+
+    child.wait()?;
+    Ok(listening)
+}
+
+/// Changes the volume by `change` percent, within `0` and [`MAX_VOLUME`]
+fn change_volume(change: i64) -> Result<()> {
+    let volume = i64::from(read()?.volume);
+    let volume = (volume + change).clamp(0, i64::from(MAX_VOLUME));
+    pactl(&["set-sink-volume", DEFAULT_SINK, &format!("{volume}%")])?;
+    Ok(())
+}
+
+fn volume_icon(volume_info: &VolumeInfo) -> &'static str {
+    if volume_info.muted {
+        icons::icons().muted
+    } else {
+        icons::icons().volume
+    }
+}
+
+fn default_render(volume_info: &VolumeInfo) -> String {
+    let icon = icons::icon_markup(volume_icon(volume_info));
+    if volume_info.muted {
+        return icon;
+    }
+
+    format!("{icon} {}%", volume_info.volume)
+}
+
+impl Widget for Volume {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let Volume {
+            attrs,
+            render,
+            update_interval,
+            step,
+        } = *self;
+
+        ipc::register("volume", move |args| {
+            match args {
+                ["up"] => change_volume(i64::from(step))?,
+                ["down"] => change_volume(-i64::from(step))?,
+                ["mute"] => {
+                    pactl(&["set-sink-mute", DEFAULT_SINK, "toggle"])?;
+                }
+                _ => bail!("usage: volume up|down|mute"),
+            }
+            state::bar().request_refresh();
+            Ok(String::new())
+        });
+
+        let (changes, changed) = mpsc::channel();
+        thread::spawn(move || {
+            while subscribe(&changes).unwrap_or(true) {
+                thread::sleep(RESUBSCRIBE_DELAY);
+            }
+        });
+
+        let (sender, receiver) = tokio_mpsc::unbounded_channel();
+        thread::spawn(move || loop {
+            if let Ok(volume_info) = read() {
+                state::bar().set_value("volume", &volume_info);
+                if sender.send(volume_info).is_err() {
+                    break;
+                }
+            }
+
+            match changed.recv_timeout(update_interval) {
+                // A burst of changes only needs one read
+                Ok(()) => while changed.try_recv().is_ok() {},
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => thread::sleep(update_interval),
+            }
+        });
+
+        let widget = EventWidget::new(
+            attrs,
+            UnboundedReceiverStream::new(receiver),
+            move |volume_info: VolumeInfo| match &render {
+                Some(render) => render.render(volume_info, &RenderContext::current()),
+                None => default_render(&volume_info),
+            },
+        )
+        .with_debounce(DEBOUNCE);
+
+        Box::new(widget).into_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::icons::IconSet;
+    use crate::render::Theme;
+
+    fn bar(volume: u32, muted: bool) -> Option<Value> {
+        let context = RenderContext {
+            theme: Arc::new(Theme::default()),
+            icons: icons::icons_of(IconSet::Ascii),
+        };
+        VolumeInfo { volume, muted }.field("bar", &context)
+    }
+
+    #[test]
+    fn bar_follows_the_volume() {
+        assert!(matches!(bar(50, false), Some(Value::Text(bar)) if bar == "████░░░░"));
+        assert!(matches!(bar(50, true), Some(Value::Text(bar)) if bar == "░░░░░░░░"));
+    }
+}
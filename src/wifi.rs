@@ -0,0 +1,288 @@
+//! Wi-Fi status and network picker through NetworkManager's `nmcli`.
+//!
+//! Networks are listed and chosen through IPC commands, which suits feeding
+//! a launcher such as rofi: `wifi list` prints the visible networks one per
+//! line, and `wifi connect <ssid>` connects to a saved network, with its
+//! progress shown by the [`Wifi`] widget.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use cnx::text::{Attributes, Color, Text};
+use cnx::widgets::{Widget, WidgetStream};
+use tokio_stream::StreamExt;
+
+use crate::cache::Cache;
+use crate::render::{Render, RenderContext};
+use crate::{color, ipc, markup, state};
+
+/// Least time between reads of the access points, which NetworkManager
+/// only updates as often as it scans anyway
+const SCAN_REFRESH: Duration = Duration::from_secs(30);
+
+// Abstracted type to represent the render closure
+type WifiRender = Box<dyn Render<WifiInfo>>;
+
+/// A network visible to the Wi-Fi adapter
+#[derive(Clone, Debug)]
+pub struct AccessPoint {
+    pub ssid: String,
+    /// Signal strength in percent
+    pub signal: u8,
+    pub secured: bool,
+    pub in_use: bool,
+}
+
+/// State of the last connection requested through `wifi connect`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionProgress {
+    #[default]
+    Idle,
+    Connecting(String),
+    Failed(String),
+}
+
+pub struct WifiInfo {
+    /// The network currently connected to, if any
+    pub current: Option<AccessPoint>,
+    pub progress: ConnectionProgress,
+}
+
+/// cnx widget that shows the connected Wi-Fi network and the progress of
+/// connections made through the `wifi` IPC command
+pub struct Wifi {
+    attrs: Attributes,
+    render: Option<WifiRender>,
+    update_interval: Duration,
+    progress: Arc<Mutex<ConnectionProgress>>,
+    /// The network currently connected to
+    current: Arc<Cache<Option<AccessPoint>>>,
+}
+
+impl Wifi {
+    /// Creates a new [`Wifi`] widget and registers the `wifi` IPC command
+    ///
+    /// Arguments
+    ///
+    /// `attrs`: [`Attributes`] - Widget attributes which control font,
+    /// foreground and background colour.
+    ///
+    /// `render`: [`Option<WifiRender>`] - Optional
+    /// parameter to customise the way the widget is rendered. Takes a
+    /// closure that returns a String, given the widget's data and a
+    /// [`RenderContext`]
+    ///
+    /// `update_interval`: [`Duration`] - How often the connection is checked.
+    /// Access points are read at most every 30s, and after `wifi connect`
+    #[must_use]
+    pub fn new(attrs: Attributes, render: Option<WifiRender>, update_interval: Duration) -> Wifi {
+        let progress = Arc::new(Mutex::new(ConnectionProgress::Idle));
+        let refresh_after = update_interval.max(SCAN_REFRESH);
+        let current = Arc::new(Cache::new(refresh_after, refresh_after * 3));
+
+        let command_current = Arc::clone(&current);
+        let command_progress = Arc::clone(&progress);
+        ipc::register("wifi", move |args| match args {
+            ["list"] => Ok(access_points()?
+                .iter()
+                .map(|access_point| {
+                    format!(
+                        "{}{} {}%{}",
+                        if access_point.in_use { "* " } else { "" },
+                        access_point.ssid,
+                        access_point.signal,
+                        if access_point.secured { " 🔒" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ["connect", ssid @ ..] if !ssid.is_empty() => {
+                let ssid = ssid.join(" ");
+                let Some(uuid) = saved_networks()?.remove(&ssid) else {
+                    bail!("{ssid} is not a saved network");
+                };
+
+                *command_progress.lock().unwrap() = ConnectionProgress::Connecting(ssid.clone());
+                state::bar().request_refresh();
+
+                // Connecting can take a while, so report back through the
+                // widget rather than holding the client
+                let progress = Arc::clone(&command_progress);
+                let current = Arc::clone(&command_current);
+                thread::spawn(move || {
+                    let result = Command::new("nmcli")
+                        .args(["connection", "up", "uuid", &uuid])
+                        .output();
+                    *progress.lock().unwrap() = match result {
+                        Ok(output) if output.status.success() => ConnectionProgress::Idle,
+                        _ => ConnectionProgress::Failed(ssid),
+                    };
+                    current.invalidate();
+                    state::bar().request_refresh();
+                });
+
+                Ok(String::new())
+            }
+            _ => bail!("usage: wifi list|connect <ssid>"),
+        });
+
+        Wifi {
+            attrs,
+            render,
+            update_interval,
+            progress,
+            current,
+        }
+    }
+
+    fn tick(&self) -> Vec<Text> {
+        let current = self.current.get(|| {
+            Ok(access_points()?
+                .into_iter()
+                .find(|access_point| access_point.in_use))
+        });
+        let wifi_info = WifiInfo {
+            current: current.and_then(|cached| cached.value),
+            progress: self.progress.lock().unwrap().clone(),
+        };
+
+        let text = if let Some(render) = &self.render {
+            render.render(wifi_info, &RenderContext::current())
+        } else {
+            match (&wifi_info.progress, &wifi_info.current) {
+                (ConnectionProgress::Connecting(ssid), _) => {
+                    format!("{}…", markup::escape(ssid))
+                }
+                (ConnectionProgress::Failed(ssid), _) => format!(
+                    "<span foreground=\"{}\">{} failed</span>",
+                    Color::red().to_hex(),
+                    markup::escape(ssid)
+                ),
+                (ConnectionProgress::Idle, Some(current)) => format!(
+                    "{} <span foreground=\"{}\">{}%</span>",
+                    markup::escape(&current.ssid),
+                    color::gradient(100.0 - f64::from(current.signal)).to_hex(),
+                    current.signal
+                ),
+                (ConnectionProgress::Idle, None) => "no wifi".to_string(),
+            }
+        };
+
+        vec![Text {
+            attr: self.attrs.clone(),
+            text,
+            stretch: false,
+            markup: true,
+        }]
+    }
+}
+
+impl Widget for Wifi {
+    fn into_stream(self: Box<Self>) -> Result<WidgetStream> {
+        let stream = state::ticks(self.update_interval)
+            .merge(self.current.updates())
+            .map(move |_| Ok(self.tick()));
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Visible networks from the adapter's last scan, the connected one first
+/// and then strongest first
+pub fn access_points() -> Result<Vec<AccessPoint>> {
+    let output = Command::new("nmcli")
+        .args([
+            "--terse",
+            "--fields",
+            "IN-USE,SSID,SIGNAL,SECURITY",
+            "device",
+            "wifi",
+            "list",
+            "--rescan",
+            "no",
+        ])
+        .output()?;
+    if !output.status.success() {
+        bail!("nmcli failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let mut access_points: Vec<AccessPoint> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields = split_terse(line);
+            let [in_use, ssid, signal, security] = fields.as_slice() else {
+                return None;
+            };
+            // Hidden networks have no SSID to connect by
+            if ssid.is_empty() {
+                return None;
+            }
+
+            Some(AccessPoint {
+                ssid: ssid.clone(),
+                signal: signal.parse().unwrap_or(0),
+                secured: !security.is_empty(),
+                in_use: in_use == "*",
+            })
+        })
+        .collect();
+    // Each access point of a network is listed separately
+    access_points.sort_by_key(|access_point| (!access_point.in_use, Reverse(access_point.signal)));
+    let mut seen = HashSet::new();
+    access_points.retain(|access_point| seen.insert(access_point.ssid.clone()));
+
+    Ok(access_points)
+}
+
+/// UUIDs of the saved Wi-Fi connections, keyed by SSID. A connection's name
+/// is only its SSID until the user renames it, so the SSID is read from its
+/// settings
+fn saved_networks() -> Result<HashMap<String, String>> {
+    let output = Command::new("nmcli")
+        .args(["--terse", "--fields", "UUID,TYPE", "connection", "show"])
+        .output()?;
+
+    let mut networks = HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let [uuid, kind] = split_terse(line).try_into().unwrap_or_default();
+        if kind != "802-11-wireless" {
+            continue;
+        }
+
+        let ssid = Command::new("nmcli")
+            .args([
+                "--get-values",
+                "802-11-wireless.ssid",
+                "connection",
+                "show",
+                "uuid",
+                &uuid,
+            ])
+            .output()?;
+        let ssid = String::from_utf8_lossy(&ssid.stdout).trim_end().to_string();
+        if !ssid.is_empty() {
+            networks.insert(ssid, uuid);
+        }
+    }
+    Ok(networks)
+}
+
+/// Splits a line of `nmcli --terse` output, in which `:` separates fields and
+/// literal colons and backslashes are escaped with a backslash
+fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut().unwrap().extend(chars.next()),
+            ':' => fields.push(String::new()),
+            _ => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
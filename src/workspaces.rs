@@ -0,0 +1,55 @@
+//! Switching workspaces through EWMH, for the pager.
+//!
+//! cnx doesn't deliver clicks or scrolling to widgets, so switching is done
+//! with the `workspace` IPC command, for the window manager's mouse bindings
+//! over the bar to call: `workspace next` and `workspace prev` step through
+//! the workspaces, wrapping around, and `workspace <name>` focuses one by
+//! name, or by its 1-based position if no workspace has that name.
+
+use anyhow::{bail, Result};
+
+use crate::ewmh::Ewmh;
+use crate::ipc;
+
+/// Switches `step` desktops forwards or backwards, wrapping around
+fn step(ewmh: &Ewmh, step: i64) -> Result<()> {
+    let count = i64::from(ewmh.desktop_count()?);
+    if count == 0 {
+        bail!("there are no workspaces");
+    }
+
+    let index = (i64::from(ewmh.current_desktop()?) + step).rem_euclid(count);
+    ewmh.switch_desktop(u32::try_from(index)?)
+}
+
+/// Switches to the desktop called `name`, or at the 1-based position `name`
+fn focus(ewmh: &Ewmh, name: &str) -> Result<()> {
+    if let Some(index) = ewmh
+        .desktop_names()?
+        .iter()
+        .position(|desktop| desktop == name)
+    {
+        return ewmh.switch_desktop(u32::try_from(index)?);
+    }
+
+    match name.parse::<u32>() {
+        Ok(position) if (1..=ewmh.desktop_count()?).contains(&position) => {
+            ewmh.switch_desktop(position - 1)
+        }
+        _ => bail!("no workspace called {name}"),
+    }
+}
+
+/// Registers the `workspace` IPC command
+pub fn register_commands() {
+    ipc::register("workspace", |args| {
+        let ewmh = Ewmh::connect()?;
+        match args {
+            ["next"] => step(&ewmh, 1)?,
+            ["prev"] => step(&ewmh, -1)?,
+            [name] => focus(&ewmh, name)?,
+            _ => bail!("usage: workspace next|prev|<name>"),
+        }
+        Ok(String::new())
+    });
+}